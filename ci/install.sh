@@ -1,6 +1,12 @@
 set -ex
 
 main() {
+    if [ ! -z $MIRI ]; then
+        rustup component add miri rust-src
+        cargo miri setup --target $TARGET
+        return
+    fi
+
     local target=
     if [ $TRAVIS_OS_NAME = linux ]; then
         target=x86_64-unknown-linux-musl
@@ -2,7 +2,20 @@
 
 set -ex
 
+# The compressed format is little-endian on every target. Miri checks the
+# scalar implementations on a big-endian target: the byte-level layout tests,
+# and the test suites of the scalar modules. The whole test suite is too slow
+# under Miri.
+miri() {
+    cargo miri test --target $TARGET --lib -- tests_unit scalar::tests
+}
+
 main() {
+    if [ ! -z $MIRI ]; then
+        miri
+        return
+    fi
+
     cross build --target $TARGET
     cross build --target $TARGET --release
 
@@ -12,6 +25,8 @@ main() {
 
     cross test --target $TARGET
     cross test --target $TARGET --release
+    cross test --target $TARGET --features bytes
+    cross test --target $TARGET --no-default-features --features bitpacker4x
 }
 
 # we don't run the "test phase" when doing deploys
@@ -0,0 +1,31 @@
+//! Decompresses arbitrary bytes with an arbitrary number of bits, in every layout.
+//!
+//! Decompressing must never panic, and the blocks that are accepted must compress
+//! back into the same bytes.
+
+#![no_main]
+
+use bitpacking::{BitPacker, BitPacker1x, BitPacker4x, BitPacker8x, BitPackerFastLanes};
+use libfuzzer_sys::fuzz_target;
+
+fn check<TBitPacker: BitPacker>(num_bits: u8, compressed: &[u8]) {
+    let bitpacker = TBitPacker::new();
+    let mut decompressed = vec![0u32; TBitPacker::BLOCK_LEN];
+    if let Ok(len) = bitpacker.decompress_checked(compressed, &mut decompressed, num_bits) {
+        let mut recompressed = vec![0u8; len];
+        bitpacker.compress(&decompressed, &mut recompressed, num_bits);
+        assert_eq!(&compressed[..len], &recompressed[..]);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let [layout, num_bits, compressed @ ..] = data else {
+        return;
+    };
+    match layout % 4 {
+        0 => check::<BitPacker1x>(*num_bits, compressed),
+        1 => check::<BitPacker4x>(*num_bits, compressed),
+        2 => check::<BitPacker8x>(*num_bits, compressed),
+        _ => check::<BitPackerFastLanes>(*num_bits, compressed),
+    }
+});
@@ -0,0 +1,62 @@
+//! Decompresses arbitrary bytes as sorted and strictly sorted blocks, in every layout.
+//!
+//! Decompressing must never panic, the blocks that are accepted must be sorted, and
+//! they must compress back into the same bytes.
+
+#![no_main]
+
+use bitpacking::{BitPacker, BitPacker1x, BitPacker4x, BitPacker8x, BitPackerFastLanes};
+use libfuzzer_sys::fuzz_target;
+
+fn check<TBitPacker: BitPacker>(
+    strict: bool,
+    initial: Option<u32>,
+    num_bits: u8,
+    compressed: &[u8],
+) {
+    let bitpacker = TBitPacker::new();
+    let mut decompressed = vec![0u32; TBitPacker::BLOCK_LEN];
+    let mut recompressed = vec![0u8; compressed.len()];
+    if strict {
+        let result = bitpacker.decompress_strictly_sorted_checked(
+            initial,
+            compressed,
+            &mut decompressed,
+            num_bits,
+        );
+        if let Ok(len) = result {
+            assert!(decompressed.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(initial.is_none_or(|initial| initial < decompressed[0]));
+            bitpacker.compress_strictly_sorted(initial, &decompressed, &mut recompressed, num_bits);
+            assert_eq!(&compressed[..len], &recompressed[..len]);
+        }
+    } else {
+        let initial = initial.unwrap_or(0);
+        let result =
+            bitpacker.decompress_sorted_checked(initial, compressed, &mut decompressed, num_bits);
+        if let Ok(len) = result {
+            assert!(decompressed.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!(initial <= decompressed[0]);
+            bitpacker.compress_sorted(initial, &decompressed, &mut recompressed, num_bits);
+            assert_eq!(&compressed[..len], &recompressed[..len]);
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let [flags, num_bits, i0, i1, i2, i3, compressed @ ..] = data else {
+        return;
+    };
+    let strict = flags & 4 != 0;
+    let initial = if flags & 8 != 0 {
+        Some(u32::from_le_bytes([*i0, *i1, *i2, *i3]))
+    } else {
+        None
+    };
+    match flags % 4 {
+        0 => check::<BitPacker1x>(strict, initial, *num_bits, compressed),
+        1 => check::<BitPacker4x>(strict, initial, *num_bits, compressed),
+        2 => check::<BitPacker8x>(strict, initial, *num_bits, compressed),
+        _ => check::<BitPackerFastLanes>(strict, initial, *num_bits, compressed),
+    }
+});
@@ -0,0 +1,365 @@
+//! Adaptive codec.
+//!
+//! Mixed columns are sometimes sorted, sometimes clustered around a value and sometimes
+//! random. Rather than picking `compress` or `compress_sorted` once per column,
+//! `AdaptiveCodec` computes the width of every mode for each block, in a single pass
+//! (see `BitPacker::num_bits_all_modes`), and picks the smallest encoding.
+//!
+//! Each compressed block starts with a one byte header holding its mode and its
+//! `num_bits`:
+//!
+//! | bits     | content                                           |
+//! |:---------|:--------------------------------------------------|
+//! | `[6..8)` | mode: plain, sorted, strictly sorted or FOR       |
+//! | `[0..6)` | `num_bits`, between 0 and 32                      |
+//!
+//! Frame-of-reference blocks are followed by their reference, the block minimum, as a
+//! little-endian `u32`. The bitpacked values come next.
+
+use crate::BitPacker;
+
+/// Size of the mode and width header.
+const HEADER_LEN: usize = 1;
+
+/// Size of the reference stored after the header of frame-of-reference blocks.
+const REFERENCE_LEN: usize = 4;
+
+const NUM_BITS_MASK: u8 = 0b0011_1111;
+
+/// Widths of a block in the different modes, as computed by
+/// `BitPacker::num_bits_all_modes(..)`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlockWidths {
+    /// Same as `.num_bits(..)`.
+    pub plain: u8,
+    /// Same as `.num_bits_sorted(initial, ..)`.
+    pub sorted: u8,
+    /// Same as `.num_bits_strictly_sorted(Some(initial), ..)`.
+    pub strictly_sorted: u8,
+    /// Number of bits of the values minus the block minimum.
+    pub frame_of_reference: u8,
+    /// Minimum of the block, used as the frame-of-reference.
+    pub min: u32,
+}
+
+/// Encoding of a block compressed by `AdaptiveCodec`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockMode {
+    /// Bitpacked as is, see `BitPacker::compress`.
+    Plain,
+    /// Delta-encoded, see `BitPacker::compress_sorted`.
+    Sorted,
+    /// Delta-encoded minus one, see `BitPacker::compress_strictly_sorted`.
+    StrictlySorted,
+    /// Bitpacked after subtracting the block minimum, see `BitPacker::compress_for`.
+    FrameOfReference,
+}
+
+impl BlockMode {
+    fn to_tag(self) -> u8 {
+        match self {
+            BlockMode::Plain => 0,
+            BlockMode::Sorted => 1,
+            BlockMode::StrictlySorted => 2,
+            BlockMode::FrameOfReference => 3,
+        }
+    }
+
+    fn from_tag(tag: u8) -> BlockMode {
+        match tag {
+            0 => BlockMode::Plain,
+            1 => BlockMode::Sorted,
+            2 => BlockMode::StrictlySorted,
+            _ => BlockMode::FrameOfReference,
+        }
+    }
+}
+
+/// Codec picking, for each block, the smallest of the plain, sorted, strictly sorted and
+/// frame-of-reference encodings.
+///
+/// It works with any of the `BitPacker` flavors, and shares their block length.
+///
+/// The sorted modes need the integer preceding the block, which the caller passes as
+/// `initial`, both to compress and to decompress. It is typically the last integer of the
+/// previous block, or `0` for the first one. Blocks that are not sorted can still be
+/// compressed: they simply end up in the plain or frame-of-reference modes.
+///
+/// ```
+/// use bitpacking::{AdaptiveCodec, BitPacker, BitPacker4x, BlockMode};
+///
+/// let codec = AdaptiveCodec::<BitPacker4x>::new();
+/// let mut compressed = vec![0u8; AdaptiveCodec::<BitPacker4x>::max_compressed_block_size()];
+///
+/// let timestamps: Vec<u32> = (0..128).map(|i| 1_000_000 + i * 3).collect();
+/// let initial = 999_997;
+/// let compressed_len = codec.compress(initial, &timestamps, &mut compressed);
+/// assert_eq!(AdaptiveCodec::<BitPacker4x>::block_mode(&compressed), BlockMode::Sorted);
+///
+/// let mut decompressed = vec![0u32; 128];
+/// assert_eq!(
+///     codec.decompress(initial, &compressed, &mut decompressed),
+///     compressed_len
+/// );
+/// assert_eq!(timestamps, decompressed);
+/// ```
+#[derive(Clone, Copy)]
+pub struct AdaptiveCodec<TBitPacker: BitPacker> {
+    bitpacker: TBitPacker,
+}
+
+impl<TBitPacker: BitPacker> Default for AdaptiveCodec<TBitPacker> {
+    fn default() -> Self {
+        AdaptiveCodec::new()
+    }
+}
+
+impl<TBitPacker: BitPacker> AdaptiveCodec<TBitPacker> {
+    /// Number of `u32` per compressed block.
+    pub const BLOCK_LEN: usize = TBitPacker::BLOCK_LEN;
+
+    /// Returns a codec relying on the best available implementation of `TBitPacker`.
+    pub fn new() -> Self {
+        AdaptiveCodec {
+            bitpacker: TBitPacker::new(),
+        }
+    }
+
+    /// Returns the size of a compressed block, header included, given its mode and its
+    /// number of bits.
+    #[must_use]
+    pub fn compressed_block_size(mode: BlockMode, num_bits: u8) -> usize {
+        let reference_len = if mode == BlockMode::FrameOfReference {
+            REFERENCE_LEN
+        } else {
+            0
+        };
+        HEADER_LEN + reference_len + TBitPacker::compressed_block_size(num_bits)
+    }
+
+    /// Returns the size of the largest possible compressed block.
+    ///
+    /// An output buffer of this size is always large enough for `.compress(..)`.
+    #[must_use]
+    pub fn max_compressed_block_size() -> usize {
+        Self::compressed_block_size(BlockMode::FrameOfReference, 32)
+    }
+
+    /// Returns the mode of a compressed block, as read from its header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `compressed` is empty.
+    #[must_use]
+    pub fn block_mode(compressed: &[u8]) -> BlockMode {
+        BlockMode::from_tag(compressed[0] >> 6)
+    }
+
+    /// Returns the mode minimizing the compressed size, and its number of bits.
+    ///
+    /// On ties, the modes that are the cheapest to decode win.
+    fn select(widths: &BlockWidths) -> (BlockMode, u8) {
+        [
+            (BlockMode::Plain, widths.plain),
+            (BlockMode::Sorted, widths.sorted),
+            (BlockMode::StrictlySorted, widths.strictly_sorted),
+            (BlockMode::FrameOfReference, widths.frame_of_reference),
+        ]
+        .into_iter()
+        .min_by_key(|&(mode, num_bits)| Self::compressed_block_size(mode, num_bits))
+        .unwrap()
+    }
+
+    /// Compress a block of `u32` in the mode yielding the smallest output.
+    ///
+    /// `initial` is the integer preceding the block, used by the sorted modes.
+    ///
+    /// Returns the amount of bytes of the compressed block, header included.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    /// - Panics if `compressed` is too small to receive the compressed block.
+    pub fn compress(&self, initial: u32, decompressed: &[u32], compressed: &mut [u8]) -> usize {
+        let widths = self.bitpacker.num_bits_all_modes(initial, decompressed);
+        let (mode, num_bits) = Self::select(&widths);
+        let compressed_len = Self::compressed_block_size(mode, num_bits);
+        assert!(
+            compressed.len() >= compressed_len,
+            "Output array too small (numbits {}). {} < {}",
+            num_bits,
+            compressed.len(),
+            compressed_len
+        );
+        compressed[0] = (mode.to_tag() << 6) | num_bits;
+        let payload = &mut compressed[HEADER_LEN..];
+        let written = match mode {
+            BlockMode::Plain => self.bitpacker.compress(decompressed, payload, num_bits),
+            BlockMode::Sorted => {
+                self.bitpacker
+                    .compress_sorted(initial, decompressed, payload, num_bits)
+            }
+            BlockMode::StrictlySorted => self.bitpacker.compress_strictly_sorted(
+                Some(initial),
+                decompressed,
+                payload,
+                num_bits,
+            ),
+            BlockMode::FrameOfReference => {
+                payload[..REFERENCE_LEN].copy_from_slice(&widths.min.to_le_bytes());
+                REFERENCE_LEN
+                    + self.bitpacker.compress_for(
+                        widths.min,
+                        decompressed,
+                        &mut payload[REFERENCE_LEN..],
+                        num_bits,
+                    )
+            }
+        };
+        HEADER_LEN + written
+    }
+
+    /// Decompress a block compressed with `.compress(..)`, dispatching on the mode
+    /// stored in its header.
+    ///
+    /// `initial` must be the same as the one passed to `.compress(..)`.
+    ///
+    /// Returns the amount of bytes that were consumed, header included.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed array is too short.
+    /// - Panics if the decompressed array is too short.
+    pub fn decompress(&self, initial: u32, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+        assert!(
+            compressed.len() >= HEADER_LEN,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            HEADER_LEN
+        );
+        let mode = Self::block_mode(compressed);
+        let num_bits = compressed[0] & NUM_BITS_MASK;
+        let payload = &compressed[HEADER_LEN..];
+        let read = match mode {
+            BlockMode::Plain => self.bitpacker.decompress(payload, decompressed, num_bits),
+            BlockMode::Sorted => {
+                self.bitpacker
+                    .decompress_sorted(initial, payload, decompressed, num_bits)
+            }
+            BlockMode::StrictlySorted => self.bitpacker.decompress_strictly_sorted(
+                Some(initial),
+                payload,
+                decompressed,
+                num_bits,
+            ),
+            BlockMode::FrameOfReference => {
+                assert!(
+                    payload.len() >= REFERENCE_LEN,
+                    "Compressed array seems too small. ({} < {}) ",
+                    compressed.len(),
+                    HEADER_LEN + REFERENCE_LEN
+                );
+                let reference = u32::from_le_bytes(payload[..REFERENCE_LEN].try_into().unwrap());
+                REFERENCE_LEN
+                    + self.bitpacker.decompress_scaled(
+                        reference,
+                        1,
+                        &payload[REFERENCE_LEN..],
+                        decompressed,
+                        num_bits,
+                    )
+            }
+        };
+        HEADER_LEN + read
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdaptiveCodec, BlockMode};
+    use crate::tests::generate_array;
+    use crate::BitPacker;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+
+    fn test_util_roundtrip<TBitPacker: BitPacker>(
+        initial: u32,
+        values: &[u32],
+        expected_mode: Option<BlockMode>,
+    ) {
+        let codec = AdaptiveCodec::<TBitPacker>::new();
+        let mut compressed = vec![0u8; AdaptiveCodec::<TBitPacker>::max_compressed_block_size()];
+        let compressed_len = codec.compress(initial, values, &mut compressed);
+        if let Some(expected_mode) = expected_mode {
+            assert_eq!(
+                AdaptiveCodec::<TBitPacker>::block_mode(&compressed),
+                expected_mode
+            );
+        }
+        let bitpacker = TBitPacker::new();
+        let smallest = [
+            TBitPacker::compressed_block_size(bitpacker.num_bits(values)),
+            TBitPacker::compressed_block_size(bitpacker.num_bits_sorted(initial, values)),
+            TBitPacker::compressed_block_size(
+                bitpacker.num_bits_strictly_sorted(Some(initial), values),
+            ),
+        ]
+        .into_iter()
+        .min()
+        .unwrap();
+        assert!(compressed_len <= 1 + smallest);
+        let mut decompressed = vec![0u32; TBitPacker::BLOCK_LEN];
+        let read_len = codec.decompress(initial, &compressed[..compressed_len], &mut decompressed);
+        assert_eq!(read_len, compressed_len);
+        assert_eq!(values, &decompressed[..]);
+    }
+
+    fn test_suite_adaptive<TBitPacker: BitPacker>() {
+        let n = TBitPacker::BLOCK_LEN as u32;
+        let sorted: Vec<u32> = (0..n).map(|i| 1_000 + (i / 2) * 7).collect();
+        test_util_roundtrip::<TBitPacker>(1_000, &sorted, Some(BlockMode::Sorted));
+        let strictly_sorted: Vec<u32> = (0..n).map(|i| 1_000 + i * 2).collect();
+        test_util_roundtrip::<TBitPacker>(998, &strictly_sorted, Some(BlockMode::StrictlySorted));
+        let clustered: Vec<u32> = (0..n).map(|i| 3_000_000_000 + (i * 37) % 500).collect();
+        test_util_roundtrip::<TBitPacker>(0, &clustered, Some(BlockMode::FrameOfReference));
+        let constant = vec![u32::MAX; TBitPacker::BLOCK_LEN];
+        test_util_roundtrip::<TBitPacker>(0, &constant, Some(BlockMode::FrameOfReference));
+        let zeros = vec![0u32; TBitPacker::BLOCK_LEN];
+        test_util_roundtrip::<TBitPacker>(0, &zeros, Some(BlockMode::Plain));
+        for num_bits in [1, 7, 31, 32] {
+            let random = generate_array(TBitPacker::BLOCK_LEN, num_bits);
+            test_util_roundtrip::<TBitPacker>(0, &random, None);
+            test_util_roundtrip::<TBitPacker>(u32::MAX, &random, None);
+        }
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_adaptive_bitpacker1x() {
+        test_suite_adaptive::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_adaptive_bitpacker4x() {
+        test_suite_adaptive::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_adaptive_bitpacker8x() {
+        test_suite_adaptive::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_adaptive_bitpackerfastlanes() {
+        test_suite_adaptive::<BitPackerFastLanes>();
+    }
+}
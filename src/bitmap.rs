@@ -0,0 +1,344 @@
+//! Conversions between 1-bit blocks and the positions of their set bits.
+//!
+//! A block compressed with `num_bits == 1` is a bitmap, but its bits are not in the
+//! order of the integers: the block is made of `lanes = BLOCK_LEN / 32` words, and bit
+//! `j` of word `c` is the integer `c + j * lanes`.
+//!
+//! The decoder first transposes the lanes back into a plain bitmap, with bit `i % 32` of
+//! word `i / 32` being the integer `i`, then walks it with `trailing_zeros`. Positions
+//! are thus emitted in increasing order.
+//!
+//! The rows of `BitPackerFastLanes` are not in the order of their integers: once
+//! transposed, the word of each row is moved to the place of its integers.
+
+use crate::BitPacker;
+
+/// Maximum number of lanes of a block, reached by `BitPackerFastLanes`.
+const MAX_NUM_LANES: usize = 32;
+
+/// Spreads the bits of a byte `4` bits apart: bit `i` goes to bit `4 * i`.
+const SPREAD_4: [u32; 256] = spread_table::<256>(4);
+
+/// Spreads the bits of a nibble `8` bits apart: bit `i` goes to bit `8 * i`.
+const SPREAD_8: [u32; 16] = spread_table::<16>(8);
+
+const fn spread_table<const N: usize>(stride: usize) -> [u32; N] {
+    let mut table = [0u32; N];
+    let mut val = 0;
+    while val < N {
+        let mut bit = 0;
+        while (1 << bit) < N {
+            if val & (1 << bit) != 0 {
+                table[val] |= 1 << (bit * stride);
+            }
+            bit += 1;
+        }
+        val += 1;
+    }
+    table
+}
+
+/// Transposes the `32 x 32` bit matrix `words` in place: bit `k` of `words[c]` becomes
+/// bit `c` of `words[k]`.
+fn transpose_32(words: &mut [u32; 32]) {
+    let mut shift = 16;
+    let mut mask: u32 = 0x0000_FFFF;
+    while shift != 0 {
+        let mut k = 0;
+        while k < 32 {
+            let t = ((words[k] >> shift) ^ words[k + shift]) & mask;
+            words[k] ^= t << shift;
+            words[k + shift] ^= t;
+            k = (k + shift + 1) & !shift;
+        }
+        shift >>= 1;
+        mask ^= mask << shift;
+    }
+}
+
+/// Turns the lanes of a 1-bit block into a plain bitmap of the same size.
+fn lanes_to_bitmap(lanes: &[u32], bitmap: &mut [u32]) {
+    let num_lanes = lanes.len();
+    match num_lanes {
+        1 => bitmap[0] = lanes[0],
+        4 => {
+            for (k, word) in bitmap.iter_mut().enumerate() {
+                *word = lanes.iter().enumerate().fold(0, |word, (c, &lane)| {
+                    word | SPREAD_4[((lane >> (8 * k)) & 0xFF) as usize] << c
+                });
+            }
+        }
+        8 => {
+            for (k, word) in bitmap.iter_mut().enumerate() {
+                *word = lanes.iter().enumerate().fold(0, |word, (c, &lane)| {
+                    word | SPREAD_8[((lane >> (4 * k)) & 0xF) as usize] << c
+                });
+            }
+        }
+        32 => {
+            let mut words = [0u32; 32];
+            words.copy_from_slice(lanes);
+            transpose_32(&mut words);
+            bitmap.copy_from_slice(&words);
+        }
+        _ => {
+            bitmap.fill(0);
+            for (c, &lane) in lanes.iter().enumerate() {
+                let mut bits = lane;
+                while bits != 0 {
+                    let pos = c + num_lanes * bits.trailing_zeros() as usize;
+                    bitmap[pos / 32] |= 1 << (pos % 32);
+                    bits &= bits - 1;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn decompress_positions<TBitPacker: BitPacker>(
+    compressed: &[u8],
+    positions: &mut [u32],
+) -> usize {
+    let num_lanes = TBitPacker::BLOCK_LEN / 32;
+    let num_bytes = TBitPacker::compressed_block_size(1);
+    assert!(
+        compressed.len() >= num_bytes,
+        "Compressed array seems too small. ({} < {}) ",
+        compressed.len(),
+        num_bytes
+    );
+    let mut lanes = [0u32; MAX_NUM_LANES];
+    let lanes = &mut lanes[..num_lanes];
+    for (lane, bytes) in lanes.iter_mut().zip(compressed.chunks_exact(4)) {
+        *lane = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+    let num_positions: usize = lanes.iter().map(|lane| lane.count_ones() as usize).sum();
+    assert!(
+        positions.len() >= num_positions,
+        "The output array is not large enough : ({} >= {})",
+        positions.len(),
+        num_positions
+    );
+    let mut bitmap = [0u32; MAX_NUM_LANES];
+    let bitmap = &mut bitmap[..num_lanes];
+    lanes_to_bitmap(lanes, bitmap);
+    write_positions(bitmap, positions);
+    num_positions
+}
+
+/// Writes the positions of the set bits of a plain bitmap, in increasing order.
+fn write_positions(bitmap: &[u32], positions: &mut [u32]) {
+    let mut num_written = 0;
+    for (k, &word) in bitmap.iter().enumerate() {
+        let mut bits = word;
+        while bits != 0 {
+            positions[num_written] = 32 * k as u32 + bits.trailing_zeros();
+            num_written += 1;
+            bits &= bits - 1;
+        }
+    }
+}
+
+/// Same as `decompress_positions`, for a block of 32 lanes whose bit `j` holds the 32
+/// integers from `row_starts[j]`, rather than from `32 * j`. The rows of
+/// `BitPackerFastLanes` are not in the order of their integers.
+#[cfg(feature = "bitpackerfastlanes")]
+pub(crate) fn decompress_row_positions(
+    compressed: &[u8],
+    positions: &mut [u32],
+    row_starts: &[usize; 32],
+) -> usize {
+    let num_bytes = 32 * 4;
+    assert!(
+        compressed.len() >= num_bytes,
+        "Compressed array seems too small. ({} < {}) ",
+        compressed.len(),
+        num_bytes
+    );
+    let mut words = [0u32; 32];
+    for (lane, bytes) in words.iter_mut().zip(compressed.chunks_exact(4)) {
+        *lane = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+    let num_positions: usize = words.iter().map(|word| word.count_ones() as usize).sum();
+    assert!(
+        positions.len() >= num_positions,
+        "The output array is not large enough : ({} >= {})",
+        positions.len(),
+        num_positions
+    );
+    // Once transposed, word `j` holds the bits of row `j`.
+    transpose_32(&mut words);
+    let mut bitmap = [0u32; 32];
+    for (&row, &start) in words.iter().zip(row_starts) {
+        bitmap[start / 32] = row;
+    }
+    write_positions(&bitmap, positions);
+    num_positions
+}
+
+pub(crate) fn compress_positions<TBitPacker: BitPacker>(
+    positions: &[u32],
+    compressed: &mut [u8],
+) -> usize {
+    let num_lanes = TBitPacker::BLOCK_LEN / 32;
+    let num_bytes = TBitPacker::compressed_block_size(1);
+    assert!(
+        compressed.len() >= num_bytes,
+        "Output array too small. {} < {}",
+        compressed.len(),
+        num_bytes
+    );
+    let mut lanes = [0u32; MAX_NUM_LANES];
+    let lanes = &mut lanes[..num_lanes];
+    for &pos in positions {
+        let pos = pos as usize;
+        assert!(
+            pos < TBitPacker::BLOCK_LEN,
+            "Position {} is out of the block (BLOCK_LEN={})",
+            pos,
+            TBitPacker::BLOCK_LEN
+        );
+        lanes[pos % num_lanes] |= 1 << (pos / num_lanes);
+    }
+    for (lane, bytes) in lanes.iter().zip(compressed.chunks_exact_mut(4)) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    num_bytes
+}
+
+/// Same as `compress_positions`, for a block of 32 lanes whose bit `j` holds the 32
+/// integers from `row_starts[j]`.
+#[cfg(feature = "bitpackerfastlanes")]
+pub(crate) fn compress_row_positions(
+    positions: &[u32],
+    compressed: &mut [u8],
+    row_starts: &[usize; 32],
+) -> usize {
+    let num_bytes = 32 * 4;
+    assert!(
+        compressed.len() >= num_bytes,
+        "Output array too small. {} < {}",
+        compressed.len(),
+        num_bytes
+    );
+    let mut chunk_rows = [0; 32];
+    for (row, &start) in row_starts.iter().enumerate() {
+        chunk_rows[start / 32] = row;
+    }
+    // Word `j` gathers the bits of row `j`, before being transposed into the lanes.
+    let mut words = [0u32; 32];
+    for &pos in positions {
+        let pos = pos as usize;
+        assert!(
+            pos < 32 * 32,
+            "Position {} is out of the block (BLOCK_LEN={})",
+            pos,
+            32 * 32
+        );
+        words[chunk_rows[pos / 32]] |= 1 << (pos % 32);
+    }
+    transpose_32(&mut words);
+    for (lane, bytes) in words.iter().zip(compressed.chunks_exact_mut(4)) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    num_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lanes_to_bitmap, transpose_32};
+    use crate::tests::generate_array;
+    use crate::BitPacker;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+
+    #[test]
+    fn test_lanes_to_bitmap_fallback() {
+        // Exercises the generic path with a number of lanes no bitpacker uses.
+        let lanes = [0b101, 0b010];
+        let mut bitmap = [0u32; 2];
+        lanes_to_bitmap(&lanes, &mut bitmap);
+        assert_eq!(bitmap, [0b1_1001, 0]);
+    }
+
+    #[test]
+    fn test_transpose_32() {
+        let words: Vec<u32> = generate_array(32, 32);
+        let mut transposed: [u32; 32] = words.clone().try_into().unwrap();
+        transpose_32(&mut transposed);
+        for (k, &word) in transposed.iter().enumerate() {
+            for (c, &original) in words.iter().enumerate() {
+                assert_eq!((word >> c) & 1, (original >> k) & 1);
+            }
+        }
+    }
+
+    fn test_suite_positions<TBitPacker: BitPacker>() {
+        let bitpacker = TBitPacker::new();
+        let block_len = TBitPacker::BLOCK_LEN;
+        let random = generate_array(block_len, 32);
+        for density in [0, 1, 7, 128, 255, 256] {
+            let flags: Vec<u32> = random
+                .iter()
+                .map(|&val| ((val & 0xFF) < density) as u32)
+                .collect();
+            let expected: Vec<u32> = (0..block_len as u32)
+                .filter(|&i| flags[i as usize] == 1)
+                .collect();
+            let mut compressed = vec![0u8; TBitPacker::compressed_block_size(1)];
+            bitpacker.compress(&flags, &mut compressed, 1);
+
+            let mut positions = vec![0u32; expected.len()];
+            let num_positions = bitpacker.decompress_positions(&compressed, &mut positions);
+            assert_eq!(num_positions, expected.len());
+            assert_eq!(positions, expected);
+
+            // Positions can be packed in any order.
+            let mut from_positions = vec![0xFFu8; compressed.len()];
+            let reversed: Vec<u32> = expected.iter().rev().copied().collect();
+            assert_eq!(
+                bitpacker.compress_positions(&reversed, &mut from_positions),
+                compressed.len()
+            );
+            assert_eq!(from_positions, compressed);
+        }
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_positions_bitpacker1x() {
+        test_suite_positions::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_positions_bitpacker4x() {
+        test_suite_positions::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_positions_bitpacker8x() {
+        test_suite_positions::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_positions_bitpackerfastlanes() {
+        test_suite_positions::<BitPackerFastLanes>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    #[should_panic(expected = "out of the block")]
+    fn test_compress_positions_out_of_block() {
+        let mut compressed = [0u8; 16];
+        BitPacker4x::new().compress_positions(&[3, 128], &mut compressed);
+    }
+}
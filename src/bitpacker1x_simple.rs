@@ -1,7 +1,10 @@
 //! Variant that does not have a specialized version for different bit-widths
 //!
 
-use super::{BitPacker, UnsafeBitPacker};
+use super::{
+    assume_init_prefix, private, BitPacker, BlockWidths, CompressError, Sink, UnsafeBitPacker,
+};
+use std::mem::MaybeUninit;
 
 const BLOCK_LEN: usize = 32;
 
@@ -14,6 +17,15 @@ mod scalar {
 
     type DataType = u32;
 
+    /// Compressed blocks are little-endian, whatever the target.
+    unsafe fn load_compressed(addr: *const DataType) -> DataType {
+        u32::from_le(load_unaligned(addr))
+    }
+
+    unsafe fn store_compressed(addr: *mut DataType, data: DataType) {
+        store_unaligned(addr, data.to_le());
+    }
+
     fn set1(el: i32) -> DataType {
         el as u32
     }
@@ -38,6 +50,14 @@ mod scalar {
         accumulator
     }
 
+    fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        if left > right {
+            u32::MAX
+        } else {
+            0
+        }
+    }
+
     fn compute_delta(curr: DataType, prev: DataType) -> DataType {
         curr.wrapping_sub(prev)
     }
@@ -54,6 +74,35 @@ mod scalar {
         left.wrapping_sub(right)
     }
 
+    fn mul(left: DataType, right: DataType) -> DataType {
+        left.wrapping_mul(right)
+    }
+
+    /// Widens an `u8` into a register.
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        *addr as u32
+    }
+
+    /// Widens an `u16` into a register.
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        *addr as u32
+    }
+
+    /// Narrows a register whose integer fits in an `u8`.
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        *dst = data as u8;
+    }
+
+    /// Narrows a register whose integer fits in an `u16`.
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        *dst = data as u16;
+    }
+
+    /// Widens a register to `u64`, and adds `base` to it.
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        *dst = base.wrapping_add(data as u64);
+    }
+
     // The `cfg(any(debug, not(debug)))` is here to put an attribute that has no effect.
     //
     // For other bitpacker, we enable specific CPU instruction set, but for the
@@ -74,6 +123,10 @@ mod scalar {
 #[derive(Clone, Copy)]
 pub struct BitPacker1x;
 
+impl private::Sealed for BitPacker1x {}
+
+impl private::InOrderRows for BitPacker1x {}
+
 impl BitPacker for BitPacker1x {
     const BLOCK_LEN: usize = BLOCK_LEN;
 
@@ -85,6 +138,52 @@ impl BitPacker for BitPacker1x {
         unsafe { scalar::UnsafeBitPackerImpl::compress(decompressed, compressed, num_bits) }
     }
 
+    fn compress_masked(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_masked(decompressed, compressed, num_bits) }
+    }
+
+    fn compress_auto(&self, decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_auto(decompressed, compressed) }
+    }
+
+    fn compress_sorted_auto(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::compress_sorted_auto(initial, decompressed, compressed)
+        }
+    }
+
+    fn compress_strictly_sorted_auto(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::compress_strictly_sorted_auto(
+                initial,
+                decompressed,
+                compressed,
+            )
+        }
+    }
+
+    fn compress_for(
+        &self,
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::compress_for(reference, decompressed, compressed, num_bits)
+        }
+    }
+
     fn compress_sorted(
         &self,
         initial: u32,
@@ -157,6 +256,173 @@ impl BitPacker for BitPacker1x {
         }
     }
 
+    fn decompress_scaled(
+        &self,
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_scaled(
+                offset,
+                factor,
+                compressed,
+                decompressed,
+                num_bits,
+            )
+        }
+    }
+
+    fn num_bits_sorted_checked(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe { scalar::UnsafeBitPackerImpl::num_bits_sorted_checked(initial, decompressed) }
+    }
+
+    fn num_bits_strictly_sorted_checked(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::num_bits_strictly_sorted_checked(initial, decompressed)
+        }
+    }
+
+    fn num_bits_all_modes(&self, initial: u32, decompressed: &[u32]) -> BlockWidths {
+        unsafe { scalar::UnsafeBitPackerImpl::num_bits_all_modes(initial, decompressed) }
+    }
+
+    fn compress_u8(&self, decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_u8(decompressed, compressed, num_bits) }
+    }
+
+    fn compress_u16(&self, decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_u16(decompressed, compressed, num_bits) }
+    }
+
+    fn decompress_into_u8(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_into_u8(compressed, decompressed, num_bits)
+        }
+    }
+
+    fn decompress_into_u16(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_into_u16(compressed, decompressed, num_bits)
+        }
+    }
+
+    fn decompress_into_u64(
+        &self,
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_into_u64(
+                base,
+                compressed,
+                decompressed,
+                num_bits,
+            )
+        }
+    }
+
+    fn decompress_sorted_into_u64(
+        &self,
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_sorted_into_u64(
+                initial,
+                compressed,
+                decompressed,
+                num_bits,
+            )
+        }
+    }
+
+    fn compress_uninit<'a>(
+        &self,
+        decompressed: &[u32],
+        compressed: &'a mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> &'a mut [u8] {
+        unsafe {
+            let compressed_len =
+                scalar::UnsafeBitPackerImpl::compress_uninit(decompressed, compressed, num_bits);
+            assume_init_prefix(compressed, compressed_len)
+        }
+    }
+
+    fn decompress_uninit<'a>(
+        &self,
+        compressed: &[u8],
+        decompressed: &'a mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> &'a mut [u32] {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_uninit(compressed, decompressed, num_bits);
+            assume_init_prefix(decompressed, Self::BLOCK_LEN)
+        }
+    }
+
+    fn decompress_to_sink<TSink: Sink>(
+        &self,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::decompress_to_sink(compressed, sink, num_bits) }
+    }
+
+    fn decompress_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_sorted_to_sink(
+                initial, compressed, sink, num_bits,
+            )
+        }
+    }
+
+    fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_strictly_sorted_to_sink(
+                initial, compressed, sink, num_bits,
+            )
+        }
+    }
+
     fn num_bits(&self, decompressed: &[u32]) -> u8 {
         unsafe { scalar::UnsafeBitPackerImpl::num_bits(decompressed) }
     }
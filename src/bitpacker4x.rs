@@ -1,4 +1,7 @@
-use super::{BitPacker, UnsafeBitPacker};
+use super::{
+    assume_init_prefix, private, BitPacker, BlockWidths, CompressError, Sink, UnsafeBitPacker,
+};
+use std::mem::MaybeUninit;
 
 #[cfg(any(
     target_arch = "x86_64",
@@ -17,15 +20,22 @@ mod sse3 {
     use std::arch::x86_64::__m128i as DataType;
     use std::arch::x86_64::_mm_and_si128 as op_and;
     use std::arch::x86_64::_mm_lddqu_si128 as load_unaligned;
+    // x86 is little-endian: compressed blocks are stored as is.
+    use std::arch::x86_64::_mm_lddqu_si128 as load_compressed;
     use std::arch::x86_64::_mm_or_si128 as op_or;
     use std::arch::x86_64::_mm_set1_epi32 as set1;
     use std::arch::x86_64::_mm_slli_epi32 as left_shift_32;
     use std::arch::x86_64::_mm_srli_epi32 as right_shift_32;
+    use std::arch::x86_64::_mm_storeu_si128 as store_compressed;
     use std::arch::x86_64::_mm_storeu_si128 as store_unaligned;
     use std::arch::x86_64::{
-        _mm_add_epi32, _mm_cvtsi128_si32, _mm_shuffle_epi32, _mm_slli_si128, _mm_srli_si128,
-        _mm_sub_epi32,
+        _mm_add_epi32, _mm_add_epi64, _mm_cmpgt_epi32, _mm_cvtsi128_si32, _mm_cvtsi32_si128,
+        _mm_loadl_epi64, _mm_mul_epu32, _mm_packs_epi32, _mm_packus_epi16, _mm_set1_epi16,
+        _mm_set1_epi64x, _mm_setzero_si128, _mm_shuffle_epi32, _mm_slli_si128, _mm_srli_si128,
+        _mm_storel_epi64, _mm_sub_epi32, _mm_unpackhi_epi32, _mm_unpacklo_epi16,
+        _mm_unpacklo_epi32, _mm_unpacklo_epi8, _mm_xor_si128,
     };
+    use std::ptr;
 
     #[allow(non_snake_case)]
     #[inline]
@@ -38,6 +48,14 @@ mod sse3 {
         _mm_cvtsi128_si32(_______cadb) as u32
     }
 
+    /// SSE only compares signed integers: flipping the sign bits maps the unsigned
+    /// order onto the signed one.
+    #[target_feature(enable = "sse3")]
+    unsafe fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let sign = set1(i32::MIN);
+        _mm_cmpgt_epi32(_mm_xor_si128(left, sign), _mm_xor_si128(right, sign))
+    }
+
     #[target_feature(enable = "sse3")]
     unsafe fn compute_delta(curr: DataType, prev: DataType) -> DataType {
         _mm_sub_epi32(
@@ -69,6 +87,70 @@ mod sse3 {
         _mm_sub_epi32(left, right)
     }
 
+    // `_mm_mullo_epi32` requires SSE4.1, so we emulate it using two
+    // 32x32->64 bits multiplications on the even and odd lanes.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn mul(left: DataType, right: DataType) -> DataType {
+        let even = _mm_mul_epu32(left, right);
+        let odd = _mm_mul_epu32(_mm_srli_si128(left, 4), _mm_srli_si128(right, 4));
+        _mm_unpacklo_epi32(
+            _mm_shuffle_epi32(even, 0b00_00_10_00),
+            _mm_shuffle_epi32(odd, 0b00_00_10_00),
+        )
+    }
+
+    // `_mm_cvtepu8_epi32` and `_mm_packus_epi32` require SSE4.1: the integers are
+    // widened by interleaving them with zeros, and narrowed with signed saturations.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        let zero = _mm_setzero_si128();
+        let bytes = _mm_cvtsi32_si128(ptr::read_unaligned(addr.cast::<i32>()));
+        _mm_unpacklo_epi16(_mm_unpacklo_epi8(bytes, zero), zero)
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        let words = _mm_loadl_epi64(addr.cast::<DataType>());
+        _mm_unpacklo_epi16(words, _mm_setzero_si128())
+    }
+
+    /// The integers fit in an `u8`: the signed saturations leave them unchanged.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        let words = _mm_packs_epi32(data, data);
+        let bytes = _mm_packus_epi16(words, words);
+        ptr::write_unaligned(dst.cast::<i32>(), _mm_cvtsi128_si32(bytes));
+    }
+
+    /// The integers fit in an `u16`: they are biased into the range of `i16` before the
+    /// signed saturation, and the bias is flipped back afterwards.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        let biased = _mm_sub_epi32(data, set1(0x8000));
+        let words = _mm_xor_si128(_mm_packs_epi32(biased, biased), _mm_set1_epi16(i16::MIN));
+        _mm_storel_epi64(dst.cast::<DataType>(), words);
+    }
+
+    /// Widens a register to `u64` by interleaving it with zeros, and adds `base` to its
+    /// integers.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        let dst = dst.cast::<DataType>();
+        let zero = _mm_setzero_si128();
+        let base = _mm_set1_epi64x(base as i64);
+        store_unaligned(dst, _mm_add_epi64(base, _mm_unpacklo_epi32(data, zero)));
+        store_unaligned(
+            dst.add(1),
+            _mm_add_epi64(base, _mm_unpackhi_epi32(data, zero)),
+        );
+    }
+
     declare_bitpacker!(target_feature(enable = "sse3"));
 
     impl Available for UnsafeBitPackerImpl {
@@ -86,12 +168,21 @@ mod neon {
 
     use super::scalar::add;
     use super::scalar::left_shift_32;
+    use super::scalar::load_compressed;
+    use super::scalar::load_u16;
+    use super::scalar::load_u8;
     use super::scalar::load_unaligned;
+    use super::scalar::mul;
     use super::scalar::op_and;
+    use super::scalar::op_gt_u32;
     use super::scalar::op_or;
     use super::scalar::or_collapse_to_u32;
     use super::scalar::right_shift_32;
     use super::scalar::set1;
+    use super::scalar::store_compressed;
+    use super::scalar::store_u16;
+    use super::scalar::store_u64;
+    use super::scalar::store_u8;
     use super::scalar::store_unaligned;
     use super::scalar::sub;
     use super::scalar::DataType;
@@ -181,10 +272,27 @@ mod scalar {
         ptr::write_unaligned(addr, data);
     }
 
+    /// Compressed blocks are little-endian, whatever the target.
+    pub(crate) unsafe fn load_compressed(addr: *const DataType) -> DataType {
+        load_unaligned(addr).map(u32::from_le)
+    }
+
+    pub(crate) unsafe fn store_compressed(addr: *mut DataType, data: DataType) {
+        store_unaligned(addr, data.map(u32::to_le));
+    }
+
     pub(crate) fn or_collapse_to_u32(accumulator: DataType) -> u32 {
         (accumulator[0] | accumulator[1]) | (accumulator[2] | accumulator[3])
     }
 
+    pub(crate) fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let mut result = left;
+        for (result, right) in result.iter_mut().zip(right) {
+            *result = if *result > right { u32::MAX } else { 0 };
+        }
+        result
+    }
+
     fn compute_delta(curr: DataType, prev: DataType) -> DataType {
         [
             curr[0].wrapping_sub(prev[3]),
@@ -220,6 +328,46 @@ mod scalar {
         ]
     }
 
+    pub(crate) fn mul(left: DataType, right: DataType) -> DataType {
+        [
+            left[0].wrapping_mul(right[0]),
+            left[1].wrapping_mul(right[1]),
+            left[2].wrapping_mul(right[2]),
+            left[3].wrapping_mul(right[3]),
+        ]
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u8` into a register.
+    pub(crate) unsafe fn load_u8(addr: *const u8) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u16` into a register.
+    pub(crate) unsafe fn load_u16(addr: *const u16) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Narrows a register whose integers fit in an `u8`.
+    pub(crate) unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u8;
+        }
+    }
+
+    /// Narrows a register whose integers fit in an `u16`.
+    pub(crate) unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u16;
+        }
+    }
+
+    /// Widens a register to `u64`, and adds `base` to its integers.
+    pub(crate) unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = base.wrapping_add(val as u64);
+        }
+    }
+
     // The `cfg(any(debug, not(debug)))` is here to put an attribute that has no effect.
     //
     // For other bitpacker, we enable specific CPU instruction set, but for the
@@ -249,6 +397,10 @@ enum InstructionSet {
 #[derive(Clone, Copy)]
 pub struct BitPacker4x(InstructionSet);
 
+impl private::Sealed for BitPacker4x {}
+
+impl private::InOrderRows for BitPacker4x {}
+
 impl BitPacker for BitPacker4x {
     const BLOCK_LEN: usize = BLOCK_LEN;
 
@@ -287,6 +439,135 @@ impl BitPacker for BitPacker4x {
         }
     }
 
+    fn compress_masked(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn compress_auto(&self, decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::compress_auto(decompressed, compressed)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::compress_auto(decompressed, compressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_auto(decompressed, compressed)
+                }
+            }
+        }
+    }
+
+    fn compress_sorted_auto(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::compress_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::compress_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::compress_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+            }
+        }
+    }
+
+    fn compress_strictly_sorted_auto(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::compress_strictly_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::compress_strictly_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_strictly_sorted_auto(
+                        initial,
+                        decompressed,
+                        compressed,
+                    )
+                }
+            }
+        }
+    }
+
+    fn compress_for(
+        &self,
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::compress_for(
+                    reference,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::compress_for(
+                    reference,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::compress_for(
+                    reference,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
     fn compress_sorted(
         &self,
         initial: u32,
@@ -437,6 +718,401 @@ impl BitPacker for BitPacker4x {
         }
     }
 
+    fn decompress_scaled(
+        &self,
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn num_bits_sorted_checked(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn num_bits_strictly_sorted_checked(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::num_bits_strictly_sorted_checked(
+                        initial,
+                        decompressed,
+                    )
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::num_bits_strictly_sorted_checked(
+                        initial,
+                        decompressed,
+                    )
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::num_bits_strictly_sorted_checked(
+                        initial,
+                        decompressed,
+                    )
+                }
+            }
+        }
+    }
+
+    fn num_bits_all_modes(&self, initial: u32, decompressed: &[u32]) -> BlockWidths {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::num_bits_all_modes(initial, decompressed)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::num_bits_all_modes(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::num_bits_all_modes(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn compress_u8(&self, decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn compress_u16(&self, decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_into_u8(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::decompress_into_u8(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::decompress_into_u8(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_into_u8(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_into_u16(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::decompress_into_u16(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::decompress_into_u16(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_into_u16(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_into_u64(
+        &self,
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_sorted_into_u64(
+        &self,
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn compress_uninit<'a>(
+        &self,
+        decompressed: &[u32],
+        compressed: &'a mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> &'a mut [u8] {
+        unsafe {
+            let compressed_len = match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+            };
+            assume_init_prefix(compressed, compressed_len)
+        }
+    }
+
+    fn decompress_uninit<'a>(
+        &self,
+        compressed: &[u8],
+        decompressed: &'a mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> &'a mut [u32] {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::decompress_uninit(compressed, decompressed, num_bits)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::decompress_uninit(compressed, decompressed, num_bits)
+                }
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_uninit(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            };
+            assume_init_prefix(decompressed, Self::BLOCK_LEN)
+        }
+    }
+
+    fn decompress_to_sink<TSink: Sink>(
+        &self,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::UnsafeBitPackerImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => neon::UnsafeBitPackerImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::UnsafeBitPackerImpl::decompress_strictly_sorted_to_sink(
+                        initial, compressed, sink, num_bits,
+                    )
+                }
+                #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                InstructionSet::NEON => {
+                    neon::UnsafeBitPackerImpl::decompress_strictly_sorted_to_sink(
+                        initial, compressed, sink, num_bits,
+                    )
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::decompress_strictly_sorted_to_sink(
+                        initial, compressed, sink, num_bits,
+                    )
+                }
+            }
+        }
+    }
+
     fn num_bits(&self, decompressed: &[u32]) -> u8 {
         unsafe {
             match self.0 {
@@ -494,7 +1170,7 @@ impl BitPacker for BitPacker4x {
 mod tests {
     use super::scalar;
     use super::BLOCK_LEN;
-    use crate::tests::test_util_compatible;
+    use crate::tests::{test_util_compatible, test_util_compress_masked_compatible};
     use crate::Available;
     use crate::{BitPacker, BitPacker4x};
 
@@ -506,6 +1182,10 @@ mod tests {
             test_util_compatible::<scalar::UnsafeBitPackerImpl, sse3::UnsafeBitPackerImpl>(
                 BLOCK_LEN,
             );
+            test_util_compress_masked_compatible::<
+                scalar::UnsafeBitPackerImpl,
+                sse3::UnsafeBitPackerImpl,
+            >(BLOCK_LEN);
         }
     }
 
@@ -517,12 +1197,16 @@ mod tests {
             test_util_compatible::<scalar::UnsafeBitPackerImpl, neon::UnsafeBitPackerImpl>(
                 BLOCK_LEN,
             );
+            test_util_compress_masked_compatible::<
+                scalar::UnsafeBitPackerImpl,
+                neon::UnsafeBitPackerImpl,
+            >(BLOCK_LEN);
         }
     }
 
     #[test]
     fn test_delta_bit_width_32() {
-        let values = vec![i32::max_value() as u32 + 1; BitPacker4x::BLOCK_LEN];
+        let values = vec![i32::MAX as u32 + 1; BitPacker4x::BLOCK_LEN];
         let bit_packer = BitPacker4x::new();
         let bit_width = bit_packer.num_bits_sorted(0, &values);
         assert_eq!(bit_width, 32);
@@ -538,7 +1222,7 @@ mod tests {
 
     #[test]
     fn test_bit_width_32() {
-        let mut values = vec![i32::max_value() as u32 + 1; BitPacker4x::BLOCK_LEN];
+        let mut values = vec![i32::MAX as u32 + 1; BitPacker4x::BLOCK_LEN];
         values[0] = 0;
         let bit_packer = BitPacker4x::new();
         let bit_width = bit_packer.num_bits(&values);
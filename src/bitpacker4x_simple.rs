@@ -1,7 +1,10 @@
 //! Variant that does not have a specialized version for different bit-widths
 //!
 
-use super::{BitPacker, UnsafeBitPacker};
+use super::{
+    assume_init_prefix, private, BitPacker, BlockWidths, CompressError, Sink, UnsafeBitPacker,
+};
+use std::mem::MaybeUninit;
 
 const BLOCK_LEN: usize = 32 * 4;
 
@@ -59,10 +62,27 @@ mod scalar {
         ptr::write_unaligned(addr, data);
     }
 
+    /// Compressed blocks are little-endian, whatever the target.
+    unsafe fn load_compressed(addr: *const DataType) -> DataType {
+        load_unaligned(addr).map(u32::from_le)
+    }
+
+    unsafe fn store_compressed(addr: *mut DataType, data: DataType) {
+        store_unaligned(addr, data.map(u32::to_le));
+    }
+
     fn or_collapse_to_u32(accumulator: DataType) -> u32 {
         (accumulator[0] | accumulator[1]) | (accumulator[2] | accumulator[3])
     }
 
+    fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let mut result = left;
+        for (result, right) in result.iter_mut().zip(right) {
+            *result = if *result > right { u32::MAX } else { 0 };
+        }
+        result
+    }
+
     fn compute_delta(curr: DataType, prev: DataType) -> DataType {
         [
             curr[0].wrapping_sub(prev[3]),
@@ -98,6 +118,46 @@ mod scalar {
         ]
     }
 
+    fn mul(left: DataType, right: DataType) -> DataType {
+        [
+            left[0].wrapping_mul(right[0]),
+            left[1].wrapping_mul(right[1]),
+            left[2].wrapping_mul(right[2]),
+            left[3].wrapping_mul(right[3]),
+        ]
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u8` into a register.
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u16` into a register.
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Narrows a register whose integers fit in an `u8`.
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u8;
+        }
+    }
+
+    /// Narrows a register whose integers fit in an `u16`.
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u16;
+        }
+    }
+
+    /// Widens a register to `u64`, and adds `base` to its integers.
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = base.wrapping_add(val as u64);
+        }
+    }
+
     declare_bitpacker_simple!(cfg(any(debug, not(debug))));
 }
 
@@ -108,6 +168,10 @@ mod scalar {
 #[derive(Clone, Copy)]
 pub struct BitPacker4x;
 
+impl private::Sealed for BitPacker4x {}
+
+impl private::InOrderRows for BitPacker4x {}
+
 impl BitPacker for BitPacker4x {
     const BLOCK_LEN: usize = BLOCK_LEN;
 
@@ -120,6 +184,52 @@ impl BitPacker for BitPacker4x {
         unsafe { scalar::UnsafeBitPackerImpl::compress(decompressed, compressed, num_bits) }
     }
 
+    fn compress_masked(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_masked(decompressed, compressed, num_bits) }
+    }
+
+    fn compress_auto(&self, decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_auto(decompressed, compressed) }
+    }
+
+    fn compress_sorted_auto(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::compress_sorted_auto(initial, decompressed, compressed)
+        }
+    }
+
+    fn compress_strictly_sorted_auto(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::compress_strictly_sorted_auto(
+                initial,
+                decompressed,
+                compressed,
+            )
+        }
+    }
+
+    fn compress_for(
+        &self,
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::compress_for(reference, decompressed, compressed, num_bits)
+        }
+    }
+
     fn compress_sorted(
         &self,
         initial: u32,
@@ -192,6 +302,173 @@ impl BitPacker for BitPacker4x {
         }
     }
 
+    fn decompress_scaled(
+        &self,
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_scaled(
+                offset,
+                factor,
+                compressed,
+                decompressed,
+                num_bits,
+            )
+        }
+    }
+
+    fn num_bits_sorted_checked(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe { scalar::UnsafeBitPackerImpl::num_bits_sorted_checked(initial, decompressed) }
+    }
+
+    fn num_bits_strictly_sorted_checked(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::num_bits_strictly_sorted_checked(initial, decompressed)
+        }
+    }
+
+    fn num_bits_all_modes(&self, initial: u32, decompressed: &[u32]) -> BlockWidths {
+        unsafe { scalar::UnsafeBitPackerImpl::num_bits_all_modes(initial, decompressed) }
+    }
+
+    fn compress_u8(&self, decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_u8(decompressed, compressed, num_bits) }
+    }
+
+    fn compress_u16(&self, decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::compress_u16(decompressed, compressed, num_bits) }
+    }
+
+    fn decompress_into_u8(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_into_u8(compressed, decompressed, num_bits)
+        }
+    }
+
+    fn decompress_into_u16(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_into_u16(compressed, decompressed, num_bits)
+        }
+    }
+
+    fn decompress_into_u64(
+        &self,
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_into_u64(
+                base,
+                compressed,
+                decompressed,
+                num_bits,
+            )
+        }
+    }
+
+    fn decompress_sorted_into_u64(
+        &self,
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_sorted_into_u64(
+                initial,
+                compressed,
+                decompressed,
+                num_bits,
+            )
+        }
+    }
+
+    fn compress_uninit<'a>(
+        &self,
+        decompressed: &[u32],
+        compressed: &'a mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> &'a mut [u8] {
+        unsafe {
+            let compressed_len =
+                scalar::UnsafeBitPackerImpl::compress_uninit(decompressed, compressed, num_bits);
+            assume_init_prefix(compressed, compressed_len)
+        }
+    }
+
+    fn decompress_uninit<'a>(
+        &self,
+        compressed: &[u8],
+        decompressed: &'a mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> &'a mut [u32] {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_uninit(compressed, decompressed, num_bits);
+            assume_init_prefix(decompressed, Self::BLOCK_LEN)
+        }
+    }
+
+    fn decompress_to_sink<TSink: Sink>(
+        &self,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe { scalar::UnsafeBitPackerImpl::decompress_to_sink(compressed, sink, num_bits) }
+    }
+
+    fn decompress_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_sorted_to_sink(
+                initial, compressed, sink, num_bits,
+            )
+        }
+    }
+
+    fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            scalar::UnsafeBitPackerImpl::decompress_strictly_sorted_to_sink(
+                initial, compressed, sink, num_bits,
+            )
+        }
+    }
+
     fn num_bits(&self, decompressed: &[u32]) -> u8 {
         unsafe { scalar::UnsafeBitPackerImpl::num_bits(decompressed) }
     }
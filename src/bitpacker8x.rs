@@ -1,4 +1,7 @@
-use super::{BitPacker, UnsafeBitPacker};
+use super::{
+    assume_init_prefix, private, BitPacker, BlockWidths, CompressError, Sink, UnsafeBitPacker,
+};
+use std::mem::MaybeUninit;
 
 #[cfg(target_arch = "x86_64")]
 use crate::Available;
@@ -14,15 +17,23 @@ mod avx2 {
     use std::arch::x86_64::__m256i as DataType;
     use std::arch::x86_64::_mm256_and_si256 as op_and;
     use std::arch::x86_64::_mm256_lddqu_si256 as load_unaligned;
+    // x86 is little-endian: compressed blocks are stored as is.
+    use std::arch::x86_64::_mm256_lddqu_si256 as load_compressed;
     use std::arch::x86_64::_mm256_or_si256 as op_or;
     use std::arch::x86_64::_mm256_set1_epi32 as set1;
     use std::arch::x86_64::_mm256_slli_epi32 as left_shift_32;
     use std::arch::x86_64::_mm256_srli_epi32 as right_shift_32;
+    use std::arch::x86_64::_mm256_storeu_si256 as store_compressed;
     use std::arch::x86_64::_mm256_storeu_si256 as store_unaligned;
 
     use std::arch::x86_64::{
-        _mm256_add_epi32, _mm256_extract_epi32, _mm256_permute2f128_si256, _mm256_shuffle_epi32,
-        _mm256_slli_si256, _mm256_srli_si256, _mm256_sub_epi32,
+        __m128i, _mm256_add_epi32, _mm256_add_epi64, _mm256_castsi256_si128, _mm256_cmpgt_epi32,
+        _mm256_cvtepu16_epi32, _mm256_cvtepu32_epi64, _mm256_cvtepu8_epi32, _mm256_extract_epi32,
+        _mm256_extracti128_si256, _mm256_mullo_epi32, _mm256_packus_epi32,
+        _mm256_permute2f128_si256, _mm256_permute4x64_epi64, _mm256_set1_epi64x,
+        _mm256_shuffle_epi32, _mm256_slli_si256, _mm256_srli_si256, _mm256_sub_epi32,
+        _mm256_xor_si256, _mm_loadl_epi64, _mm_loadu_si128, _mm_packus_epi16, _mm_storel_epi64,
+        _mm_storeu_si128,
     };
 
     #[allow(non_snake_case)]
@@ -37,6 +48,13 @@ mod avx2 {
         (cadb | gehf) as u32
     }
 
+    /// AVX2 only compares signed integers: flipping the sign bits maps the unsigned
+    /// order onto the signed one.
+    unsafe fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let sign = set1(i32::MIN);
+        _mm256_cmpgt_epi32(_mm256_xor_si256(left, sign), _mm256_xor_si256(right, sign))
+    }
+
     unsafe fn compute_delta(curr: DataType, prev: DataType) -> DataType {
         let left_shift = _mm256_slli_si256(curr, 4);
         let curr_shift = _mm256_srli_si256(curr, 12);
@@ -61,7 +79,7 @@ mod avx2 {
             _mm256_add_epi32(___a__b__ca____e__f__ge_, a__b__ca_db_e__f__ge_fh_);
         let offseted_halved_prefix_sum = _mm256_add_epi32(halved_prefix_sum, offset);
         let select_last_low = _mm256_shuffle_epi32(offseted_halved_prefix_sum, 0xff);
-        let high_offset = _mm256_permute2f128_si256(select_last_low, select_last_low, 8 | 0);
+        let high_offset = _mm256_permute2f128_si256(select_last_low, select_last_low, 8);
         _mm256_add_epi32(high_offset, offseted_halved_prefix_sum)
     }
 
@@ -73,6 +91,56 @@ mod avx2 {
         _mm256_sub_epi32(left, right)
     }
 
+    unsafe fn mul(left: DataType, right: DataType) -> DataType {
+        _mm256_mullo_epi32(left, right)
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        _mm256_cvtepu8_epi32(_mm_loadl_epi64(addr.cast::<__m128i>()))
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        _mm256_cvtepu16_epi32(_mm_loadu_si128(addr.cast::<__m128i>()))
+    }
+
+    /// `_mm256_packus_epi32` packs each 128-bit half separately: the 64-bit groups
+    /// holding the integers are gathered in the low half afterwards.
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn narrow_to_u16(data: DataType) -> __m128i {
+        let words = _mm256_packus_epi32(data, data);
+        _mm256_castsi256_si128(_mm256_permute4x64_epi64::<0b00_00_10_00>(words))
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        let words = narrow_to_u16(data);
+        _mm_storel_epi64(dst.cast::<__m128i>(), _mm_packus_epi16(words, words));
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        _mm_storeu_si128(dst.cast::<__m128i>(), narrow_to_u16(data));
+    }
+
+    /// Widens a register to `u64`, and adds `base` to its integers.
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        let dst = dst.cast::<DataType>();
+        let base = _mm256_set1_epi64x(base as i64);
+        let low = _mm256_cvtepu32_epi64(_mm256_castsi256_si128(data));
+        let high = _mm256_cvtepu32_epi64(_mm256_extracti128_si256::<1>(data));
+        store_unaligned(dst, _mm256_add_epi64(base, low));
+        store_unaligned(dst.add(1), _mm256_add_epi64(base, high));
+    }
+
     declare_bitpacker!(target_feature(enable = "avx2"));
 
     impl Available for UnsafeBitPackerImpl {
@@ -154,11 +222,28 @@ mod scalar {
         ptr::write_unaligned(dst, data);
     }
 
+    /// Compressed blocks are little-endian, whatever the target.
+    unsafe fn load_compressed(addr: *const DataType) -> DataType {
+        load_unaligned(addr).map(u32::from_le)
+    }
+
+    unsafe fn store_compressed(dst: *mut DataType, data: DataType) {
+        store_unaligned(dst, data.map(u32::to_le));
+    }
+
     fn or_collapse_to_u32(accumulator: DataType) -> u32 {
         ((accumulator[0] | accumulator[1]) | (accumulator[2] | accumulator[3]))
             | ((accumulator[4] | accumulator[5]) | (accumulator[6] | accumulator[7]))
     }
 
+    fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let mut result = left;
+        for (result, right) in result.iter_mut().zip(right) {
+            *result = if *result > right { u32::MAX } else { 0 };
+        }
+        result
+    }
+
     fn compute_delta(curr: DataType, prev: DataType) -> DataType {
         [
             curr[0].wrapping_sub(prev[7]),
@@ -210,6 +295,50 @@ mod scalar {
         ]
     }
 
+    fn mul(left: DataType, right: DataType) -> DataType {
+        [
+            left[0].wrapping_mul(right[0]),
+            left[1].wrapping_mul(right[1]),
+            left[2].wrapping_mul(right[2]),
+            left[3].wrapping_mul(right[3]),
+            left[4].wrapping_mul(right[4]),
+            left[5].wrapping_mul(right[5]),
+            left[6].wrapping_mul(right[6]),
+            left[7].wrapping_mul(right[7]),
+        ]
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u8` into a register.
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u16` into a register.
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Narrows a register whose integers fit in an `u8`.
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u8;
+        }
+    }
+
+    /// Narrows a register whose integers fit in an `u16`.
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u16;
+        }
+    }
+
+    /// Widens a register to `u64`, and adds `base` to its integers.
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = base.wrapping_add(val as u64);
+        }
+    }
+
     // The `cfg(any(debug, not(debug)))` is here to put an attribute that has no effect.
     //
     // For other bitpacker, we enable specific CPU instruction set, but for the
@@ -236,6 +365,10 @@ enum InstructionSet {
 #[derive(Clone, Copy)]
 pub struct BitPacker8x(InstructionSet);
 
+impl private::Sealed for BitPacker8x {}
+
+impl private::InOrderRows for BitPacker8x {}
+
 impl BitPacker for BitPacker8x {
     const BLOCK_LEN: usize = BLOCK_LEN;
 
@@ -263,6 +396,108 @@ impl BitPacker for BitPacker8x {
         }
     }
 
+    fn compress_masked(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn compress_auto(&self, decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::compress_auto(decompressed, compressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_auto(decompressed, compressed)
+                }
+            }
+        }
+    }
+
+    fn compress_sorted_auto(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::compress_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::compress_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+            }
+        }
+    }
+
+    fn compress_strictly_sorted_auto(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::compress_strictly_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_strictly_sorted_auto(
+                        initial,
+                        decompressed,
+                        compressed,
+                    )
+                }
+            }
+        }
+    }
+
+    fn compress_for(
+        &self,
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::compress_for(
+                    reference,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::compress_for(
+                    reference,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
     fn compress_sorted(
         &self,
         initial: u32,
@@ -381,6 +616,322 @@ impl BitPacker for BitPacker8x {
         }
     }
 
+    fn decompress_scaled(
+        &self,
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn num_bits_sorted_checked(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn num_bits_strictly_sorted_checked(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::num_bits_strictly_sorted_checked(
+                        initial,
+                        decompressed,
+                    )
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::num_bits_strictly_sorted_checked(
+                        initial,
+                        decompressed,
+                    )
+                }
+            }
+        }
+    }
+
+    fn num_bits_all_modes(&self, initial: u32, decompressed: &[u32]) -> BlockWidths {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::num_bits_all_modes(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::num_bits_all_modes(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn compress_u8(&self, decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn compress_u16(&self, decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_into_u8(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::decompress_into_u8(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_into_u8(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_into_u16(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::decompress_into_u16(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_into_u16(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_into_u64(
+        &self,
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_sorted_into_u64(
+        &self,
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn compress_uninit<'a>(
+        &self,
+        decompressed: &[u32],
+        compressed: &'a mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> &'a mut [u8] {
+        unsafe {
+            let compressed_len = match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+            };
+            assume_init_prefix(compressed, compressed_len)
+        }
+    }
+
+    fn decompress_uninit<'a>(
+        &self,
+        compressed: &[u8],
+        decompressed: &'a mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> &'a mut [u32] {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::decompress_uninit(compressed, decompressed, num_bits)
+                }
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_uninit(
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            };
+            assume_init_prefix(decompressed, Self::BLOCK_LEN)
+        }
+    }
+
+    fn decompress_to_sink<TSink: Sink>(
+        &self,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::UnsafeBitPackerImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+                InstructionSet::Scalar => scalar::UnsafeBitPackerImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::UnsafeBitPackerImpl::decompress_strictly_sorted_to_sink(
+                        initial, compressed, sink, num_bits,
+                    )
+                }
+                InstructionSet::Scalar => {
+                    scalar::UnsafeBitPackerImpl::decompress_strictly_sorted_to_sink(
+                        initial, compressed, sink, num_bits,
+                    )
+                }
+            }
+        }
+    }
+
     fn num_bits(&self, decompressed: &[u32]) -> u8 {
         unsafe {
             match self.0 {
@@ -425,7 +976,7 @@ impl BitPacker for BitPacker8x {
 mod tests {
     use super::BLOCK_LEN;
     use super::{avx2, scalar};
-    use crate::tests::test_util_compatible;
+    use crate::tests::{test_util_compatible, test_util_compress_masked_compatible};
     use crate::Available;
 
     #[test]
@@ -434,6 +985,10 @@ mod tests {
             test_util_compatible::<scalar::UnsafeBitPackerImpl, avx2::UnsafeBitPackerImpl>(
                 BLOCK_LEN,
             );
+            test_util_compress_masked_compatible::<
+                scalar::UnsafeBitPackerImpl,
+                avx2::UnsafeBitPackerImpl,
+            >(BLOCK_LEN);
         }
     }
 }
@@ -0,0 +1,1990 @@
+use super::{
+    assume_init_prefix, private, BitPacker, BlockWidths, CompressError, Sink, UnsafeBitPacker,
+};
+use crate::{as_uninit, bitmap, first_unsorted, Available};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+
+// A block is packed as 32 rows of 1024 bits, with the `u32` words of the 32 lanes
+// interleaved, as in FastLanes. Narrower registers process a row in several steps,
+// so that the compressed layout does not depend on the instruction set.
+//
+// The rows do not hold the integers in order. Plain blocks follow FastLanes' `04261537`
+// order, and sorted blocks are transposed first, so that each lane holds a run of 32
+// consecutive integers and is delta-encoded independently of the other lanes.
+const BLOCK_LEN: usize = 32 * 32;
+
+/// FastLanes' order of the rows within a group of 8.
+const FL_ORDER: [usize; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// Index of the integer packed in `lane` of `row`, as in FastLanes' `iterate!`.
+const fn fl_index(row: usize, lane: usize) -> usize {
+    FL_ORDER[row / 8] * 16 + (row % 8) * 128 + lane
+}
+
+/// FastLanes' transposition: the integer moved to index `idx` of a transposed block.
+const fn fl_transpose(idx: usize) -> usize {
+    (idx % 16) * 64 + FL_ORDER[(idx / 16) % 8] * 8 + idx / 128
+}
+
+/// First of the 32 consecutive integers held by each row of a plain block.
+const ROW_STARTS: [usize; 32] = {
+    let mut starts = [0; 32];
+    let mut row = 0;
+    while row < 32 {
+        starts[row] = fl_index(row, 0);
+        row += 1;
+    }
+    starts
+};
+
+/// First of the 32 consecutive integers held by each lane of a sorted block.
+const LANE_STARTS: [usize; 32] = {
+    let mut starts = [0; 32];
+    let mut lane = 0;
+    while lane < 32 {
+        starts[lane] = fl_transpose(fl_index(0, lane));
+        lane += 1;
+    }
+    starts
+};
+
+/// Lane holding each run of 32 consecutive integers of a sorted block.
+const RUN_LANES: [usize; 32] = {
+    let mut lanes = [0; 32];
+    let mut lane = 0;
+    while lane < 32 {
+        lanes[LANE_STARTS[lane] / 32] = lane;
+        lane += 1;
+    }
+    lanes
+};
+
+fn assert_block_len<T>(decompressed: &[T]) {
+    assert_eq!(
+        decompressed.len(),
+        BLOCK_LEN,
+        "`decompressed`'s len is not `BLOCK_LEN={}`",
+        BLOCK_LEN
+    );
+}
+
+fn assert_output_len<T>(decompressed: &[T]) {
+    assert!(
+        decompressed.len() >= BLOCK_LEN,
+        "The output array is not large enough : ({} >= {})",
+        decompressed.len(),
+        BLOCK_LEN
+    );
+}
+
+/// Reorders a plain block into its rows.
+fn to_rows<T: Copy + Default>(decompressed: &[T]) -> [T; BLOCK_LEN] {
+    assert_block_len(decompressed);
+    let mut rows = [T::default(); BLOCK_LEN];
+    for (row, &start) in rows.chunks_exact_mut(32).zip(ROW_STARTS.iter()) {
+        row.copy_from_slice(&decompressed[start..start + 32]);
+    }
+    rows
+}
+
+/// Puts the integers of the rows of a plain block back in order.
+fn from_rows<T: Copy>(rows: &[T; BLOCK_LEN], decompressed: &mut [MaybeUninit<T>]) {
+    assert_output_len(decompressed);
+    for (row, &start) in rows.chunks_exact(32).zip(ROW_STARTS.iter()) {
+        for (dst, &val) in decompressed[start..start + 32].iter_mut().zip(row) {
+            dst.write(val);
+        }
+    }
+}
+
+/// Transposes a sorted block into its lanes. The integers are taken relatively to
+/// the one preceding the run of their lane, `initial` for the first run: the row
+/// packer then computes the deltas of each lane from `0`.
+fn to_lanes(initial: u32, decompressed: &[u32]) -> [u32; BLOCK_LEN] {
+    assert_block_len(decompressed);
+    let mut lanes = [0u32; BLOCK_LEN];
+    for (lane, &start) in LANE_STARTS.iter().enumerate() {
+        let base = if start == 0 {
+            initial
+        } else {
+            decompressed[start - 1]
+        };
+        for (row, &val) in decompressed[start..start + 32].iter().enumerate() {
+            lanes[row * 32 + lane] = val.wrapping_sub(base);
+        }
+    }
+    lanes
+}
+
+/// Puts the integers of the lanes of a sorted block back in order. The lanes are
+/// integrated independently: only their bases, the last integer of the previous
+/// run, are chained.
+fn from_lanes(initial: u32, lanes: &[u32; BLOCK_LEN], decompressed: &mut [u32]) {
+    assert_output_len(decompressed);
+    let mut base = initial;
+    for (run, &lane) in decompressed.chunks_exact_mut(32).zip(RUN_LANES.iter()) {
+        for (row, dst) in run.iter_mut().enumerate() {
+            *dst = base.wrapping_add(lanes[row * 32 + lane]);
+        }
+        base = run[31];
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+
+    use super::BLOCK_LEN;
+    use crate::Available;
+
+    // x86 is little-endian: compressed blocks are stored as is.
+    use self::load_unaligned as load_compressed;
+    use self::store_unaligned as store_compressed;
+    use std::arch::x86_64::__m256i;
+    use std::arch::x86_64::{
+        __m128i, _mm256_add_epi32, _mm256_add_epi64, _mm256_and_si256, _mm256_castsi256_si128,
+        _mm256_cmpgt_epi32, _mm256_cvtepu16_epi32, _mm256_cvtepu32_epi64, _mm256_cvtepu8_epi32,
+        _mm256_extract_epi32, _mm256_extracti128_si256, _mm256_lddqu_si256, _mm256_mullo_epi32,
+        _mm256_or_si256, _mm256_packus_epi16, _mm256_packus_epi32, _mm256_permute4x64_epi64,
+        _mm256_set1_epi32, _mm256_set1_epi64x, _mm256_slli_epi32, _mm256_srli_epi32,
+        _mm256_srli_si256, _mm256_storeu_si256, _mm256_sub_epi32, _mm256_xor_si256,
+        _mm_loadl_epi64, _mm_loadu_si128,
+    };
+
+    type DataType = [__m256i; 4];
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn set1(el: i32) -> DataType {
+        [_mm256_set1_epi32(el); 4]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn right_shift_32<const N: i32>(el: DataType) -> DataType {
+        [
+            _mm256_srli_epi32::<N>(el[0]),
+            _mm256_srli_epi32::<N>(el[1]),
+            _mm256_srli_epi32::<N>(el[2]),
+            _mm256_srli_epi32::<N>(el[3]),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn left_shift_32<const N: i32>(el: DataType) -> DataType {
+        [
+            _mm256_slli_epi32::<N>(el[0]),
+            _mm256_slli_epi32::<N>(el[1]),
+            _mm256_slli_epi32::<N>(el[2]),
+            _mm256_slli_epi32::<N>(el[3]),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn op_or(left: DataType, right: DataType) -> DataType {
+        [
+            _mm256_or_si256(left[0], right[0]),
+            _mm256_or_si256(left[1], right[1]),
+            _mm256_or_si256(left[2], right[2]),
+            _mm256_or_si256(left[3], right[3]),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn op_and(left: DataType, right: DataType) -> DataType {
+        [
+            _mm256_and_si256(left[0], right[0]),
+            _mm256_and_si256(left[1], right[1]),
+            _mm256_and_si256(left[2], right[2]),
+            _mm256_and_si256(left[3], right[3]),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn load_unaligned(addr: *const DataType) -> DataType {
+        let addr = addr.cast::<__m256i>();
+        [
+            _mm256_lddqu_si256(addr),
+            _mm256_lddqu_si256(addr.add(1)),
+            _mm256_lddqu_si256(addr.add(2)),
+            _mm256_lddqu_si256(addr.add(3)),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_unaligned(dst: *mut DataType, data: DataType) {
+        let dst = dst.cast::<__m256i>();
+        _mm256_storeu_si256(dst, data[0]);
+        _mm256_storeu_si256(dst.add(1), data[1]);
+        _mm256_storeu_si256(dst.add(2), data[2]);
+        _mm256_storeu_si256(dst.add(3), data[3]);
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[allow(non_snake_case)]
+    unsafe fn or_collapse_to_u32(accumulator: DataType) -> u32 {
+        let a__b__c__d__e__f__g__h_ = _mm256_or_si256(
+            _mm256_or_si256(accumulator[0], accumulator[1]),
+            _mm256_or_si256(accumulator[2], accumulator[3]),
+        );
+        let ______a__b________e__f = _mm256_srli_si256(a__b__c__d__e__f__g__h_, 8);
+        let a__b__ca_db_e__f__ge_hf =
+            _mm256_or_si256(a__b__c__d__e__f__g__h_, ______a__b________e__f);
+        let ___a__b__ca____e__f__ge = _mm256_srli_si256(a__b__ca_db_e__f__ge_hf, 4);
+        let _________cadb______gehf =
+            _mm256_or_si256(a__b__ca_db_e__f__ge_hf, ___a__b__ca____e__f__ge);
+        let cadb = _mm256_extract_epi32(_________cadb______gehf, 0);
+        let gehf = _mm256_extract_epi32(_________cadb______gehf, 4);
+        (cadb | gehf) as u32
+    }
+
+    /// AVX2 only compares signed integers: flipping the sign bits maps the unsigned
+    /// order onto the signed one.
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let sign = _mm256_set1_epi32(i32::MIN);
+        let mut result = left;
+        for (result, right) in result.iter_mut().zip(right) {
+            *result = _mm256_cmpgt_epi32(
+                _mm256_xor_si256(*result, sign),
+                _mm256_xor_si256(right, sign),
+            );
+        }
+        result
+    }
+
+    /// The deltas are taken between rows: each lane has its own.
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn compute_delta(curr: DataType, prev: DataType) -> DataType {
+        sub(curr, prev)
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn integrate_delta(prev: DataType, delta: DataType) -> DataType {
+        add(prev, delta)
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn add(left: DataType, right: DataType) -> DataType {
+        [
+            _mm256_add_epi32(left[0], right[0]),
+            _mm256_add_epi32(left[1], right[1]),
+            _mm256_add_epi32(left[2], right[2]),
+            _mm256_add_epi32(left[3], right[3]),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn sub(left: DataType, right: DataType) -> DataType {
+        [
+            _mm256_sub_epi32(left[0], right[0]),
+            _mm256_sub_epi32(left[1], right[1]),
+            _mm256_sub_epi32(left[2], right[2]),
+            _mm256_sub_epi32(left[3], right[3]),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn mul(left: DataType, right: DataType) -> DataType {
+        [
+            _mm256_mullo_epi32(left[0], right[0]),
+            _mm256_mullo_epi32(left[1], right[1]),
+            _mm256_mullo_epi32(left[2], right[2]),
+            _mm256_mullo_epi32(left[3], right[3]),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        let addr = addr.cast::<__m128i>();
+        [
+            _mm256_cvtepu8_epi32(_mm_loadl_epi64(addr)),
+            _mm256_cvtepu8_epi32(_mm_loadl_epi64(addr.byte_add(8))),
+            _mm256_cvtepu8_epi32(_mm_loadl_epi64(addr.byte_add(16))),
+            _mm256_cvtepu8_epi32(_mm_loadl_epi64(addr.byte_add(24))),
+        ]
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        let addr = addr.cast::<__m128i>();
+        [
+            _mm256_cvtepu16_epi32(_mm_loadu_si128(addr)),
+            _mm256_cvtepu16_epi32(_mm_loadu_si128(addr.add(1))),
+            _mm256_cvtepu16_epi32(_mm_loadu_si128(addr.add(2))),
+            _mm256_cvtepu16_epi32(_mm_loadu_si128(addr.add(3))),
+        ]
+    }
+
+    /// `_mm256_packus_epi32` and `_mm256_packus_epi16` pack each 128-bit half
+    /// separately: the 64-bit groups are put back in order afterwards.
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn narrow_to_u16(low: __m256i, high: __m256i) -> __m256i {
+        _mm256_permute4x64_epi64::<0b11_01_10_00>(_mm256_packus_epi32(low, high))
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        let low = narrow_to_u16(data[0], data[1]);
+        let high = narrow_to_u16(data[2], data[3]);
+        let bytes = _mm256_permute4x64_epi64::<0b11_01_10_00>(_mm256_packus_epi16(low, high));
+        _mm256_storeu_si256(dst.cast::<__m256i>(), bytes);
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        let dst = dst.cast::<__m256i>();
+        _mm256_storeu_si256(dst, narrow_to_u16(data[0], data[1]));
+        _mm256_storeu_si256(dst.add(1), narrow_to_u16(data[2], data[3]));
+    }
+
+    /// Widens a register to `u64`, and adds `base` to its integers.
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        let dst = dst.cast::<__m256i>();
+        let base = _mm256_set1_epi64x(base as i64);
+        for (i, &register) in data.iter().enumerate() {
+            let low = _mm256_cvtepu32_epi64(_mm256_castsi256_si128(register));
+            let high = _mm256_cvtepu32_epi64(_mm256_extracti128_si256::<1>(register));
+            _mm256_storeu_si256(dst.add(2 * i), _mm256_add_epi64(base, low));
+            _mm256_storeu_si256(dst.add(2 * i + 1), _mm256_add_epi64(base, high));
+        }
+    }
+
+    declare_bitpacker!(target_feature(enable = "avx2"), FastLanesImpl);
+
+    pub(super) type FastLanesImpl = super::FastLanes<UnsafeBitPackerImpl>;
+
+    impl Available for UnsafeBitPackerImpl {
+        fn available() -> bool {
+            is_x86_feature_detected!("avx2")
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse3 {
+
+    use super::BLOCK_LEN;
+    use crate::Available;
+
+    // x86 is little-endian: compressed blocks are stored as is.
+    use self::load_unaligned as load_compressed;
+    use self::store_unaligned as store_compressed;
+    use std::arch::x86_64::__m128i;
+    use std::arch::x86_64::{
+        _mm_add_epi32, _mm_add_epi64, _mm_and_si128, _mm_cmpgt_epi32, _mm_cvtsi128_si32,
+        _mm_lddqu_si128, _mm_mul_epu32, _mm_or_si128, _mm_packs_epi32, _mm_packus_epi16,
+        _mm_set1_epi16, _mm_set1_epi32, _mm_set1_epi64x, _mm_setzero_si128, _mm_shuffle_epi32,
+        _mm_slli_epi32, _mm_srli_epi32, _mm_srli_si128, _mm_storeu_si128, _mm_sub_epi32,
+        _mm_unpackhi_epi16, _mm_unpackhi_epi32, _mm_unpackhi_epi8, _mm_unpacklo_epi16,
+        _mm_unpacklo_epi32, _mm_unpacklo_epi8, _mm_xor_si128,
+    };
+
+    type DataType = [__m128i; 8];
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn set1(el: i32) -> DataType {
+        [_mm_set1_epi32(el); 8]
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn right_shift_32<const N: i32>(mut el: DataType) -> DataType {
+        for register in el.iter_mut() {
+            *register = _mm_srli_epi32::<N>(*register);
+        }
+        el
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn left_shift_32<const N: i32>(mut el: DataType) -> DataType {
+        for register in el.iter_mut() {
+            *register = _mm_slli_epi32::<N>(*register);
+        }
+        el
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn op_or(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left = _mm_or_si128(*left, right);
+        }
+        left
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn op_and(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left = _mm_and_si128(*left, right);
+        }
+        left
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn load_unaligned(addr: *const DataType) -> DataType {
+        let addr = addr.cast::<__m128i>();
+        let mut data = set1(0);
+        for (i, register) in data.iter_mut().enumerate() {
+            *register = _mm_lddqu_si128(addr.add(i));
+        }
+        data
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn store_unaligned(dst: *mut DataType, data: DataType) {
+        let dst = dst.cast::<__m128i>();
+        for (i, register) in data.into_iter().enumerate() {
+            _mm_storeu_si128(dst.add(i), register);
+        }
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[allow(non_snake_case)]
+    unsafe fn or_collapse_to_u32(accumulator: DataType) -> u32 {
+        let a__b__c__d_ = accumulator
+            .into_iter()
+            .fold(_mm_set1_epi32(0), |acc, register| {
+                _mm_or_si128(acc, register)
+            });
+        let ______a__b_ = _mm_srli_si128(a__b__c__d_, 8);
+        let a__b__ca_db = _mm_or_si128(a__b__c__d_, ______a__b_);
+        let ___a__b__ca = _mm_srli_si128(a__b__ca_db, 4);
+        let _______cadb = _mm_or_si128(a__b__ca_db, ___a__b__ca);
+        _mm_cvtsi128_si32(_______cadb) as u32
+    }
+
+    /// SSE only compares signed integers: flipping the sign bits maps the unsigned
+    /// order onto the signed one.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let sign = _mm_set1_epi32(i32::MIN);
+        let mut result = left;
+        for (result, right) in result.iter_mut().zip(right) {
+            *result = _mm_cmpgt_epi32(_mm_xor_si128(*result, sign), _mm_xor_si128(right, sign));
+        }
+        result
+    }
+
+    /// The deltas are taken between rows: each lane has its own.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn compute_delta(curr: DataType, prev: DataType) -> DataType {
+        sub(curr, prev)
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn integrate_delta(prev: DataType, delta: DataType) -> DataType {
+        add(prev, delta)
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn add(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left = _mm_add_epi32(*left, right);
+        }
+        left
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn sub(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left = _mm_sub_epi32(*left, right);
+        }
+        left
+    }
+
+    // Same emulation of `_mm_mullo_epi32` as `BitPacker4x`'s.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn mul(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            let even = _mm_mul_epu32(*left, right);
+            let odd = _mm_mul_epu32(_mm_srli_si128(*left, 4), _mm_srli_si128(right, 4));
+            *left = _mm_unpacklo_epi32(
+                _mm_shuffle_epi32(even, 0b00_00_10_00),
+                _mm_shuffle_epi32(odd, 0b00_00_10_00),
+            );
+        }
+        left
+    }
+
+    // `_mm_cvtepu8_epi32` and `_mm_packus_epi32` require SSE4.1: the integers are
+    // widened by interleaving them with zeros, and narrowed with signed saturations.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        let addr = addr.cast::<__m128i>();
+        let zero = _mm_setzero_si128();
+        let mut register = set1(0);
+        for (i, quarter) in register.chunks_exact_mut(4).enumerate() {
+            let bytes = _mm_lddqu_si128(addr.add(i));
+            let low = _mm_unpacklo_epi8(bytes, zero);
+            let high = _mm_unpackhi_epi8(bytes, zero);
+            quarter[0] = _mm_unpacklo_epi16(low, zero);
+            quarter[1] = _mm_unpackhi_epi16(low, zero);
+            quarter[2] = _mm_unpacklo_epi16(high, zero);
+            quarter[3] = _mm_unpackhi_epi16(high, zero);
+        }
+        register
+    }
+
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        let addr = addr.cast::<__m128i>();
+        let zero = _mm_setzero_si128();
+        let mut register = set1(0);
+        for (i, half) in register.chunks_exact_mut(2).enumerate() {
+            let words = _mm_lddqu_si128(addr.add(i));
+            half[0] = _mm_unpacklo_epi16(words, zero);
+            half[1] = _mm_unpackhi_epi16(words, zero);
+        }
+        register
+    }
+
+    /// The integers fit in an `u8`: the signed saturations leave them unchanged.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        let dst = dst.cast::<__m128i>();
+        for (i, quarter) in data.chunks_exact(4).enumerate() {
+            let low = _mm_packs_epi32(quarter[0], quarter[1]);
+            let high = _mm_packs_epi32(quarter[2], quarter[3]);
+            _mm_storeu_si128(dst.add(i), _mm_packus_epi16(low, high));
+        }
+    }
+
+    /// The integers fit in an `u16`: they are biased into the range of `i16` before the
+    /// signed saturation, and the bias is flipped back afterwards.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        let dst = dst.cast::<__m128i>();
+        let bias = _mm_set1_epi32(0x8000);
+        for (i, half) in data.chunks_exact(2).enumerate() {
+            let low = _mm_sub_epi32(half[0], bias);
+            let high = _mm_sub_epi32(half[1], bias);
+            let words = _mm_xor_si128(_mm_packs_epi32(low, high), _mm_set1_epi16(i16::MIN));
+            _mm_storeu_si128(dst.add(i), words);
+        }
+    }
+
+    /// Widens a register to `u64` by interleaving it with zeros, and adds `base` to its
+    /// integers.
+    #[target_feature(enable = "sse3")]
+    #[inline]
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        let dst = dst.cast::<__m128i>();
+        let zero = _mm_setzero_si128();
+        let base = _mm_set1_epi64x(base as i64);
+        for (i, &register) in data.iter().enumerate() {
+            let low = _mm_unpacklo_epi32(register, zero);
+            let high = _mm_unpackhi_epi32(register, zero);
+            _mm_storeu_si128(dst.add(2 * i), _mm_add_epi64(base, low));
+            _mm_storeu_si128(dst.add(2 * i + 1), _mm_add_epi64(base, high));
+        }
+    }
+
+    declare_bitpacker!(target_feature(enable = "sse3"), FastLanesImpl);
+
+    pub(super) type FastLanesImpl = super::FastLanes<UnsafeBitPackerImpl>;
+
+    impl Available for UnsafeBitPackerImpl {
+        fn available() -> bool {
+            is_x86_feature_detected!("sse3")
+        }
+    }
+}
+
+mod scalar {
+
+    use super::BLOCK_LEN;
+    use crate::Available;
+    use std::ptr;
+
+    type DataType = [u32; 32];
+
+    fn set1(el: i32) -> DataType {
+        [el as u32; 32]
+    }
+
+    fn right_shift_32<const N: i32>(el: DataType) -> DataType {
+        el.map(|lane| lane >> N)
+    }
+
+    fn left_shift_32<const N: i32>(el: DataType) -> DataType {
+        el.map(|lane| lane << N)
+    }
+
+    fn op_or(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left |= right;
+        }
+        left
+    }
+
+    fn op_and(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left &= right;
+        }
+        left
+    }
+
+    unsafe fn load_unaligned(addr: *const DataType) -> DataType {
+        ptr::read_unaligned(addr)
+    }
+
+    unsafe fn store_unaligned(dst: *mut DataType, data: DataType) {
+        ptr::write_unaligned(dst, data);
+    }
+
+    /// Compressed blocks are little-endian, whatever the target.
+    unsafe fn load_compressed(addr: *const DataType) -> DataType {
+        load_unaligned(addr).map(u32::from_le)
+    }
+
+    unsafe fn store_compressed(dst: *mut DataType, data: DataType) {
+        store_unaligned(dst, data.map(u32::to_le));
+    }
+
+    fn or_collapse_to_u32(accumulator: DataType) -> u32 {
+        accumulator.into_iter().fold(0, |acc, lane| acc | lane)
+    }
+
+    fn op_gt_u32(left: DataType, right: DataType) -> DataType {
+        let mut result = left;
+        for (result, right) in result.iter_mut().zip(right) {
+            *result = if *result > right { u32::MAX } else { 0 };
+        }
+        result
+    }
+
+    /// The deltas are taken between rows: each lane has its own.
+    fn compute_delta(curr: DataType, prev: DataType) -> DataType {
+        sub(curr, prev)
+    }
+
+    fn integrate_delta(prev: DataType, delta: DataType) -> DataType {
+        add(prev, delta)
+    }
+
+    fn add(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left = left.wrapping_add(right);
+        }
+        left
+    }
+
+    fn sub(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left = left.wrapping_sub(right);
+        }
+        left
+    }
+
+    fn mul(mut left: DataType, right: DataType) -> DataType {
+        for (left, right) in left.iter_mut().zip(right) {
+            *left = left.wrapping_mul(right);
+        }
+        left
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u8` into a register.
+    unsafe fn load_u8(addr: *const u8) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Widens `BLOCK_LEN / 32` consecutive `u16` into a register.
+    unsafe fn load_u16(addr: *const u16) -> DataType {
+        std::array::from_fn(|i| *addr.add(i) as u32)
+    }
+
+    /// Narrows a register whose integers fit in an `u8`.
+    unsafe fn store_u8(dst: *mut u8, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u8;
+        }
+    }
+
+    /// Narrows a register whose integers fit in an `u16`.
+    unsafe fn store_u16(dst: *mut u16, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = val as u16;
+        }
+    }
+
+    /// Widens a register to `u64`, and adds `base` to its integers.
+    unsafe fn store_u64(dst: *mut u64, base: u64, data: DataType) {
+        for (i, &val) in data.iter().enumerate() {
+            *dst.add(i) = base.wrapping_add(val as u64);
+        }
+    }
+
+    // The `cfg(any(debug, not(debug)))` is here to put an attribute that has no effect.
+    //
+    // For other bitpacker, we enable specific CPU instruction set, but for the
+    // scalar bitpacker none is required.
+    declare_bitpacker!(cfg(any(debug, not(debug))), FastLanesImpl);
+
+    pub(super) type FastLanesImpl = super::FastLanes<UnsafeBitPackerImpl>;
+
+    impl Available for UnsafeBitPackerImpl {
+        fn available() -> bool {
+            true
+        }
+    }
+}
+
+/// Implements FastLanes' layout on top of a packer of rows, `TRows`, whose deltas are
+/// taken lane by lane between consecutive rows.
+struct FastLanes<TRows>(PhantomData<TRows>);
+
+impl<TRows: Available> Available for FastLanes<TRows> {
+    fn available() -> bool {
+        TRows::available()
+    }
+}
+
+impl<TRows: UnsafeBitPacker> UnsafeBitPacker for FastLanes<TRows> {
+    const BLOCK_LEN: usize = BLOCK_LEN;
+
+    unsafe fn compress(decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        TRows::compress(&to_rows(decompressed), compressed, num_bits)
+    }
+
+    unsafe fn compress_sorted(
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        TRows::compress_sorted(0, &to_lanes(initial, decompressed), compressed, num_bits)
+    }
+
+    unsafe fn compress_strictly_sorted(
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        // As for the other layouts, `None` wraps around to `u32::MAX`.
+        let lanes = to_lanes(initial.unwrap_or(u32::MAX), decompressed);
+        TRows::compress_strictly_sorted(Some(0), &lanes, compressed, num_bits)
+    }
+
+    unsafe fn compress_for(
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        TRows::compress_for(reference, &to_rows(decompressed), compressed, num_bits)
+    }
+
+    unsafe fn compress_masked(decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        TRows::compress_masked(&to_rows(decompressed), compressed, num_bits)
+    }
+
+    unsafe fn compress_auto(decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+        TRows::compress_auto(&to_rows(decompressed), compressed)
+    }
+
+    unsafe fn compress_sorted_auto(
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        TRows::compress_sorted_auto(0, &to_lanes(initial, decompressed), compressed)
+    }
+
+    unsafe fn compress_strictly_sorted_auto(
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        let lanes = to_lanes(initial.unwrap_or(u32::MAX), decompressed);
+        TRows::compress_strictly_sorted_auto(Some(0), &lanes, compressed)
+    }
+
+    unsafe fn decompress(compressed: &[u8], decompressed: &mut [u32], num_bits: u8) -> usize {
+        Self::decompress_uninit(compressed, as_uninit(decompressed), num_bits)
+    }
+
+    unsafe fn compress_uninit(
+        decompressed: &[u32],
+        compressed: &mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> usize {
+        TRows::compress_uninit(&to_rows(decompressed), compressed, num_bits)
+    }
+
+    unsafe fn decompress_uninit(
+        compressed: &[u8],
+        decompressed: &mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> usize {
+        let mut rows = [0u32; BLOCK_LEN];
+        let read = TRows::decompress(compressed, &mut rows, num_bits);
+        from_rows(&rows, decompressed);
+        read
+    }
+
+    unsafe fn decompress_sorted(
+        initial: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        let mut lanes = [0u32; BLOCK_LEN];
+        let read = TRows::decompress_sorted(0, compressed, &mut lanes, num_bits);
+        from_lanes(initial, &lanes, decompressed);
+        read
+    }
+
+    unsafe fn decompress_strictly_sorted(
+        initial: Option<u32>,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        let mut lanes = [0u32; BLOCK_LEN];
+        let read = TRows::decompress_strictly_sorted(Some(0), compressed, &mut lanes, num_bits);
+        from_lanes(initial.unwrap_or(u32::MAX), &lanes, decompressed);
+        read
+    }
+
+    unsafe fn decompress_scaled(
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        let mut rows = [0u32; BLOCK_LEN];
+        let read = TRows::decompress_scaled(offset, factor, compressed, &mut rows, num_bits);
+        from_rows(&rows, as_uninit(decompressed));
+        read
+    }
+
+    unsafe fn compress_u8(decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+        TRows::compress_u8(&to_rows(decompressed), compressed, num_bits)
+    }
+
+    unsafe fn compress_u16(decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+        TRows::compress_u16(&to_rows(decompressed), compressed, num_bits)
+    }
+
+    unsafe fn decompress_into_u8(
+        compressed: &[u8],
+        decompressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        let mut rows = [0u8; BLOCK_LEN];
+        let read = TRows::decompress_into_u8(compressed, &mut rows, num_bits);
+        from_rows(&rows, as_uninit(decompressed));
+        read
+    }
+
+    unsafe fn decompress_into_u16(
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize {
+        let mut rows = [0u16; BLOCK_LEN];
+        let read = TRows::decompress_into_u16(compressed, &mut rows, num_bits);
+        from_rows(&rows, as_uninit(decompressed));
+        read
+    }
+
+    unsafe fn decompress_into_u64(
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        let mut rows = [0u64; BLOCK_LEN];
+        let read = TRows::decompress_into_u64(base, compressed, &mut rows, num_bits);
+        from_rows(&rows, as_uninit(decompressed));
+        read
+    }
+
+    unsafe fn decompress_sorted_into_u64(
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        assert_output_len(decompressed);
+        // The deltas are integrated in `u32`, relative to the initial value.
+        let mut relative = [0u32; BLOCK_LEN];
+        let read = Self::decompress_sorted(0, compressed, &mut relative, num_bits);
+        for (dst, &val) in decompressed.iter_mut().zip(relative.iter()) {
+            *dst = initial.wrapping_add(u64::from(val));
+        }
+        read
+    }
+
+    unsafe fn decompress_to_sink<TSink: Sink>(
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        let mut decompressed = [0u32; BLOCK_LEN];
+        let read = Self::decompress(compressed, &mut decompressed, num_bits);
+        for values in decompressed.chunks_exact(BLOCK_LEN / 32) {
+            sink.process(values);
+        }
+        read
+    }
+
+    unsafe fn decompress_sorted_to_sink<TSink: Sink>(
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        let mut decompressed = [0u32; BLOCK_LEN];
+        let read = Self::decompress_sorted(initial, compressed, &mut decompressed, num_bits);
+        for values in decompressed.chunks_exact(BLOCK_LEN / 32) {
+            sink.process(values);
+        }
+        read
+    }
+
+    unsafe fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        let mut decompressed = [0u32; BLOCK_LEN];
+        let read =
+            Self::decompress_strictly_sorted(initial, compressed, &mut decompressed, num_bits);
+        for values in decompressed.chunks_exact(BLOCK_LEN / 32) {
+            sink.process(values);
+        }
+        read
+    }
+
+    unsafe fn num_bits(decompressed: &[u32]) -> u8 {
+        TRows::num_bits(decompressed)
+    }
+
+    unsafe fn num_bits_sorted(initial: u32, decompressed: &[u32]) -> u8 {
+        TRows::num_bits_sorted(0, &to_lanes(initial, decompressed))
+    }
+
+    unsafe fn num_bits_strictly_sorted(initial: Option<u32>, decompressed: &[u32]) -> u8 {
+        let lanes = to_lanes(initial.unwrap_or(u32::MAX), decompressed);
+        TRows::num_bits_strictly_sorted(Some(0), &lanes)
+    }
+
+    unsafe fn num_bits_sorted_checked(
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        assert_block_len(decompressed);
+        match first_unsorted(Some(initial), false, decompressed) {
+            Some(index) => Err(CompressError::NotSorted { index }),
+            None => Ok(Self::num_bits_sorted(initial, decompressed)),
+        }
+    }
+
+    unsafe fn num_bits_strictly_sorted_checked(
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        assert_block_len(decompressed);
+        match first_unsorted(initial, true, decompressed) {
+            Some(index) => Err(CompressError::NotSorted { index }),
+            None => Ok(Self::num_bits_strictly_sorted(initial, decompressed)),
+        }
+    }
+
+    unsafe fn num_bits_all_modes(initial: u32, decompressed: &[u32]) -> BlockWidths {
+        // Only the sorted widths depend on the order of the integers.
+        let lanes = to_lanes(initial, decompressed);
+        BlockWidths {
+            sorted: TRows::num_bits_sorted(0, &lanes),
+            strictly_sorted: TRows::num_bits_strictly_sorted(Some(0), &lanes),
+            ..TRows::num_bits_all_modes(initial, decompressed)
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum InstructionSet {
+    #[cfg(target_arch = "x86_64")]
+    AVX2,
+    #[cfg(target_arch = "x86_64")]
+    SSE3,
+    Scalar,
+}
+
+/// `BitPackerFastLanes` packs blocks of 1024 integers in the unified transposed layout of
+/// [FastLanes](https://www.vldb.org/pvldb/vol16/p2132-afroozeh.pdf).
+///
+/// The block is packed as 32 rows of 1024 bits. Each of the 32 lanes of these virtual
+/// registers is bitpacked independently, so that any register width can decode it at
+/// SIMD speed: `AVX2` handles a row in 4 steps, `SSE3` in 8, and the scalar
+/// implementation relies on auto-vectorization. Unlike `BitPacker1x`, `BitPacker4x` and
+/// `BitPacker8x`, the compressed format is therefore the same whatever the instruction
+/// set used to produce it.
+///
+/// Plain blocks hold the words of FastLanes' `BitPacking::pack`: row `r` holds 32
+/// consecutive integers, in the `04261537` order of FastLanes. Sorted blocks go through
+/// FastLanes' transposition first, so that each lane holds a run of 32 consecutive
+/// integers, and their deltas are taken lane by lane. Rather than being stored aside,
+/// the base of each lane is the integer preceding its run: the lanes are integrated
+/// independently, and their bases are then chained run after run.
+///
+/// On top of the `BitPacker` methods, it offers a fused frame-of-reference variant
+/// (`.compress_for(..)`/`.decompress_for(..)`), subtracting and adding back a
+/// reference value while packing and unpacking registers. The sorted variants
+/// similarly fuse the delta computation into the packing kernels.
+///
+/// ```
+/// use bitpacking::{BitPacker, BitPackerFastLanes};
+///
+/// let timestamps: Vec<u32> = (0..BitPackerFastLanes::BLOCK_LEN as u32)
+///     .map(|i| 1_600_000_000 + i * 3 % 200)
+///     .collect();
+///
+/// let bitpacker = BitPackerFastLanes::new();
+/// let reference = *timestamps.iter().min().unwrap();
+/// let num_bits = bitpacker.num_bits_for(reference, &timestamps);
+/// assert_eq!(num_bits, 8);
+///
+/// let mut compressed = vec![0u8; BitPackerFastLanes::compressed_block_size(num_bits)];
+/// bitpacker.compress_for(reference, &timestamps, &mut compressed, num_bits);
+///
+/// let mut decompressed = vec![0u32; BitPackerFastLanes::BLOCK_LEN];
+/// bitpacker.decompress_for(reference, &compressed, &mut decompressed, num_bits);
+/// assert_eq!(timestamps, decompressed);
+/// ```
+#[derive(Clone, Copy)]
+pub struct BitPackerFastLanes(InstructionSet);
+
+impl private::Sealed for BitPackerFastLanes {}
+
+impl BitPacker for BitPackerFastLanes {
+    const BLOCK_LEN: usize = BLOCK_LEN;
+
+    fn new() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if avx2::FastLanesImpl::available() {
+                return BitPackerFastLanes(InstructionSet::AVX2);
+            }
+            if sse3::FastLanesImpl::available() {
+                return BitPackerFastLanes(InstructionSet::SSE3);
+            }
+        }
+        BitPackerFastLanes(InstructionSet::Scalar)
+    }
+
+    fn compress(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress(decompressed, compressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::compress(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn compress_masked(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::compress_masked(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn compress_auto(&self, decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress_auto(decompressed, compressed)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress_auto(decompressed, compressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::compress_auto(decompressed, compressed)
+                }
+            }
+        }
+    }
+
+    fn compress_sorted_auto(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress_sorted_auto(initial, decompressed, compressed)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress_sorted_auto(initial, decompressed, compressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::compress_sorted_auto(initial, decompressed, compressed)
+                }
+            }
+        }
+    }
+
+    fn compress_strictly_sorted_auto(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize) {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::compress_strictly_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::compress_strictly_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::compress_strictly_sorted_auto(
+                    initial,
+                    decompressed,
+                    compressed,
+                ),
+            }
+        }
+    }
+
+    fn compress_for(
+        &self,
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress_for(reference, decompressed, compressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress_for(reference, decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => scalar::FastLanesImpl::compress_for(
+                    reference,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn compress_sorted(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::compress_sorted(
+                    initial,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::compress_sorted(
+                    initial,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::compress_sorted(
+                    initial,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn compress_strictly_sorted(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::compress_strictly_sorted(
+                    initial,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::compress_strictly_sorted(
+                    initial,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::compress_strictly_sorted(
+                    initial,
+                    decompressed,
+                    compressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress(&self, compressed: &[u8], decompressed: &mut [u32], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::decompress(compressed, decompressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::decompress(compressed, decompressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::decompress(compressed, decompressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_sorted(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::decompress_sorted(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::decompress_sorted(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::decompress_sorted(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_strictly_sorted(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::decompress_strictly_sorted(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::decompress_strictly_sorted(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::decompress_strictly_sorted(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_scaled(
+        &self,
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::decompress_scaled(
+                    offset,
+                    factor,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn num_bits_sorted_checked(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::num_bits_sorted_checked(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn num_bits_strictly_sorted_checked(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError> {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::num_bits_strictly_sorted_checked(initial, decompressed)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::num_bits_strictly_sorted_checked(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::num_bits_strictly_sorted_checked(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn num_bits_all_modes(&self, initial: u32, decompressed: &[u32]) -> BlockWidths {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::num_bits_all_modes(initial, decompressed)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::num_bits_all_modes(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::num_bits_all_modes(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn compress_u8(&self, decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::compress_u8(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn compress_u16(&self, decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::compress_u16(decompressed, compressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_into_u8(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::decompress_into_u8(compressed, decompressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::decompress_into_u8(compressed, decompressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::decompress_into_u8(compressed, decompressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_into_u16(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::decompress_into_u16(compressed, decompressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::decompress_into_u16(compressed, decompressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::decompress_into_u16(compressed, decompressed, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_into_u64(
+        &self,
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::decompress_into_u64(
+                    base,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_sorted_into_u64(
+        &self,
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::decompress_sorted_into_u64(
+                    initial,
+                    compressed,
+                    decompressed,
+                    num_bits,
+                ),
+            }
+        }
+    }
+
+    fn compress_uninit<'a>(
+        &self,
+        decompressed: &[u32],
+        compressed: &'a mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> &'a mut [u8] {
+        unsafe {
+            let compressed_len = match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::compress_uninit(decompressed, compressed, num_bits)
+                }
+            };
+            assume_init_prefix(compressed, compressed_len)
+        }
+    }
+
+    fn decompress_uninit<'a>(
+        &self,
+        compressed: &[u8],
+        decompressed: &'a mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> &'a mut [u32] {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::decompress_uninit(compressed, decompressed, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::decompress_uninit(compressed, decompressed, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::decompress_uninit(compressed, decompressed, num_bits)
+                }
+            };
+            assume_init_prefix(decompressed, Self::BLOCK_LEN)
+        }
+    }
+
+    fn decompress_to_sink<TSink: Sink>(
+        &self,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::decompress_to_sink(compressed, sink, num_bits)
+                }
+            }
+        }
+    }
+
+    fn decompress_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+                InstructionSet::Scalar => scalar::FastLanesImpl::decompress_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+            }
+        }
+    }
+
+    fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::decompress_strictly_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::decompress_strictly_sorted_to_sink(
+                    initial, compressed, sink, num_bits,
+                ),
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::decompress_strictly_sorted_to_sink(
+                        initial, compressed, sink, num_bits,
+                    )
+                }
+            }
+        }
+    }
+
+    fn num_bits(&self, decompressed: &[u32]) -> u8 {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::num_bits(decompressed),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::num_bits(decompressed),
+                InstructionSet::Scalar => scalar::FastLanesImpl::num_bits(decompressed),
+            }
+        }
+    }
+
+    fn num_bits_sorted(&self, initial: u32, decompressed: &[u32]) -> u8 {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => avx2::FastLanesImpl::num_bits_sorted(initial, decompressed),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => sse3::FastLanesImpl::num_bits_sorted(initial, decompressed),
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::num_bits_sorted(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn num_bits_strictly_sorted(&self, initial: Option<u32>, decompressed: &[u32]) -> u8 {
+        unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::AVX2 => {
+                    avx2::FastLanesImpl::num_bits_strictly_sorted(initial, decompressed)
+                }
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE3 => {
+                    sse3::FastLanesImpl::num_bits_strictly_sorted(initial, decompressed)
+                }
+                InstructionSet::Scalar => {
+                    scalar::FastLanesImpl::num_bits_strictly_sorted(initial, decompressed)
+                }
+            }
+        }
+    }
+
+    fn decompress_positions(&self, compressed: &[u8], positions: &mut [u32]) -> usize {
+        bitmap::decompress_row_positions(compressed, positions, &ROW_STARTS)
+    }
+
+    fn compress_positions(&self, positions: &[u32], compressed: &mut [u8]) -> usize {
+        bitmap::compress_row_positions(positions, compressed, &ROW_STARTS)
+    }
+}
+
+impl BitPackerFastLanes {
+    /// Returns the number of bits required to pack `decompressed` relatively to
+    /// `reference`, using `.compress_for(..)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    #[must_use]
+    pub fn num_bits_for(&self, reference: u32, decompressed: &[u32]) -> u8 {
+        assert_eq!(
+            decompressed.len(),
+            BLOCK_LEN,
+            "`decompressed`'s len is not `BLOCK_LEN={}`",
+            BLOCK_LEN
+        );
+        let accumulator = decompressed
+            .iter()
+            .fold(0u32, |acc, &val| acc | val.wrapping_sub(reference));
+        crate::most_significant_bit(accumulator)
+    }
+
+    /// Decompresses a block compressed with `.compress_for(..)`, adding `reference`
+    /// back in-register.
+    ///
+    /// Returns the amount of bytes of the compressed block.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed array is too short to contain `BLOCK_LEN` elements.
+    /// - Panics if the decompressed array is too small.
+    pub fn decompress_for(
+        &self,
+        reference: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        self.decompress_scaled(reference, 1, compressed, decompressed, num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "x86_64")]
+    use super::{avx2, scalar, sse3};
+    use super::{fl_index, fl_transpose, BitPackerFastLanes, BLOCK_LEN, LANE_STARTS};
+    use crate::tests::generate_array;
+    #[cfg(target_arch = "x86_64")]
+    use crate::tests::{test_util_compatible, test_util_compress_masked_compatible};
+    #[cfg(target_arch = "x86_64")]
+    use crate::Available;
+    use crate::BitPacker;
+
+    /// FastLanes' `BitPacking::pack`, transcribed from the `pack!` macro of the
+    /// `fastlanes` crate: word `w` of lane `lane` is stored at `32 * w + lane`.
+    fn fastlanes_pack(input: &[u32], num_bits: usize) -> Vec<u32> {
+        let mut packed = vec![0u32; 32 * num_bits];
+        for lane in 0..32 {
+            if num_bits == 32 {
+                for row in 0..32 {
+                    packed[32 * row + lane] = input[fl_index(row, lane)];
+                }
+                continue;
+            }
+            let mask = (1u32 << num_bits).wrapping_sub(1);
+            let mut tmp = 0u32;
+            for row in 0..32 {
+                let src = input[fl_index(row, lane)] & mask;
+                if row == 0 {
+                    tmp = src;
+                } else {
+                    tmp |= src << ((row * num_bits) % 32);
+                }
+                let curr_word = (row * num_bits) / 32;
+                let next_word = ((row + 1) * num_bits) / 32;
+                if next_word > curr_word {
+                    packed[32 * curr_word + lane] = tmp;
+                    let remaining_bits = ((row + 1) * num_bits) % 32;
+                    tmp = src >> (num_bits - remaining_bits);
+                }
+            }
+        }
+        packed
+    }
+
+    /// FastLanes' `Transpose::transpose` followed by `Delta::delta`. The base of each
+    /// lane is the integer preceding the first one of the lane.
+    fn fastlanes_delta(initial: u32, input: &[u32]) -> Vec<u32> {
+        let transposed: Vec<u32> = (0..BLOCK_LEN).map(|idx| input[fl_transpose(idx)]).collect();
+        let mut deltas = vec![0u32; BLOCK_LEN];
+        for lane in 0..32 {
+            let first = fl_transpose(fl_index(0, lane));
+            let mut prev = if first == 0 {
+                initial
+            } else {
+                input[first - 1]
+            };
+            for row in 0..32 {
+                let idx = fl_index(row, lane);
+                deltas[idx] = transposed[idx].wrapping_sub(prev);
+                prev = transposed[idx];
+            }
+        }
+        deltas
+    }
+
+    fn to_bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_lanes_hold_runs() {
+        for (lane, &start) in LANE_STARTS.iter().enumerate() {
+            for row in 0..32 {
+                assert_eq!(fl_transpose(fl_index(row, lane)), start + row);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fastlanes_reference() {
+        let bitpacker = BitPackerFastLanes::new();
+        // Lanes `0` and `1` start with the integers `0`, `128`, `256` and `384`, and
+        // `1`, `129`, `257` and `385`: the fourth one straddles two words.
+        let values: Vec<u32> = (0..BLOCK_LEN as u32).collect();
+        let mut compressed = vec![0u8; BitPackerFastLanes::compressed_block_size(10)];
+        bitpacker.compress(&values, &mut compressed, 10);
+        assert_eq!(
+            compressed[..8],
+            [0x00, 0x00, 0x02, 0x10, 0x01, 0x04, 0x12, 0x50]
+        );
+
+        for num_bits in 0..=32 {
+            let values = generate_array(BLOCK_LEN, num_bits);
+            let mut compressed = vec![0u8; BitPackerFastLanes::compressed_block_size(num_bits)];
+            bitpacker.compress(&values, &mut compressed, num_bits);
+            let expected = to_bytes(&fastlanes_pack(&values, num_bits as usize));
+            assert_eq!(compressed, expected, "num_bits {}", num_bits);
+
+            let mut sorted = values;
+            sorted.sort_unstable();
+            let initial = sorted[0] / 2;
+            let num_bits = bitpacker.num_bits_sorted(initial, &sorted);
+            let mut compressed = vec![0u8; BitPackerFastLanes::compressed_block_size(num_bits)];
+            bitpacker.compress_sorted(initial, &sorted, &mut compressed, num_bits);
+            let deltas = fastlanes_delta(initial, &sorted);
+            let expected = to_bytes(&fastlanes_pack(&deltas, num_bits as usize));
+            assert_eq!(compressed, expected, "sorted, num_bits {}", num_bits);
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_compatible_avx2() {
+        if avx2::FastLanesImpl::available() {
+            test_util_compatible::<scalar::FastLanesImpl, avx2::FastLanesImpl>(BLOCK_LEN);
+            test_util_compress_masked_compatible::<scalar::FastLanesImpl, avx2::FastLanesImpl>(
+                BLOCK_LEN,
+            );
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_compatible_sse3() {
+        if sse3::FastLanesImpl::available() {
+            test_util_compatible::<scalar::FastLanesImpl, sse3::FastLanesImpl>(BLOCK_LEN);
+            test_util_compress_masked_compatible::<scalar::FastLanesImpl, sse3::FastLanesImpl>(
+                BLOCK_LEN,
+            );
+        }
+    }
+}
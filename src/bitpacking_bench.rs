@@ -1,26 +1,23 @@
 use criterion::{Bencher, Criterion, criterion_group, criterion_main};
 
 use bitpacking::{BitPacker, BitPacker1x, BitPacker4x, BitPacker8x};
-use criterion::Benchmark;
 use criterion::Throughput;
 
 const NUM_BLOCKS: usize = 10;
 
 fn integrate_data(initial: u32, data: &mut [u32]) {
     let mut cumul = initial;
-    let len = data.len();
-    for i in 0..len {
-        cumul = cumul.wrapping_add(data[i]);
-        data[i] = cumul;
+    for el in data.iter_mut() {
+        cumul = cumul.wrapping_add(*el);
+        *el = cumul;
     }
 }
 
 fn strict_integrate_data(initial: Option<u32>, data: &mut [u32]) {
     let mut cumul = initial.unwrap_or(u32::MAX);
-    let len = data.len();
-    for i in 0..len {
-        cumul = cumul.wrapping_add(data[i]).wrapping_add(1);
-        data[i] = cumul;
+    for el in data.iter_mut() {
+        cumul = cumul.wrapping_add(*el).wrapping_add(1);
+        *el = cumul;
     }
 }
 
@@ -238,69 +235,32 @@ fn criterion_benchmark_bitpacker<TBitPacker: BitPacker + 'static>(
     bitpacker: TBitPacker,
     criterion: &mut Criterion,
 ) {
+    let mut group = criterion.benchmark_group(name);
+    group.throughput(Throughput::Elements(
+        (NUM_BLOCKS * TBitPacker::BLOCK_LEN) as u64,
+    ));
     for num_bit in [1u8, 2u8, 24u8, 31u8] {
         let num_bits = [num_bit; NUM_BLOCKS];
-        criterion.bench(
-            name,
-            Benchmark::new(format!("decompress-{num_bit}").as_str(), move |b| {
-                bench_decompress_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
-            })
-            .throughput(Throughput::Elements(
-                (NUM_BLOCKS * TBitPacker::BLOCK_LEN) as u64,
-            )),
-        );
-        criterion.bench(
-            name,
-            Benchmark::new(format!("decompress-delta-{num_bit}").as_str(), move |b| {
-                bench_decompress_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
-            })
-            .throughput(Throughput::Elements(
-                (NUM_BLOCKS * TBitPacker::BLOCK_LEN) as u64,
-            )),
-        );
-        criterion.bench(
-            name,
-            Benchmark::new(
-                format!("decompress-strict-delta-{num_bit}").as_str(),
-                move |b| {
-                    bench_decompress_strict_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
-                },
-            )
-            .throughput(Throughput::Elements(
-                (NUM_BLOCKS * TBitPacker::BLOCK_LEN) as u64,
-            )),
-        );
-        criterion.bench(
-            name,
-            Benchmark::new(format!("compress-{num_bit}").as_str(), move |b| {
-                bench_compress_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
-            })
-            .throughput(Throughput::Elements(
-                (NUM_BLOCKS * TBitPacker::BLOCK_LEN) as u64,
-            )),
-        );
-        criterion.bench(
-            name,
-            Benchmark::new(format!("compress-delta-{num_bit}").as_str(), move |b| {
-                bench_compress_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
-            })
-            .throughput(Throughput::Elements(
-                (NUM_BLOCKS * TBitPacker::BLOCK_LEN) as u64,
-            )),
-        );
-        criterion.bench(
-            name,
-            Benchmark::new(
-                format!("compress-strict-delta-{num_bit}").as_str(),
-                move |b| {
-                    bench_compress_strict_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
-                },
-            )
-            .throughput(Throughput::Elements(
-                (NUM_BLOCKS * TBitPacker::BLOCK_LEN) as u64,
-            )),
-        );
+        group.bench_function(format!("decompress-{num_bit}"), |b| {
+            bench_decompress_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
+        });
+        group.bench_function(format!("decompress-delta-{num_bit}"), |b| {
+            bench_decompress_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
+        });
+        group.bench_function(format!("decompress-strict-delta-{num_bit}"), |b| {
+            bench_decompress_strict_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
+        });
+        group.bench_function(format!("compress-{num_bit}"), |b| {
+            bench_compress_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
+        });
+        group.bench_function(format!("compress-delta-{num_bit}"), |b| {
+            bench_compress_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
+        });
+        group.bench_function(format!("compress-strict-delta-{num_bit}"), |b| {
+            bench_compress_strict_delta_util::<TBitPacker>(bitpacker, b, &num_bits[..]);
+        });
     }
+    group.finish();
 }
 
 fn criterion_benchmark(criterion: &mut Criterion) {
@@ -0,0 +1,483 @@
+//! Per-block checksums.
+//!
+//! A flipped bit in a bitpacked block decodes into plausible, but wrong, integers.
+//! `ChecksummedCodec` wraps the `BitPacker` APIs and appends a CRC32C (Castagnoli)
+//! checksum to each group of `blocks_per_checksum` blocks. The decoder verifies it before
+//! decompressing the group, and reports a mismatch as a `DecompressError`.
+//!
+//! The checksum is computed with the `SSE4.2` `crc32` instruction when it is available,
+//! and with a lookup table otherwise.
+//!
+//! The compressed stream is the concatenation of the groups:
+//!
+//! | bytes        | content                                              |
+//! |:-------------|:-----------------------------------------------------|
+//! | `[0..n)`     | the compressed blocks of the group                   |
+//! | `[n..n + 4)` | CRC32C of these `n` bytes, as a little-endian `u32`  |
+//!
+//! The last group may hold fewer than `blocks_per_checksum` blocks.
+
+use crate::delta_mode::next_mode;
+#[cfg(target_arch = "x86_64")]
+use crate::Available;
+use crate::{BitPacker, DecompressError, DeltaMode};
+
+/// Size of the checksum appended to each group of blocks.
+const CHECKSUM_LEN: usize = 4;
+
+trait UnsafeCrc32c {
+    /// Updates the (non-inverted) `crc` with `data`.
+    unsafe fn update(crc: u32, data: &[u8]) -> u32;
+}
+
+/// Lookup table of the reflected Castagnoli polynomial.
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+};
+
+mod scalar {
+
+    use super::{UnsafeCrc32c, TABLE};
+
+    pub(super) struct UnsafeCrc32cImpl;
+
+    impl UnsafeCrc32c for UnsafeCrc32cImpl {
+        unsafe fn update(mut crc: u32, data: &[u8]) -> u32 {
+            for &byte in data {
+                crc = TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+            }
+            crc
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse42 {
+
+    use super::UnsafeCrc32c;
+    use crate::Available;
+
+    use std::arch::x86_64::{_mm_crc32_u64, _mm_crc32_u8};
+
+    pub(super) struct UnsafeCrc32cImpl;
+
+    impl UnsafeCrc32c for UnsafeCrc32cImpl {
+        #[target_feature(enable = "sse4.2")]
+        unsafe fn update(crc: u32, data: &[u8]) -> u32 {
+            let mut crc = crc as u64;
+            let mut words = data.chunks_exact(8);
+            for word in words.by_ref() {
+                crc = _mm_crc32_u64(crc, u64::from_le_bytes(word.try_into().unwrap()));
+            }
+            let mut crc = crc as u32;
+            for &byte in words.remainder() {
+                crc = _mm_crc32_u8(crc, byte);
+            }
+            crc
+        }
+    }
+
+    impl Available for UnsafeCrc32cImpl {
+        fn available() -> bool {
+            is_x86_feature_detected!("sse4.2")
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum InstructionSet {
+    #[cfg(target_arch = "x86_64")]
+    SSE42,
+    Scalar,
+}
+
+/// CRC32C (Castagnoli) checksum.
+///
+/// ```
+/// use bitpacking::Crc32c;
+///
+/// assert_eq!(Crc32c::new().checksum(b"123456789"), 0xE306_9283);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Crc32c(InstructionSet);
+
+impl Default for Crc32c {
+    fn default() -> Self {
+        Crc32c::new()
+    }
+}
+
+impl Crc32c {
+    /// Checks the available instructions set on the current
+    /// CPU and returns the best available implementation.
+    pub fn new() -> Crc32c {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if sse42::UnsafeCrc32cImpl::available() {
+                return Crc32c(InstructionSet::SSE42);
+            }
+        }
+        Crc32c(InstructionSet::Scalar)
+    }
+
+    /// Returns the CRC32C of `data`.
+    #[must_use]
+    pub fn checksum(&self, data: &[u8]) -> u32 {
+        let crc = unsafe {
+            match self.0 {
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::SSE42 => sse42::UnsafeCrc32cImpl::update(!0, data),
+                InstructionSet::Scalar => scalar::UnsafeCrc32cImpl::update(!0, data),
+            }
+        };
+        !crc
+    }
+}
+
+/// Codec appending a CRC32C checksum to each group of blocks compressed by
+/// `TBitPacker`.
+///
+/// The numbers of bits of the blocks are not stored: as with the `BitPacker` APIs,
+/// the caller keeps them and passes them back to the decoder.
+///
+/// ```
+/// use bitpacking::{BitPacker, BitPacker4x, ChecksummedCodec, DecompressError, DeltaMode};
+///
+/// let values: Vec<u32> = (0..256).map(|i| i % 100).collect();
+/// let num_bits = [7, 7];
+///
+/// let codec = ChecksummedCodec::<BitPacker4x>::new(1);
+/// let mut compressed = vec![0u8; codec.compressed_len(&num_bits)];
+/// codec.compress(DeltaMode::NoDelta, &values, &num_bits, &mut compressed);
+///
+/// let mut decompressed = vec![0u32; 256];
+/// codec
+///     .decompress(DeltaMode::NoDelta, &compressed, &num_bits, &mut decompressed)
+///     .unwrap();
+/// assert_eq!(values, decompressed);
+///
+/// // Flips a bit of the second block.
+/// compressed[BitPacker4x::compressed_block_size(7) + 4 + 10] ^= 1;
+/// assert!(matches!(
+///     codec.decompress(DeltaMode::NoDelta, &compressed, &num_bits, &mut decompressed),
+///     Err(DecompressError::ChecksumMismatch { group: 1, .. })
+/// ));
+/// ```
+#[derive(Clone, Copy)]
+pub struct ChecksummedCodec<TBitPacker: BitPacker> {
+    bitpacker: TBitPacker,
+    crc32c: Crc32c,
+    blocks_per_checksum: usize,
+}
+
+impl<TBitPacker: BitPacker> ChecksummedCodec<TBitPacker> {
+    /// Number of `u32` per compressed block.
+    pub const BLOCK_LEN: usize = TBitPacker::BLOCK_LEN;
+
+    /// Returns a codec appending a checksum every `blocks_per_checksum` blocks, relying
+    /// on the best available implementations of `TBitPacker` and of CRC32C.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `blocks_per_checksum` is 0.
+    pub fn new(blocks_per_checksum: usize) -> Self {
+        assert!(
+            blocks_per_checksum > 0,
+            "There must be at least one block per checksum"
+        );
+        ChecksummedCodec {
+            bitpacker: TBitPacker::new(),
+            crc32c: Crc32c::new(),
+            blocks_per_checksum,
+        }
+    }
+
+    /// Returns the size of the compressed stream, checksums included, given the numbers
+    /// of bits of its blocks.
+    #[must_use]
+    pub fn compressed_len(&self, num_bits: &[u8]) -> usize {
+        let num_groups = num_bits.len().div_ceil(self.blocks_per_checksum);
+        num_bits
+            .iter()
+            .map(|&num_bits| TBitPacker::compressed_block_size(num_bits))
+            .sum::<usize>()
+            + num_groups * CHECKSUM_LEN
+    }
+
+    /// Compresses the blocks of `decompressed`, the block `i` with `num_bits[i]` bits,
+    /// and appends a checksum to each group of blocks.
+    ///
+    /// Sorted blocks are chained: each block uses the last integer of the previous one
+    /// as its initial value.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not `num_bits.len() * BLOCK_LEN`.
+    /// - Panics if `compressed` is smaller than `.compressed_len(num_bits)`.
+    pub fn compress(
+        &self,
+        mode: DeltaMode,
+        decompressed: &[u32],
+        num_bits: &[u8],
+        compressed: &mut [u8],
+    ) -> usize {
+        assert_eq!(
+            decompressed.len(),
+            num_bits.len() * TBitPacker::BLOCK_LEN,
+            "`decompressed`'s len is not `num_bits.len() * BLOCK_LEN`"
+        );
+        let compressed_len = self.compressed_len(num_bits);
+        assert!(
+            compressed.len() >= compressed_len,
+            "Output array too small. {} < {}",
+            compressed.len(),
+            compressed_len
+        );
+        let mut mode = mode;
+        let mut offset = 0;
+        let groups = decompressed
+            .chunks(self.blocks_per_checksum * TBitPacker::BLOCK_LEN)
+            .zip(num_bits.chunks(self.blocks_per_checksum));
+        for (group, group_num_bits) in groups {
+            let group_start = offset;
+            let blocks = group.chunks(TBitPacker::BLOCK_LEN).zip(group_num_bits);
+            for (block, &block_num_bits) in blocks {
+                let output = &mut compressed[offset..];
+                offset += match mode {
+                    DeltaMode::NoDelta => self.bitpacker.compress(block, output, block_num_bits),
+                    DeltaMode::Sorted { initial } => {
+                        self.bitpacker
+                            .compress_sorted(initial, block, output, block_num_bits)
+                    }
+                    DeltaMode::StrictlySorted { initial } => self
+                        .bitpacker
+                        .compress_strictly_sorted(initial, block, output, block_num_bits),
+                };
+                mode = next_mode(mode, block);
+            }
+            let checksum = self.crc32c.checksum(&compressed[group_start..offset]);
+            compressed[offset..offset + CHECKSUM_LEN].copy_from_slice(&checksum.to_le_bytes());
+            offset += CHECKSUM_LEN;
+        }
+        offset
+    }
+
+    /// Decompresses a stream compressed with `.compress(..)`, with the same `mode` and
+    /// `num_bits`.
+    ///
+    /// The checksum of each group is verified before the group is decompressed. On
+    /// mismatch, `DecompressError::ChecksumMismatch` is returned, and the groups
+    /// preceding the corrupted one have already been written to `decompressed`.
+    ///
+    /// Returns the amount of bytes that were consumed.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `compressed` is smaller than `.compressed_len(num_bits)`.
+    /// - Panics if `decompressed`'s len is not `num_bits.len() * BLOCK_LEN`.
+    pub fn decompress(
+        &self,
+        mode: DeltaMode,
+        compressed: &[u8],
+        num_bits: &[u8],
+        decompressed: &mut [u32],
+    ) -> Result<usize, DecompressError> {
+        assert_eq!(
+            decompressed.len(),
+            num_bits.len() * TBitPacker::BLOCK_LEN,
+            "`decompressed`'s len is not `num_bits.len() * BLOCK_LEN`"
+        );
+        let compressed_len = self.compressed_len(num_bits);
+        assert!(
+            compressed.len() >= compressed_len,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            compressed_len
+        );
+        let mut mode = mode;
+        let mut offset = 0;
+        let groups = decompressed
+            .chunks_mut(self.blocks_per_checksum * TBitPacker::BLOCK_LEN)
+            .zip(num_bits.chunks(self.blocks_per_checksum))
+            .enumerate();
+        for (group_id, (group, group_num_bits)) in groups {
+            let group_len: usize = group_num_bits
+                .iter()
+                .map(|&num_bits| TBitPacker::compressed_block_size(num_bits))
+                .sum();
+            let checksum_bytes = &compressed[offset + group_len..][..CHECKSUM_LEN];
+            let expected = u32::from_le_bytes(checksum_bytes.try_into().unwrap());
+            let actual = self
+                .crc32c
+                .checksum(&compressed[offset..offset + group_len]);
+            if expected != actual {
+                return Err(DecompressError::ChecksumMismatch {
+                    group: group_id,
+                    expected,
+                    actual,
+                });
+            }
+            let blocks = group.chunks_mut(TBitPacker::BLOCK_LEN).zip(group_num_bits);
+            for (block, &block_num_bits) in blocks {
+                let input = &compressed[offset..];
+                offset += match mode {
+                    DeltaMode::NoDelta => self.bitpacker.decompress(input, block, block_num_bits),
+                    DeltaMode::Sorted { initial } => {
+                        self.bitpacker
+                            .decompress_sorted(initial, input, block, block_num_bits)
+                    }
+                    DeltaMode::StrictlySorted { initial } => self
+                        .bitpacker
+                        .decompress_strictly_sorted(initial, input, block, block_num_bits),
+                };
+                mode = next_mode(mode, block);
+            }
+            offset += CHECKSUM_LEN;
+        }
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scalar, ChecksummedCodec, Crc32c, UnsafeCrc32c};
+    use crate::tests::generate_array;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+    use crate::{BitPacker, DecompressError, DeltaMode};
+
+    #[test]
+    fn test_crc32c() {
+        let crc32c = Crc32c::new();
+        assert_eq!(crc32c.checksum(b""), 0);
+        assert_eq!(crc32c.checksum(b"123456789"), 0xE306_9283);
+        assert_eq!(crc32c.checksum(&[0u8; 32]), 0x8A91_36AA);
+        assert_eq!(crc32c.checksum(&[0xFFu8; 32]), 0x62A8_AB43);
+        let data: Vec<u8> = (0..1_000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+        for len in [0, 1, 7, 8, 9, 63, 64, 65, 1_000] {
+            let expected = !unsafe { scalar::UnsafeCrc32cImpl::update(!0, &data[..len]) };
+            assert_eq!(crc32c.checksum(&data[..len]), expected);
+        }
+    }
+
+    fn test_util_checksummed<TBitPacker: BitPacker>(blocks_per_checksum: usize, mode: DeltaMode) {
+        let bitpacker = TBitPacker::new();
+        let num_blocks = 5;
+        let mut values = generate_array(num_blocks * TBitPacker::BLOCK_LEN, 12);
+        if mode != DeltaMode::NoDelta {
+            let mut cumul = 0u32;
+            for val in &mut values {
+                cumul += *val + 1;
+                *val = cumul;
+            }
+        }
+        let mut initial = match mode {
+            DeltaMode::Sorted { initial } => Some(initial),
+            DeltaMode::StrictlySorted { initial } => initial,
+            DeltaMode::NoDelta => None,
+        };
+        let num_bits: Vec<u8> = values
+            .chunks(TBitPacker::BLOCK_LEN)
+            .map(|block| {
+                let block_num_bits = match mode {
+                    DeltaMode::NoDelta => bitpacker.num_bits(block),
+                    DeltaMode::Sorted { .. } => bitpacker.num_bits_sorted(initial.unwrap(), block),
+                    DeltaMode::StrictlySorted { .. } => {
+                        bitpacker.num_bits_strictly_sorted(initial, block)
+                    }
+                };
+                initial = block.last().copied();
+                block_num_bits
+            })
+            .collect();
+
+        let codec = ChecksummedCodec::<TBitPacker>::new(blocks_per_checksum);
+        let compressed_len = codec.compressed_len(&num_bits);
+        let mut compressed = vec![0u8; compressed_len];
+        assert_eq!(
+            codec.compress(mode, &values, &num_bits, &mut compressed),
+            compressed_len
+        );
+        let mut decompressed = vec![0u32; values.len()];
+        assert_eq!(
+            codec.decompress(mode, &compressed, &num_bits, &mut decompressed),
+            Ok(compressed_len)
+        );
+        assert_eq!(values, decompressed);
+
+        let num_groups = num_blocks.div_ceil(blocks_per_checksum);
+        for byte in (0..compressed_len).step_by(13) {
+            for bit in [0, 5] {
+                let mut corrupted = compressed.clone();
+                corrupted[byte] ^= 1 << bit;
+                let result = codec.decompress(mode, &corrupted, &num_bits, &mut decompressed);
+                let Err(DecompressError::ChecksumMismatch { group, .. }) = result else {
+                    panic!("Corruption of byte {} was not detected", byte);
+                };
+                assert!(group < num_groups);
+            }
+        }
+    }
+
+    fn test_suite_checksummed<TBitPacker: BitPacker>() {
+        for blocks_per_checksum in [1, 2, 5, 8] {
+            test_util_checksummed::<TBitPacker>(blocks_per_checksum, DeltaMode::NoDelta);
+            test_util_checksummed::<TBitPacker>(
+                blocks_per_checksum,
+                DeltaMode::Sorted { initial: 0 },
+            );
+            test_util_checksummed::<TBitPacker>(
+                blocks_per_checksum,
+                DeltaMode::StrictlySorted { initial: None },
+            );
+        }
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_checksummed_bitpacker1x() {
+        test_suite_checksummed::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_checksummed_bitpacker4x() {
+        test_suite_checksummed::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_checksummed_bitpacker8x() {
+        test_suite_checksummed::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_checksummed_bitpackerfastlanes() {
+        test_suite_checksummed::<BitPackerFastLanes>();
+    }
+}
@@ -0,0 +1,30 @@
+//! How the blocks of a stream were compressed.
+
+/// Describes how the blocks of a stream were compressed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeltaMode {
+    /// The blocks were compressed with `.compress(..)`.
+    NoDelta,
+    /// The blocks were compressed with `.compress_sorted(..)`.
+    Sorted {
+        /// Initial value of the first block.
+        initial: u32,
+    },
+    /// The blocks were compressed with `.compress_strictly_sorted(..)`.
+    StrictlySorted {
+        /// Initial value of the first block.
+        initial: Option<u32>,
+    },
+}
+
+/// Tracks the initial value of the next block of a stream.
+pub(crate) fn next_mode(mode: DeltaMode, block: &[u32]) -> DeltaMode {
+    let last = block[block.len() - 1];
+    match mode {
+        DeltaMode::NoDelta => DeltaMode::NoDelta,
+        DeltaMode::Sorted { .. } => DeltaMode::Sorted { initial: last },
+        DeltaMode::StrictlySorted { .. } => DeltaMode::StrictlySorted {
+            initial: Some(last),
+        },
+    }
+}
@@ -0,0 +1,410 @@
+//! Elias-Fano encoding of sorted sequences.
+//!
+//! For sparse sorted sequences over a large universe, delta-encoding with
+//! `compress_sorted` wastes bits whenever the size of the deltas varies a lot.
+//! Elias-Fano uses at most `2 + log2(universe / len)` bits per element, whatever the
+//! distribution of the values, and supports random access.
+//!
+//! Each value is split into:
+//! - its `low_bits` least significant bits. They are bitpacked in blocks of 32 using
+//!   the `BitPacker1x` kernels.
+//! - its remaining high bits. They are unary-encoded in a bitvector: the element `i`
+//!   sets the bit `(value >> low_bits) + i`.
+//!
+//! Since `BitPacker1x` blocks are a plain concatenation of `low_bits`-bits integers,
+//! the low bits of the `i`-th element can be read directly, without decompressing
+//! its block. Sequential iteration decompresses the low bits one block at a time.
+
+use std::marker::PhantomData;
+
+use crate::{BitPacker, BitPacker1x};
+
+/// One out of `SELECT_STRIDE` ones (resp. zeros) has its position sampled to speed up
+/// `select`.
+const SELECT_STRIDE: usize = 256;
+
+/// Integer types that can be stored in an [`EliasFano`] sequence.
+///
+/// This trait is implemented for `u32` and `u64`.
+pub trait EliasFanoInt: Copy + Ord + private::Sealed {
+    #[doc(hidden)]
+    fn to_u64(self) -> u64;
+    #[doc(hidden)]
+    fn from_u64(val: u64) -> Self;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
+}
+
+impl EliasFanoInt for u32 {
+    fn to_u64(self) -> u64 {
+        self as u64
+    }
+    fn from_u64(val: u64) -> Self {
+        val as u32
+    }
+}
+
+impl EliasFanoInt for u64 {
+    fn to_u64(self) -> u64 {
+        self
+    }
+    fn from_u64(val: u64) -> Self {
+        val
+    }
+}
+
+/// Elias-Fano encoded sorted sequence of `u32` or `u64`.
+///
+/// The number of low bits is capped to 32, the width supported by the `BitPacker1x`
+/// kernels. Very sparse `u64` sequences (`universe / len > 2^32`) therefore use more
+/// than the optimal amount of bits for their high parts.
+///
+/// ```
+/// use bitpacking::EliasFano;
+///
+/// let doc_ids: Vec<u32> = vec![3, 17, 17, 1_000, 40_000, 1_000_000];
+/// let elias_fano = EliasFano::new(&doc_ids);
+///
+/// assert_eq!(elias_fano.len(), 6);
+/// assert_eq!(elias_fano.get(4), 40_000);
+/// assert_eq!(elias_fano.next_geq(18), Some((3, 1_000)));
+/// assert_eq!(elias_fano.next_geq(1_000_001), None);
+/// assert_eq!(elias_fano.iter().collect::<Vec<u32>>(), doc_ids);
+/// ```
+#[derive(Clone)]
+pub struct EliasFano<T: EliasFanoInt = u32> {
+    len: usize,
+    low_bits: u8,
+    // Concatenation of `BitPacker1x` blocks, followed by one `u32` of padding.
+    low: Vec<u8>,
+    high: Vec<u64>,
+    high_len: usize,
+    // Position of the `k * SELECT_STRIDE`-th one (resp. zero) in `high`.
+    select1_samples: Vec<usize>,
+    select0_samples: Vec<usize>,
+    _marker: PhantomData<T>,
+}
+
+/// Returns the number of low bits minimizing the size of the encoding.
+fn compute_low_bits(len: usize, universe: u128) -> u8 {
+    if len == 0 || universe <= len as u128 {
+        return 0;
+    }
+    let ratio = universe / len as u128;
+    (127 - ratio.leading_zeros()).min(32) as u8
+}
+
+impl<T: EliasFanoInt> EliasFano<T> {
+    /// Encodes a sorted sequence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is not sorted.
+    pub fn new(values: &[T]) -> EliasFano<T> {
+        assert!(
+            values.windows(2).all(|pair| pair[0] <= pair[1]),
+            "`values` is not sorted"
+        );
+        let len = values.len();
+        let max_value = values.last().map(|val| val.to_u64()).unwrap_or(0);
+        let low_bits = compute_low_bits(len, max_value as u128 + 1);
+        let low_mask = ((1u64 << low_bits) - 1) as u32;
+
+        let bitpacker = BitPacker1x::new();
+        let num_blocks = len.div_ceil(BitPacker1x::BLOCK_LEN);
+        let block_num_bytes = BitPacker1x::compressed_block_size(low_bits);
+        let mut low = vec![0u8; num_blocks * block_num_bytes + 4];
+        for (block_id, chunk) in values.chunks(BitPacker1x::BLOCK_LEN).enumerate() {
+            let mut block = [0u32; BitPacker1x::BLOCK_LEN];
+            for (dest, val) in block.iter_mut().zip(chunk) {
+                *dest = val.to_u64() as u32 & low_mask;
+            }
+            bitpacker.compress(&block, &mut low[block_id * block_num_bytes..], low_bits);
+        }
+
+        let max_high = (max_value >> low_bits) as usize;
+        let high_len = max_high + len + 1;
+        let mut high = vec![0u64; high_len.div_ceil(64)];
+        let mut select1_samples = Vec::with_capacity(len / SELECT_STRIDE + 1);
+        let mut select0_samples = Vec::with_capacity(max_high / SELECT_STRIDE + 1);
+        let mut next_zero_sample = 0;
+        for (i, val) in values.iter().enumerate() {
+            let high_part = (val.to_u64() >> low_bits) as usize;
+            // The zeros before this element are exactly those of rank < `high_part`.
+            while next_zero_sample < high_part {
+                select0_samples.push(next_zero_sample + i);
+                next_zero_sample += SELECT_STRIDE;
+            }
+            let pos = high_part + i;
+            high[pos / 64] |= 1u64 << (pos % 64);
+            if i % SELECT_STRIDE == 0 {
+                select1_samples.push(pos);
+            }
+        }
+        while next_zero_sample <= max_high {
+            select0_samples.push(next_zero_sample + len);
+            next_zero_sample += SELECT_STRIDE;
+        }
+
+        EliasFano {
+            len,
+            low_bits,
+            low,
+            high,
+            high_len,
+            select1_samples,
+            select0_samples,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements in the sequence.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes used by the encoded sequence.
+    pub fn num_bytes(&self) -> usize {
+        self.low.len() + self.high.len() * 8
+    }
+
+    /// Returns the `idx`-th element of the sequence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx >= self.len()`.
+    pub fn get(&self, idx: usize) -> T {
+        assert!(
+            idx < self.len,
+            "Index out of bounds ({} >= {})",
+            idx,
+            self.len
+        );
+        let high_part = (self.select1(idx) - idx) as u64;
+        T::from_u64((high_part << self.low_bits) | self.get_low(idx) as u64)
+    }
+
+    /// Returns the index and the value of the first element greater or equal to `target`,
+    /// or `None` if all elements are smaller than `target`.
+    pub fn next_geq(&self, target: T) -> Option<(usize, T)> {
+        let target = target.to_u64();
+        let high_part = (target >> self.low_bits) as usize;
+        if high_part >= self.high_len - self.len {
+            return None;
+        }
+        // The bucket of the elements sharing `high_part` starts right after
+        // the `high_part - 1`-th zero.
+        let bucket_start = if high_part == 0 {
+            0
+        } else {
+            self.select0(high_part - 1) + 1
+        };
+        let mut iter = EliasFanoIter::new(self, bucket_start - high_part, bucket_start);
+        loop {
+            let idx = iter.idx;
+            let val = iter.next()?;
+            if val.to_u64() >= target {
+                return Some((idx, val));
+            }
+        }
+    }
+
+    /// Returns an iterator over the elements of the sequence.
+    pub fn iter(&self) -> EliasFanoIter<'_, T> {
+        EliasFanoIter::new(self, 0, 0)
+    }
+
+    fn get_low(&self, idx: usize) -> u32 {
+        if self.low_bits == 0 {
+            return 0;
+        }
+        let bit_offset = idx * self.low_bits as usize;
+        let word_offset = (bit_offset / 32) * 4;
+        let read_word = |offset: usize| {
+            u32::from_le_bytes(self.low[offset..offset + 4].try_into().unwrap()) as u64
+        };
+        let words = read_word(word_offset) | (read_word(word_offset + 4) << 32);
+        let mask = (1u64 << self.low_bits) - 1;
+        ((words >> (bit_offset % 32)) & mask) as u32
+    }
+
+    fn select1(&self, rank: usize) -> usize {
+        select(&self.high, &self.select1_samples, rank, |word| word)
+    }
+
+    fn select0(&self, rank: usize) -> usize {
+        select(&self.high, &self.select0_samples, rank, |word| !word)
+    }
+}
+
+/// Returns the position of the `rank`-th set bit of `words` once mapped through `map_word`.
+fn select(words: &[u64], samples: &[usize], rank: usize, map_word: impl Fn(u64) -> u64) -> usize {
+    let sample_id = rank / SELECT_STRIDE;
+    let start = samples[sample_id];
+    let mut remaining = (rank - sample_id * SELECT_STRIDE) as u32;
+    let mut word_id = start / 64;
+    let mut word = map_word(words[word_id]) & (u64::MAX << (start % 64));
+    loop {
+        let num_ones = word.count_ones();
+        if remaining < num_ones {
+            for _ in 0..remaining {
+                word &= word - 1;
+            }
+            return word_id * 64 + word.trailing_zeros() as usize;
+        }
+        remaining -= num_ones;
+        word_id += 1;
+        word = map_word(words[word_id]);
+    }
+}
+
+/// Iterator over the elements of an [`EliasFano`] sequence.
+///
+/// The low bits are decompressed one `BitPacker1x` block at a time.
+pub struct EliasFanoIter<'a, T: EliasFanoInt> {
+    elias_fano: &'a EliasFano<T>,
+    idx: usize,
+    high_pos: usize,
+    block_id: usize,
+    block: [u32; BitPacker1x::BLOCK_LEN],
+}
+
+impl<'a, T: EliasFanoInt> EliasFanoIter<'a, T> {
+    fn new(elias_fano: &'a EliasFano<T>, idx: usize, high_pos: usize) -> Self {
+        EliasFanoIter {
+            elias_fano,
+            idx,
+            high_pos,
+            block_id: usize::MAX,
+            block: [0u32; BitPacker1x::BLOCK_LEN],
+        }
+    }
+}
+
+impl<T: EliasFanoInt> Iterator for EliasFanoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let elias_fano = self.elias_fano;
+        if self.idx >= elias_fano.len {
+            return None;
+        }
+        let mut word_id = self.high_pos / 64;
+        let mut word = elias_fano.high[word_id] & (u64::MAX << (self.high_pos % 64));
+        while word == 0 {
+            word_id += 1;
+            word = elias_fano.high[word_id];
+        }
+        let pos = word_id * 64 + word.trailing_zeros() as usize;
+        self.high_pos = pos + 1;
+
+        let block_id = self.idx / BitPacker1x::BLOCK_LEN;
+        if block_id != self.block_id {
+            let block_num_bytes = BitPacker1x::compressed_block_size(elias_fano.low_bits);
+            BitPacker1x::new().decompress(
+                &elias_fano.low[block_id * block_num_bytes..],
+                &mut self.block,
+                elias_fano.low_bits,
+            );
+            self.block_id = block_id;
+        }
+        let low = self.block[self.idx % BitPacker1x::BLOCK_LEN] as u64;
+        let high = (pos - self.idx) as u64;
+        self.idx += 1;
+        Some(T::from_u64((high << elias_fano.low_bits) | low))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.elias_fano.len - self.idx.min(self.elias_fano.len);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: EliasFanoInt> ExactSizeIterator for EliasFanoIter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_low_bits, EliasFano, EliasFanoInt};
+    use crate::tests::generate_array;
+
+    fn test_util_elias_fano<T: EliasFanoInt + std::fmt::Debug>(values: &[T]) {
+        let elias_fano = EliasFano::new(values);
+        assert_eq!(elias_fano.len(), values.len());
+        assert_eq!(elias_fano.iter().len(), values.len());
+        assert_eq!(elias_fano.iter().collect::<Vec<T>>(), values);
+        for (idx, &val) in values.iter().enumerate() {
+            assert_eq!(elias_fano.get(idx), val);
+            let expected_idx = values.partition_point(|&other| other < val);
+            assert_eq!(elias_fano.next_geq(val), Some((expected_idx, val)));
+        }
+        for (idx, pair) in values.windows(2).enumerate() {
+            if pair[0].to_u64() + 1 < pair[1].to_u64() {
+                let target = T::from_u64(pair[0].to_u64() + 1);
+                assert_eq!(elias_fano.next_geq(target), Some((idx + 1, pair[1])));
+            }
+        }
+        if let Some(&last) = values.last() {
+            let target = T::from_u64(last.to_u64().wrapping_add(1));
+            if target > last {
+                assert_eq!(elias_fano.next_geq(target), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_low_bits() {
+        assert_eq!(compute_low_bits(0, 10), 0);
+        assert_eq!(compute_low_bits(10, 10), 0);
+        assert_eq!(compute_low_bits(10, 100), 3);
+        assert_eq!(compute_low_bits(1, 1u128 << 64), 32);
+    }
+
+    #[test]
+    fn test_elias_fano_edge_cases() {
+        test_util_elias_fano::<u32>(&[]);
+        test_util_elias_fano::<u32>(&[0]);
+        test_util_elias_fano::<u32>(&[u32::MAX]);
+        test_util_elias_fano::<u32>(&[0, 0, 0, 1, 1, u32::MAX, u32::MAX]);
+        test_util_elias_fano::<u64>(&[0, 1 << 40, (1 << 45) - 1, 1 << 45]);
+    }
+
+    #[test]
+    fn test_elias_fano_dense() {
+        let values: Vec<u32> = (0..10_000).collect();
+        test_util_elias_fano(&values);
+    }
+
+    #[test]
+    fn test_elias_fano_random() {
+        for num_bits in [4, 12, 20, 32] {
+            let mut values = generate_array(3_000, num_bits);
+            values.sort_unstable();
+            test_util_elias_fano(&values);
+            let values_u64: Vec<u64> = values.iter().map(|&val| (val as u64) << 20).collect();
+            test_util_elias_fano(&values_u64);
+        }
+    }
+
+    #[test]
+    fn test_elias_fano_is_compact() {
+        let values: Vec<u32> = (0..4096).map(|i| i * 1_000_000).collect();
+        let elias_fano = EliasFano::new(&values);
+        // At most `2 + ceil(log2(universe / len))` bits per element.
+        assert!(elias_fano.num_bytes() * 8 <= values.len() * (2 + 20) + 128);
+    }
+
+    #[test]
+    #[should_panic(expected = "`values` is not sorted")]
+    fn test_elias_fano_unsorted() {
+        EliasFano::new(&[3u32, 2u32]);
+    }
+}
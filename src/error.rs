@@ -0,0 +1,116 @@
+use std::fmt;
+
+/// Error returned by the checked compression methods.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompressError {
+    /// The integer at `index` does not fit in `num_bits` bits.
+    ValueTooWide {
+        /// Position of the integer in the block.
+        index: usize,
+        /// The integer itself.
+        value: u32,
+        /// The number of bits the block was compressed with.
+        num_bits: u8,
+    },
+    /// The integer at `index` breaks the order of a sorted block: it is lower than the
+    /// previous integer, or equal to it for strictly sorted blocks.
+    NotSorted {
+        /// Position of the integer in the block.
+        index: usize,
+    },
+}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CompressError::ValueTooWide {
+                index,
+                value,
+                num_bits,
+            } => write!(
+                f,
+                "Integer {} at index {} does not fit in {} bits",
+                value, index, num_bits
+            ),
+            CompressError::NotSorted { index } => {
+                write!(f, "Integer at index {} is not sorted", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompressError {}
+
+/// Error returned by the checked and verifying decompression methods.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DecompressError {
+    /// The checksum of a group of blocks does not match its content: the compressed
+    /// data is corrupted.
+    ChecksumMismatch {
+        /// Position of the group of blocks in the stream.
+        group: usize,
+        /// The checksum stored after the group.
+        expected: u32,
+        /// The checksum of the group, as read.
+        actual: u32,
+    },
+    /// The number of bits is greater than 32.
+    InvalidNumBits {
+        /// The number of bits the block was supposedly compressed with.
+        num_bits: u8,
+    },
+    /// The compressed data is shorter than the compressed block.
+    Truncated {
+        /// The length of the compressed data.
+        len: usize,
+        /// The length of the compressed block.
+        expected_len: usize,
+    },
+    /// The output array cannot receive a whole block.
+    OutputTooShort {
+        /// The length of the output array.
+        len: usize,
+        /// The length of a block.
+        expected_len: usize,
+    },
+    /// Integrating the deltas of a sorted block overflows at the integer at `index`:
+    /// the block does not decode into sorted integers.
+    Overflow {
+        /// Position of the integer in the block.
+        index: usize,
+    },
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecompressError::ChecksumMismatch {
+                group,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Checksum mismatch for group {}: expected {:#010x}, got {:#010x}",
+                group, expected, actual
+            ),
+            DecompressError::InvalidNumBits { num_bits } => {
+                write!(f, "Num bits must be <= 32. Was {}", num_bits)
+            }
+            DecompressError::Truncated { len, expected_len } => write!(
+                f,
+                "Compressed data is truncated: {} bytes, expected {}",
+                len, expected_len
+            ),
+            DecompressError::OutputTooShort { len, expected_len } => write!(
+                f,
+                "Output array too short: {} integers, expected {}",
+                len, expected_len
+            ),
+            DecompressError::Overflow { index } => {
+                write!(f, "Integrating the deltas overflows at index {}", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecompressError {}
@@ -0,0 +1,192 @@
+//! Compressed size estimation.
+//!
+//! Planners choosing a codec, or preallocating buffers, need the compressed size of a
+//! whole array without compressing it. The estimate computes the number of bits of
+//! each block with the SIMD `num_bits` kernels, in a single pass over the array, and
+//! sums the block sizes.
+
+use crate::{BitPacker, DeltaMode, MAX_BLOCK_LEN};
+
+/// Compressed size of an array, as computed by `BitPacker::estimate_compressed_len(..)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompressedLenEstimate {
+    /// Total amount of bytes of the compressed blocks, tail included.
+    pub compressed_len: usize,
+    /// Number of blocks for each number of bits, from 0 to 32.
+    pub num_bits_histogram: [usize; 33],
+}
+
+impl CompressedLenEstimate {
+    /// Returns the number of blocks, tail included.
+    #[must_use]
+    pub fn num_blocks(&self) -> usize {
+        self.num_bits_histogram.iter().sum()
+    }
+}
+
+pub(crate) fn estimate_compressed_len<TBitPacker: BitPacker>(
+    bitpacker: &TBitPacker,
+    decompressed: &[u32],
+    mode: DeltaMode,
+) -> CompressedLenEstimate {
+    let mut estimate = CompressedLenEstimate {
+        compressed_len: 0,
+        num_bits_histogram: [0; 33],
+    };
+    let mut mode = mode;
+    let mut add_block = |block: &[u32], mode: &mut DeltaMode| {
+        let num_bits = match *mode {
+            DeltaMode::NoDelta => bitpacker.num_bits(block),
+            DeltaMode::Sorted { initial } => {
+                *mode = DeltaMode::Sorted {
+                    initial: block[TBitPacker::BLOCK_LEN - 1],
+                };
+                bitpacker.num_bits_sorted(initial, block)
+            }
+            DeltaMode::StrictlySorted { initial } => {
+                *mode = DeltaMode::StrictlySorted {
+                    initial: Some(block[TBitPacker::BLOCK_LEN - 1]),
+                };
+                bitpacker.num_bits_strictly_sorted(initial, block)
+            }
+        };
+        estimate.compressed_len += TBitPacker::compressed_block_size(num_bits);
+        estimate.num_bits_histogram[num_bits as usize] += 1;
+    };
+    let mut blocks = decompressed.chunks_exact(TBitPacker::BLOCK_LEN);
+    for block in blocks.by_ref() {
+        add_block(block, &mut mode);
+    }
+    let tail = blocks.remainder();
+    if let Some(&last) = tail.last() {
+        // The padding is chosen so that it does not widen the tail block.
+        let mut padded = [0u32; MAX_BLOCK_LEN];
+        let padded = &mut padded[..TBitPacker::BLOCK_LEN];
+        padded[..tail.len()].copy_from_slice(tail);
+        match mode {
+            DeltaMode::NoDelta => {}
+            DeltaMode::Sorted { .. } => padded[tail.len()..].fill(last),
+            DeltaMode::StrictlySorted { .. } => {
+                let mut next = last;
+                for val in &mut padded[tail.len()..] {
+                    next = next.wrapping_add(1);
+                    *val = next;
+                }
+            }
+        }
+        add_block(padded, &mut mode);
+    }
+    estimate
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::generate_array;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+    use crate::{BitPacker, DeltaMode};
+
+    fn test_util_estimate<TBitPacker: BitPacker>(values: &[u32], mode: DeltaMode) {
+        let bitpacker = TBitPacker::new();
+        let estimate = bitpacker.estimate_compressed_len(values, mode);
+        assert_eq!(
+            estimate.num_blocks(),
+            values.len().div_ceil(TBitPacker::BLOCK_LEN)
+        );
+        // Compresses the blocks one by one, the tail being padded with its last value
+        // repeated (sorted) or incremented (strictly sorted).
+        let mut padded = values.to_vec();
+        let mut next = values.last().copied().unwrap_or(0);
+        while !padded.len().is_multiple_of(TBitPacker::BLOCK_LEN) {
+            if let DeltaMode::StrictlySorted { .. } = mode {
+                next = next.wrapping_add(1);
+            } else if let DeltaMode::NoDelta = mode {
+                next = 0;
+            }
+            padded.push(next);
+        }
+        let mut compressed = vec![0u8; 4 * TBitPacker::BLOCK_LEN];
+        let mut compressed_len = 0;
+        let mut histogram = [0usize; 33];
+        let mut previous = None;
+        for block in padded.chunks(TBitPacker::BLOCK_LEN) {
+            let (num_bits, block_len) = match mode {
+                DeltaMode::NoDelta => bitpacker.compress_auto(block, &mut compressed),
+                DeltaMode::Sorted { initial } => bitpacker.compress_sorted_auto(
+                    previous.unwrap_or(initial),
+                    block,
+                    &mut compressed,
+                ),
+                DeltaMode::StrictlySorted { initial } => bitpacker.compress_strictly_sorted_auto(
+                    previous.or(initial),
+                    block,
+                    &mut compressed,
+                ),
+            };
+            compressed_len += block_len;
+            histogram[num_bits as usize] += 1;
+            previous = block.last().copied();
+        }
+        assert_eq!(estimate.compressed_len, compressed_len);
+        assert_eq!(estimate.num_bits_histogram, histogram);
+    }
+
+    fn test_suite_estimate<TBitPacker: BitPacker>() {
+        let block_len = TBitPacker::BLOCK_LEN;
+        for len in [0, 1, block_len - 1, block_len, 3 * block_len + 5] {
+            let random = generate_array(len, 17);
+            test_util_estimate::<TBitPacker>(&random, DeltaMode::NoDelta);
+            let sorted: Vec<u32> = random
+                .iter()
+                .scan(10u32, |acc, &val| {
+                    *acc = acc.wrapping_add(val % 1_000);
+                    Some(*acc)
+                })
+                .collect();
+            test_util_estimate::<TBitPacker>(&sorted, DeltaMode::Sorted { initial: 10 });
+            let strictly_sorted: Vec<u32> = (0..len as u32)
+                .map(|i| (u32::MAX - 3).wrapping_add(i * 2))
+                .collect();
+            test_util_estimate::<TBitPacker>(
+                &strictly_sorted,
+                DeltaMode::StrictlySorted { initial: None },
+            );
+            test_util_estimate::<TBitPacker>(
+                &strictly_sorted,
+                DeltaMode::StrictlySorted {
+                    initial: Some(u32::MAX - 5),
+                },
+            );
+        }
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_estimate_bitpacker1x() {
+        test_suite_estimate::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_estimate_bitpacker4x() {
+        test_suite_estimate::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_estimate_bitpacker8x() {
+        test_suite_estimate::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_estimate_bitpackerfastlanes() {
+        test_suite_estimate::<BitPackerFastLanes>();
+    }
+}
@@ -0,0 +1,266 @@
+//! GCD codec.
+//!
+//! Some columns only contain multiples of a common divisor: timestamps in milliseconds
+//! that are rounded to the second, prices in cents that are multiples of 5...
+//! Bitpacking them directly wastes `log2(gcd)` bits per integer.
+//!
+//! For each block, `GcdCodec` subtracts the block minimum, computes the greatest common
+//! divisor of the resulting values and divides them by it before bitpacking them with the
+//! underlying `BitPacker`. On decode, the multiplication and the addition of the minimum
+//! happen in-register, as the values are unpacked (see `BitPacker::decompress_scaled`).
+//!
+//! The minimum and the divisor are stored in a small header preceding the bitpacked values:
+//!
+//! | bytes    | content                                  |
+//! |:---------|:-----------------------------------------|
+//! | `[0..4)` | block minimum, as a little-endian `u32`  |
+//! | `[4..8)` | gcd, as a little-endian `u32`            |
+//! | `8`      | `num_bits` of the divided values         |
+
+use crate::{BitPacker, MAX_BLOCK_LEN};
+
+/// Size of the header preceding the bitpacked values.
+const HEADER_LEN: usize = 4 + 4 + 1;
+
+/// Codec dividing the values of a block by their greatest common divisor before
+/// bitpacking them.
+///
+/// It works with any of the `BitPacker` flavors, and shares their block length.
+///
+/// ```
+/// use bitpacking::{BitPacker, BitPacker4x, GcdCodec};
+///
+/// let prices: Vec<u32> = (0..BitPacker4x::BLOCK_LEN as u32)
+///     .map(|i| 1_000 + (i % 16) * 5)
+///     .collect();
+///
+/// let codec = GcdCodec::<BitPacker4x>::new();
+/// let mut compressed = vec![0u8; GcdCodec::<BitPacker4x>::max_compressed_block_size()];
+/// let compressed_len = codec.compress(&prices, &mut compressed);
+/// assert_eq!(compressed_len, GcdCodec::<BitPacker4x>::compressed_block_size(4));
+///
+/// let mut decompressed = vec![0u32; BitPacker4x::BLOCK_LEN];
+/// codec.decompress(&compressed, &mut decompressed);
+/// assert_eq!(prices, decompressed);
+/// ```
+#[derive(Clone, Copy)]
+pub struct GcdCodec<TBitPacker: BitPacker> {
+    bitpacker: TBitPacker,
+}
+
+impl<TBitPacker: BitPacker> Default for GcdCodec<TBitPacker> {
+    fn default() -> Self {
+        GcdCodec::new()
+    }
+}
+
+/// Binary GCD. `gcd(0, b) = b`.
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    if a == 0 {
+        return b;
+    }
+    if b == 0 {
+        return a;
+    }
+    let shift = (a | b).trailing_zeros();
+    a >>= a.trailing_zeros();
+    loop {
+        b >>= b.trailing_zeros();
+        if a > b {
+            std::mem::swap(&mut a, &mut b);
+        }
+        b -= a;
+        if b == 0 {
+            return a << shift;
+        }
+    }
+}
+
+impl<TBitPacker: BitPacker> GcdCodec<TBitPacker> {
+    /// Number of `u32` per compressed block.
+    pub const BLOCK_LEN: usize = TBitPacker::BLOCK_LEN;
+
+    /// Returns a codec relying on the best available implementation of `TBitPacker`.
+    pub fn new() -> Self {
+        GcdCodec {
+            bitpacker: TBitPacker::new(),
+        }
+    }
+
+    /// Returns the size of a compressed block, header included, given the
+    /// number of bits of its divided values.
+    #[must_use]
+    pub fn compressed_block_size(num_bits: u8) -> usize {
+        HEADER_LEN + TBitPacker::compressed_block_size(num_bits)
+    }
+
+    /// Returns the size of the largest possible compressed block.
+    ///
+    /// An output buffer of this size is always large enough for `.compress(..)`.
+    #[must_use]
+    pub fn max_compressed_block_size() -> usize {
+        Self::compressed_block_size(32)
+    }
+
+    /// Compress a block of `u32`.
+    ///
+    /// Returns the amount of bytes of the compressed block, header included.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    /// - Panics if `compressed` is too small to receive the compressed block.
+    pub fn compress(&self, decompressed: &[u32], compressed: &mut [u8]) -> usize {
+        assert_eq!(
+            decompressed.len(),
+            TBitPacker::BLOCK_LEN,
+            "`decompressed`'s len is not `BLOCK_LEN={}`",
+            TBitPacker::BLOCK_LEN
+        );
+        let min = decompressed.iter().copied().min().unwrap_or(0);
+        let divisor = decompressed
+            .iter()
+            .fold(0u32, |acc, &val| gcd(acc, val - min))
+            .max(1);
+        let mut divided = [0u32; MAX_BLOCK_LEN];
+        let divided = &mut divided[..TBitPacker::BLOCK_LEN];
+        if divisor == 1 {
+            for (dest, &val) in divided.iter_mut().zip(decompressed) {
+                *dest = val - min;
+            }
+        } else {
+            for (dest, &val) in divided.iter_mut().zip(decompressed) {
+                *dest = (val - min) / divisor;
+            }
+        }
+        let num_bits = self.bitpacker.num_bits(divided);
+        let compressed_len = Self::compressed_block_size(num_bits);
+        assert!(
+            compressed.len() >= compressed_len,
+            "Output array too small (numbits {}). {} < {}",
+            num_bits,
+            compressed.len(),
+            compressed_len
+        );
+        compressed[0..4].copy_from_slice(&min.to_le_bytes());
+        compressed[4..8].copy_from_slice(&divisor.to_le_bytes());
+        compressed[8] = num_bits;
+        HEADER_LEN
+            + self
+                .bitpacker
+                .compress(divided, &mut compressed[HEADER_LEN..], num_bits)
+    }
+
+    /// Decompress a block compressed with `.compress(..)`.
+    ///
+    /// Returns the amount of bytes that were consumed, header included.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed array is too short.
+    /// - Panics if the decompressed array is too short.
+    pub fn decompress(&self, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+        assert!(
+            compressed.len() >= HEADER_LEN,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            HEADER_LEN
+        );
+        let min = u32::from_le_bytes(compressed[0..4].try_into().unwrap());
+        let divisor = u32::from_le_bytes(compressed[4..8].try_into().unwrap());
+        let num_bits = compressed[8];
+        HEADER_LEN
+            + self.bitpacker.decompress_scaled(
+                min,
+                divisor,
+                &compressed[HEADER_LEN..],
+                decompressed,
+                num_bits,
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gcd, GcdCodec};
+    use crate::tests::generate_array;
+    use crate::BitPacker;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+
+    fn test_util_roundtrip<TBitPacker: BitPacker>(values: &[u32], expected_num_bits: Option<u8>) {
+        let codec = GcdCodec::<TBitPacker>::new();
+        let mut compressed = vec![0u8; GcdCodec::<TBitPacker>::max_compressed_block_size()];
+        let compressed_len = codec.compress(values, &mut compressed);
+        if let Some(expected_num_bits) = expected_num_bits {
+            assert_eq!(
+                compressed_len,
+                GcdCodec::<TBitPacker>::compressed_block_size(expected_num_bits)
+            );
+        }
+        let mut decompressed = vec![0u32; TBitPacker::BLOCK_LEN];
+        let read_len = codec.decompress(&compressed[..compressed_len], &mut decompressed);
+        assert_eq!(read_len, compressed_len);
+        assert_eq!(values, &decompressed[..]);
+    }
+
+    fn test_suite_gcd<TBitPacker: BitPacker>() {
+        let n = TBitPacker::BLOCK_LEN as u32;
+        let seconds: Vec<u32> = (0..n).map(|i| 1_600_000_000 + (i % 8) * 1_000).collect();
+        test_util_roundtrip::<TBitPacker>(&seconds, Some(3));
+        let huge_divisor: Vec<u32> = (0..n).map(|i| (i % 2) * (u32::MAX - 1)).collect();
+        test_util_roundtrip::<TBitPacker>(&huge_divisor, Some(1));
+        let constant = vec![u32::MAX; TBitPacker::BLOCK_LEN];
+        test_util_roundtrip::<TBitPacker>(&constant, Some(0));
+        for num_bits in [1, 7, 31, 32] {
+            let multiples: Vec<u32> = generate_array(TBitPacker::BLOCK_LEN, num_bits)
+                .into_iter()
+                .map(|val| val.wrapping_mul(6) + 7)
+                .collect();
+            test_util_roundtrip::<TBitPacker>(&multiples, None);
+            let random = generate_array(TBitPacker::BLOCK_LEN, num_bits);
+            test_util_roundtrip::<TBitPacker>(&random, None);
+        }
+    }
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(gcd(0, 12), 12);
+        assert_eq!(gcd(12, 0), 12);
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(1_000, 3_000_000), 1_000);
+        assert_eq!(gcd(17, 5), 1);
+        assert_eq!(gcd(u32::MAX, u32::MAX - 1), 1);
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_gcd_bitpacker1x() {
+        test_suite_gcd::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_gcd_bitpacker4x() {
+        test_suite_gcd::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_gcd_bitpacker8x() {
+        test_suite_gcd::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_gcd_bitpackerfastlanes() {
+        test_suite_gcd::<BitPackerFastLanes>();
+    }
+}
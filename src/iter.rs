@@ -0,0 +1,269 @@
+//! Lazy iteration over a stream of compressed blocks.
+//!
+//! `BlockIter` decodes the blocks one at a time, when their first integer is requested,
+//! into a buffer it owns. `nth(..)`, and therefore `skip(..)`, jump over the blocks that
+//! hold none of the requested integers. Plain blocks are jumped over without being
+//! decoded. Sorted blocks are still decoded, as the initial value of a block is the last
+//! integer of the previous one.
+
+use crate::delta_mode::next_mode;
+use crate::{BitPacker, DeltaMode, MAX_BLOCK_LEN};
+
+/// Iterator over the integers of a stream of compressed blocks, as returned by
+/// `BitPacker::iter(..)`.
+#[derive(Clone)]
+pub struct BlockIter<'a, TBitPacker: BitPacker> {
+    bitpacker: TBitPacker,
+    mode: DeltaMode,
+    /// Compressed blocks that were not decoded yet.
+    compressed: &'a [u8],
+    /// Numbers of bits of the blocks that were not decoded yet.
+    num_bits: &'a [u8],
+    /// Amount of integers of the blocks that were not decoded yet.
+    len: usize,
+    block: [u32; MAX_BLOCK_LEN],
+    /// Range of the integers of `block` that were not returned yet.
+    start: usize,
+    end: usize,
+}
+
+impl<'a, TBitPacker: BitPacker> BlockIter<'a, TBitPacker> {
+    pub(crate) fn new(
+        bitpacker: TBitPacker,
+        mode: DeltaMode,
+        compressed: &'a [u8],
+        num_bits: &'a [u8],
+        len: usize,
+    ) -> Self {
+        assert_eq!(
+            num_bits.len(),
+            len.div_ceil(TBitPacker::BLOCK_LEN),
+            "`num_bits`'s len is not the number of blocks of `len` integers"
+        );
+        let compressed_len: usize = num_bits
+            .iter()
+            .map(|&num_bits| TBitPacker::compressed_block_size(num_bits))
+            .sum();
+        assert!(
+            compressed.len() >= compressed_len,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            compressed_len
+        );
+        BlockIter {
+            bitpacker,
+            mode,
+            compressed,
+            num_bits,
+            len,
+            block: [0u32; MAX_BLOCK_LEN],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Decodes the next block. Returns `false` if there is none.
+    fn decode_block(&mut self) -> bool {
+        let Some((&num_bits, num_bits_tail)) = self.num_bits.split_first() else {
+            return false;
+        };
+        let block = &mut self.block[..TBitPacker::BLOCK_LEN];
+        let compressed = self.compressed;
+        let block_size = match self.mode {
+            DeltaMode::NoDelta => self.bitpacker.decompress(compressed, block, num_bits),
+            DeltaMode::Sorted { initial } => self
+                .bitpacker
+                .decompress_sorted(initial, compressed, block, num_bits),
+            DeltaMode::StrictlySorted { initial } => self
+                .bitpacker
+                .decompress_strictly_sorted(initial, compressed, block, num_bits),
+        };
+        self.mode = next_mode(self.mode, block);
+        self.compressed = &compressed[block_size..];
+        self.num_bits = num_bits_tail;
+        self.start = 0;
+        self.end = self.len.min(TBitPacker::BLOCK_LEN);
+        self.len -= self.end;
+        true
+    }
+
+    /// Jumps over the next `num_blocks` blocks, which must all be full.
+    fn skip_blocks(&mut self, num_blocks: usize) {
+        if let DeltaMode::NoDelta = self.mode {
+            let (skipped, num_bits) = self.num_bits.split_at(num_blocks);
+            let skipped_len: usize = skipped
+                .iter()
+                .map(|&num_bits| TBitPacker::compressed_block_size(num_bits))
+                .sum();
+            self.compressed = &self.compressed[skipped_len..];
+            self.num_bits = num_bits;
+            self.len -= num_blocks * TBitPacker::BLOCK_LEN;
+        } else {
+            for _ in 0..num_blocks {
+                self.decode_block();
+            }
+        }
+        self.start = 0;
+        self.end = 0;
+    }
+}
+
+impl<TBitPacker: BitPacker> Iterator for BlockIter<'_, TBitPacker> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.start == self.end && !self.decode_block() {
+            return None;
+        }
+        let val = self.block[self.start];
+        self.start += 1;
+        Some(val)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<u32> {
+        let buffered = self.end - self.start;
+        if n < buffered {
+            self.start += n;
+            return self.next();
+        }
+        let n = n - buffered;
+        if n >= self.len {
+            self.num_bits = &[];
+            self.len = 0;
+            self.start = 0;
+            self.end = 0;
+            return None;
+        }
+        self.skip_blocks(n / TBitPacker::BLOCK_LEN);
+        self.decode_block();
+        self.start = n % TBitPacker::BLOCK_LEN;
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<TBitPacker: BitPacker> ExactSizeIterator for BlockIter<'_, TBitPacker> {
+    fn len(&self) -> usize {
+        self.end - self.start + self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::delta_mode::next_mode;
+    use crate::tests::generate_array;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+    use crate::{BitPacker, DeltaMode};
+
+    fn test_util_iter<TBitPacker: BitPacker>(values: &[u32], mode: DeltaMode) {
+        let bitpacker = TBitPacker::new();
+        let mut padded = values.to_vec();
+        let last = values.last().copied().unwrap_or(0);
+        while !padded.len().is_multiple_of(TBitPacker::BLOCK_LEN) {
+            padded.push(last.wrapping_add((padded.len() - values.len() + 1) as u32));
+        }
+        let mut compressed = Vec::new();
+        let mut num_bits = Vec::new();
+        let mut block_mode = mode;
+        for block in padded.chunks(TBitPacker::BLOCK_LEN) {
+            let mut output = vec![0u8; 4 * TBitPacker::BLOCK_LEN];
+            let (block_num_bits, block_len) = match block_mode {
+                DeltaMode::NoDelta => bitpacker.compress_auto(block, &mut output),
+                DeltaMode::Sorted { initial } => {
+                    bitpacker.compress_sorted_auto(initial, block, &mut output)
+                }
+                DeltaMode::StrictlySorted { initial } => {
+                    bitpacker.compress_strictly_sorted_auto(initial, block, &mut output)
+                }
+            };
+            compressed.extend_from_slice(&output[..block_len]);
+            num_bits.push(block_num_bits);
+            block_mode = next_mode(block_mode, block);
+        }
+
+        let iter = bitpacker.iter(mode, &compressed, &num_bits, values.len());
+        assert_eq!(iter.len(), values.len());
+        assert_eq!(iter.collect::<Vec<u32>>(), values);
+
+        let block_len = TBitPacker::BLOCK_LEN;
+        for n in [
+            0,
+            1,
+            block_len - 1,
+            block_len,
+            2 * block_len + 3,
+            values.len(),
+        ] {
+            let mut iter = bitpacker.iter(mode, &compressed, &num_bits, values.len());
+            assert_eq!(iter.nth(n), values.get(n).copied());
+            assert_eq!(iter.len(), values.len().saturating_sub(n + 1));
+            assert!(iter.eq(values.iter().copied().skip(n + 1)));
+        }
+
+        // Consecutive jumps, starting within a decoded block.
+        let mut iter = bitpacker.iter(mode, &compressed, &num_bits, values.len());
+        let mut expected = values.iter().copied();
+        for n in [2, block_len + 1, 0, 3 * block_len] {
+            assert_eq!(iter.nth(n), expected.nth(n));
+            assert_eq!(iter.len(), expected.len());
+        }
+    }
+
+    fn test_suite_iter<TBitPacker: BitPacker>() {
+        let block_len = TBitPacker::BLOCK_LEN;
+        for len in [0, 1, block_len - 1, block_len, 3 * block_len + 5] {
+            let random = generate_array(len, 17);
+            test_util_iter::<TBitPacker>(&random, DeltaMode::NoDelta);
+            let sorted: Vec<u32> = random
+                .iter()
+                .scan(10u32, |acc, &val| {
+                    *acc = acc.wrapping_add(val % 1_000);
+                    Some(*acc)
+                })
+                .collect();
+            test_util_iter::<TBitPacker>(&sorted, DeltaMode::Sorted { initial: 10 });
+            let strictly_sorted: Vec<u32> = (0..len as u32)
+                .map(|i| (u32::MAX - 3).wrapping_add(i * 2))
+                .collect();
+            test_util_iter::<TBitPacker>(
+                &strictly_sorted,
+                DeltaMode::StrictlySorted { initial: None },
+            );
+        }
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_iter_bitpacker1x() {
+        test_suite_iter::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_iter_bitpacker4x() {
+        test_suite_iter::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_iter_bitpacker8x() {
+        test_suite_iter::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_iter_bitpackerfastlanes() {
+        test_suite_iter::<BitPackerFastLanes>();
+    }
+}
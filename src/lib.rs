@@ -22,6 +22,9 @@ sets are not available. For instance :
 
 I recommend using `BitPacker4x` if you are in doubt.
 
+The compressed format does not depend on the target: blocks are made of `u32`
+words stored in little-endian, and big-endian targets byte-swap them.
+
 See the [`BitPacker` trait](./trait.BitPacker.html) for example usage.
 
 */
@@ -30,6 +33,8 @@ See the [`BitPacker` trait](./trait.BitPacker.html) for example usage.
 #![warn(missing_docs)]
 
 use std::marker::Sized;
+use std::mem::MaybeUninit;
+use std::slice;
 
 #[cfg(test)]
 #[macro_use]
@@ -45,6 +50,7 @@ trait Available {
 }
 
 trait UnsafeBitPacker {
+    #[allow(dead_code)]
     const BLOCK_LEN: usize;
     unsafe fn compress(decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize;
     unsafe fn compress_sorted(
@@ -59,7 +65,35 @@ trait UnsafeBitPacker {
         compressed: &mut [u8],
         num_bits: u8,
     ) -> usize;
+    unsafe fn compress_for(
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn compress_masked(decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize;
+    unsafe fn compress_auto(decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize);
+    unsafe fn compress_sorted_auto(
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize);
+    unsafe fn compress_strictly_sorted_auto(
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize);
     unsafe fn decompress(compressed: &[u8], decompressed: &mut [u32], num_bits: u8) -> usize;
+    unsafe fn compress_uninit(
+        decompressed: &[u32],
+        compressed: &mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn decompress_uninit(
+        compressed: &[u8],
+        decompressed: &mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> usize;
     unsafe fn decompress_sorted(
         initial: u32,
         compressed: &[u8],
@@ -72,11 +106,100 @@ trait UnsafeBitPacker {
         decompressed: &mut [u32],
         num_bits: u8,
     ) -> usize;
+    unsafe fn decompress_scaled(
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn compress_u8(decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize;
+    unsafe fn compress_u16(decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize;
+    unsafe fn decompress_into_u8(compressed: &[u8], decompressed: &mut [u8], num_bits: u8)
+        -> usize;
+    unsafe fn decompress_into_u16(
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn decompress_into_u64(
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn decompress_sorted_into_u64(
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn decompress_to_sink<TSink: Sink>(
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn decompress_sorted_to_sink<TSink: Sink>(
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize;
+    unsafe fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize;
     unsafe fn num_bits(decompressed: &[u32]) -> u8;
     unsafe fn num_bits_sorted(initial: u32, decompressed: &[u32]) -> u8;
     unsafe fn num_bits_strictly_sorted(initial: Option<u32>, decompressed: &[u32]) -> u8;
+    unsafe fn num_bits_sorted_checked(
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError>;
+    unsafe fn num_bits_strictly_sorted_checked(
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError>;
+    unsafe fn num_bits_all_modes(initial: u32, decompressed: &[u32]) -> BlockWidths;
 }
 
+/// Consumer of the integers of a block, as they are decoded by
+/// `BitPacker::decompress_to_sink(..)` and its sorted variants.
+///
+/// A block is decoded one SIMD register at a time, and each register is handed to the
+/// sink without going through an output array. `.process(..)` is called 32 times per
+/// block, in order, with `BLOCK_LEN / 32` integers each time: the chunk `i` holds the
+/// integers `[i * BLOCK_LEN / 32, (i + 1) * BLOCK_LEN / 32)` of the decompressed block.
+///
+/// Closures taking a `&[u32]` are sinks.
+pub trait Sink {
+    /// Receives the next `BLOCK_LEN / 32` integers of the block.
+    fn process(&mut self, values: &[u32]);
+}
+
+impl<F: FnMut(&[u32])> Sink for F {
+    fn process(&mut self, values: &[u32]) {
+        self(values);
+    }
+}
+
+mod private {
+    /// Keeps `BitPacker` from being implemented outside of this crate. Its default
+    /// methods rely on `.compress_uninit(..)` and `.decompress_uninit(..)` initializing
+    /// the slices they return.
+    pub trait Sealed {}
+
+    /// Implemented by the bitpackers whose rows hold their integers in order, as
+    /// `Transcoder` reads and writes them.
+    pub trait InOrderRows {}
+}
+
+/// Bitpacking of blocks of `BLOCK_LEN` integers.
+///
+/// The trait is sealed: it is only implemented by the bitpackers of this crate.
+///
 /// # Examples without delta-encoding
 /// ```
 /// use bitpacking::{BitPacker4x, BitPacker};
@@ -181,7 +304,7 @@ trait UnsafeBitPacker {
 ///
 /// assert_eq!(&my_data, &decompressed);
 /// # }
-pub trait BitPacker: Sized + Clone + Copy {
+pub trait BitPacker: private::Sealed + Sized + Clone + Copy {
     /// Number of `u32` per compressed block
     const BLOCK_LEN: usize;
 
@@ -196,7 +319,8 @@ pub trait BitPacker: Sized + Clone + Copy {
     /// Compress a block of `u32`.
     ///
     /// Assumes that the integers are all lower than `2^num_bits`.
-    /// The result is undefined if they are larger.
+    /// The result is undefined if they are larger: see `.compress_masked(..)` and
+    /// `.compress_checked(..)` for a defined behavior.
     ///
     /// Returns the amount of bytes of the compressed block.
     ///
@@ -206,15 +330,135 @@ pub trait BitPacker: Sized + Clone + Copy {
     /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
     fn compress(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize;
 
+    /// Compress a block of `u32`, truncating each integer to its `num_bits` lowest bits.
+    ///
+    /// Returns the amount of bytes of the compressed block.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed destination array is too small
+    /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn compress_masked(&self, decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize;
+
+    /// Compress a block of `u32`, checking that the integers are all lower than
+    /// `2^num_bits`.
+    ///
+    /// Returns the amount of bytes of the compressed block, or
+    /// `CompressError::ValueTooWide` for the first integer that is too large, in which
+    /// case nothing is written.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x, CompressError};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let mut values = vec![3u32; BitPacker4x::BLOCK_LEN];
+    /// let mut compressed = vec![0u8; BitPacker4x::compressed_block_size(2)];
+    /// assert_eq!(bitpacker.compress_checked(&values, &mut compressed, 2), Ok(32));
+    ///
+    /// values[7] = 4;
+    /// assert_eq!(
+    ///     bitpacker.compress_checked(&values, &mut compressed, 2),
+    ///     Err(CompressError::ValueTooWide { index: 7, value: 4, num_bits: 2 })
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed destination array is too small
+    /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn compress_checked(
+        &self,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> Result<usize, CompressError> {
+        if self.num_bits(decompressed) > num_bits {
+            let (index, &value) = decompressed
+                .iter()
+                .enumerate()
+                .find(|(_, &value)| most_significant_bit(value) > num_bits)
+                .unwrap();
+            return Err(CompressError::ValueTooWide {
+                index,
+                value,
+                num_bits,
+            });
+        }
+        Ok(self.compress(decompressed, compressed, num_bits))
+    }
+
+    /// Compress a block of `u32` with the minimum number of bits, as computed by
+    /// `.num_bits(..)`.
+    ///
+    /// The block is read once: its registers are OR-accumulated and staged as they are
+    /// loaded, and then packed.
+    ///
+    /// Returns the number of bits and the amount of bytes of the compressed block.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..128).map(|i| i % 10).collect();
+    /// let mut compressed = vec![0u8; 4 * BitPacker4x::BLOCK_LEN];
+    /// let (num_bits, compressed_len) = bitpacker.compress_auto(&values, &mut compressed);
+    /// assert_eq!(num_bits, 4);
+    /// assert_eq!(compressed_len, BitPacker4x::compressed_block_size(4));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed destination array is too small
+    /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn compress_auto(&self, decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize);
+
+    /// Delta encode and compress a sorted block of `u32` with the minimum number of bits,
+    /// as computed by `.num_bits_sorted(..)`, reading the block once.
+    ///
+    /// See `.compress_sorted(..)` and `.compress_auto(..)`.
+    ///
+    /// Returns the number of bits and the amount of bytes of the compressed block.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed destination array is too small
+    /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn compress_sorted_auto(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize);
+
+    /// Delta encode and compress a strictly sorted block of `u32` with the minimum number
+    /// of bits, as computed by `.num_bits_strictly_sorted(..)`, reading the block once.
+    ///
+    /// See `.compress_strictly_sorted(..)` and `.compress_auto(..)`.
+    ///
+    /// Returns the number of bits and the amount of bytes of the compressed block.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed destination array is too small
+    /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn compress_strictly_sorted_auto(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> (u8, usize);
+
     /// Delta encode and compressed the `decompressed` array.
     ///
     /// Assumes that the elements in the `decompressed` array are sorted.
     /// `initial` will be used to compute the first `delta`.
     ///
+    /// The order is not checked: the result is undefined if `initial` is greater than
+    /// `decompressed[0]` or if `decompressed` is not sorted. See
+    /// `.num_bits_sorted_checked(..)`.
+    ///
     /// # Panics
     ///
-    /// - Panics if `initial` is greater than `decompressed[0]`
-    /// - Panics if `decompressed` is not sorted
     /// - Panics if `decompressed`'s length is not exactly `BLOCK_LEN`
     /// - Panics if `compressed` is not large enough to receive the compressed data
     /// - Panics if the compressed destination array is too small.
@@ -243,10 +487,12 @@ pub trait BitPacker: Sized + Clone + Copy {
     /// every number appears in sequence), but isn't very different from the sorted compressor
     /// on more sparse datasets.
     ///
+    /// The order is not checked: the result is undefined if `initial` is greater or
+    /// equal to `decompressed[0]` or if `decompressed` isn't strictly monotonic. See
+    /// `.num_bits_strictly_sorted_checked(..)`.
+    ///
     /// # Panics
     ///
-    /// - Panics if `initial` is greater or equal to `decompressed[0]`
-    /// - Panics if `decompressed` isn't strictly monotonic
     /// - Panics if `decompressed`'s length is not exactly `BLOCK_LEN`
     /// - Panics if `compressed` is not large enough to receive the compressed data
     ///
@@ -268,6 +514,37 @@ pub trait BitPacker: Sized + Clone + Copy {
     /// Panics if the compressed array is too short, or the decompressed array is too short.
     fn decompress(&self, compressed: &[u8], decompressed: &mut [u32], num_bits: u8) -> usize;
 
+    /// Same as `.decompress(..)`, for compressed blocks coming from an untrusted source.
+    ///
+    /// Rather than panicking, returns an error if `num_bits` is greater than 32, if
+    /// `compressed` is shorter than the compressed block, or if `decompressed` is
+    /// shorter than `BLOCK_LEN`.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x, DecompressError};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let mut decompressed = vec![0u32; BitPacker4x::BLOCK_LEN];
+    /// assert_eq!(
+    ///     bitpacker.decompress_checked(&[0u8; 64], &mut decompressed, 33),
+    ///     Err(DecompressError::InvalidNumBits { num_bits: 33 })
+    /// );
+    /// assert_eq!(
+    ///     bitpacker.decompress_checked(&[0u8; 64], &mut decompressed, 5),
+    ///     Err(DecompressError::Truncated { len: 64, expected_len: 80 })
+    /// );
+    /// assert_eq!(bitpacker.decompress_checked(&[0u8; 64], &mut decompressed, 4), Ok(64));
+    /// ```
+    fn decompress_checked(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> Result<usize, DecompressError> {
+        check_decompress_args::<Self>(compressed, decompressed, num_bits)?;
+        Ok(self.decompress(compressed, decompressed, num_bits))
+    }
+
     /// Decompress the`compress`array to the `decompressed` array.
     /// The `compressed` array is assumed to have been delta-encoded and compressed.
     ///
@@ -288,6 +565,29 @@ pub trait BitPacker: Sized + Clone + Copy {
         num_bits: u8,
     ) -> usize;
 
+    /// Same as `.decompress_sorted(..)`, for compressed blocks coming from an untrusted
+    /// source that must decode into a sorted block.
+    ///
+    /// On top of the checks of `.decompress_checked(..)`, returns
+    /// `DecompressError::Overflow` if integrating the deltas overflows, that is, if the
+    /// decompressed block is not sorted or does not start at `initial` or above. The
+    /// integers of the block have been written to `decompressed` in that case.
+    fn decompress_sorted_checked(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> Result<usize, DecompressError> {
+        check_decompress_args::<Self>(compressed, decompressed, num_bits)?;
+        let read = self.decompress_sorted(initial, compressed, decompressed, num_bits);
+        let block = &decompressed[..Self::BLOCK_LEN];
+        if let Some(index) = first_unsorted(Some(initial), false, block) {
+            return Err(DecompressError::Overflow { index });
+        }
+        Ok(read)
+    }
+
     /// Decompress the`compress`array to the `decompressed` array.
     /// The `compressed` array is assumed to have been strict-delta-encoded and compressed.
     ///
@@ -300,7 +600,6 @@ pub trait BitPacker: Sized + Clone + Copy {
     ///
     /// - Panics if the compressed array is too short to contain `BLOCK_LEN` elements
     /// - Panics if the decompressed array is too short.
-
     fn decompress_strictly_sorted(
         &self,
         initial: Option<u32>,
@@ -309,6 +608,478 @@ pub trait BitPacker: Sized + Clone + Copy {
         num_bits: u8,
     ) -> usize;
 
+    /// Same as `.compress(..)`, but compresses into a possibly uninitialized buffer.
+    ///
+    /// Every byte of the compressed block is written, so that the buffer does not need to
+    /// be zeroed beforehand. Returns the compressed block, as an initialized slice of
+    /// `compressed`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed destination array is too small
+    /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn compress_uninit<'a>(
+        &self,
+        decompressed: &[u32],
+        compressed: &'a mut [MaybeUninit<u8>],
+        num_bits: u8,
+    ) -> &'a mut [u8];
+
+    /// Same as `.decompress(..)`, but decompresses into a possibly uninitialized buffer.
+    ///
+    /// The `BLOCK_LEN` first integers of `decompressed` are written, so that the buffer
+    /// does not need to be zeroed beforehand. Returns them, as an initialized slice of
+    /// `decompressed`.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    /// use std::mem::MaybeUninit;
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..128).collect();
+    /// let mut compressed = [MaybeUninit::uninit(); 4 * BitPacker4x::BLOCK_LEN];
+    /// let compressed = bitpacker.compress_uninit(&values, &mut compressed, 7);
+    ///
+    /// let mut decompressed = [MaybeUninit::uninit(); BitPacker4x::BLOCK_LEN];
+    /// let decompressed = bitpacker.decompress_uninit(compressed, &mut decompressed, 7);
+    /// assert_eq!(&values[..], decompressed);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short, or the decompressed array is too short.
+    fn decompress_uninit<'a>(
+        &self,
+        compressed: &[u8],
+        decompressed: &'a mut [MaybeUninit<u32>],
+        num_bits: u8,
+    ) -> &'a mut [u32];
+
+    /// Compresses a block of `u32`, and appends the compressed block to `output`.
+    ///
+    /// The block is compressed directly in the spare capacity of `output`, which is
+    /// grown if needed. Returns the amount of bytes appended.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn compress_append(&self, decompressed: &[u32], num_bits: u8, output: &mut Vec<u8>) -> usize {
+        output.reserve(Self::compressed_block_size(num_bits));
+        let compressed_len = self
+            .compress_uninit(decompressed, output.spare_capacity_mut(), num_bits)
+            .len();
+        // SAFETY: `BitPacker` is sealed, and the `compress_uninit` implementations of this
+        // crate return the prefix of the spare capacity that their kernels wrote.
+        unsafe { output.set_len(output.len() + compressed_len) };
+        compressed_len
+    }
+
+    /// Decompresses a block, and appends its `BLOCK_LEN` integers to `output`.
+    ///
+    /// The block is decompressed directly in the spare capacity of `output`, which is
+    /// grown if needed. Returns the amount of bytes that were consumed.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..256).map(|i| i % 100).collect();
+    /// let mut compressed = Vec::new();
+    /// for block in values.chunks(BitPacker4x::BLOCK_LEN) {
+    ///     bitpacker.compress_append(block, 7, &mut compressed);
+    /// }
+    ///
+    /// let mut decompressed = Vec::new();
+    /// let mut offset = 0;
+    /// while offset < compressed.len() {
+    ///     offset += bitpacker.decompress_append(&compressed[offset..], 7, &mut decompressed);
+    /// }
+    /// assert_eq!(values, decompressed);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short.
+    fn decompress_append(&self, compressed: &[u8], num_bits: u8, output: &mut Vec<u32>) -> usize {
+        output.reserve(Self::BLOCK_LEN);
+        let read = Self::compressed_block_size(num_bits);
+        self.decompress_uninit(compressed, output.spare_capacity_mut(), num_bits);
+        // SAFETY: `BitPacker` is sealed, and the `decompress_uninit` implementations of
+        // this crate write the `BLOCK_LEN` first integers of the spare capacity.
+        unsafe { output.set_len(output.len() + Self::BLOCK_LEN) };
+        read
+    }
+
+    /// Compresses a block of `u32`, and returns the compressed block.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..128).collect();
+    /// let compressed = bitpacker.encode_to_vec(&values, 7);
+    /// assert_eq!(compressed.len(), BitPacker4x::compressed_block_size(7));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    fn encode_to_vec(&self, decompressed: &[u32], num_bits: u8) -> Vec<u8> {
+        let mut compressed = Vec::with_capacity(Self::compressed_block_size(num_bits));
+        self.compress_append(decompressed, num_bits, &mut compressed);
+        compressed
+    }
+
+    /// Compresses a block of `u32`, and appends the compressed block to `buf`.
+    ///
+    /// The block is compressed directly in the next chunk of `buf` if it is large
+    /// enough, and copied from a scratch block otherwise.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    /// use bytes::{Buf, BytesMut};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..256).map(|i| i % 100).collect();
+    /// let mut buf = BytesMut::new();
+    /// for block in values.chunks(BitPacker4x::BLOCK_LEN) {
+    ///     bitpacker.encode_to_buf(block, 7, &mut buf);
+    /// }
+    ///
+    /// let mut payload = buf.freeze();
+    /// let mut decompressed = vec![0u32; 256];
+    /// for block in decompressed.chunks_mut(BitPacker4x::BLOCK_LEN) {
+    ///     bitpacker.decode_from_buf(&mut payload, block, 7);
+    /// }
+    /// assert!(!payload.has_remaining());
+    /// assert_eq!(values, decompressed);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed` length is not exactly the `BLOCK_LEN`.
+    /// - Panics if `buf` does not have enough remaining capacity.
+    #[cfg(feature = "bytes")]
+    fn encode_to_buf<B: bytes::BufMut>(&self, decompressed: &[u32], num_bits: u8, buf: &mut B) {
+        let compressed_len = Self::compressed_block_size(num_bits);
+        let chunk = buf.chunk_mut();
+        if chunk.len() >= compressed_len {
+            // SAFETY: `compress_uninit` only writes to the chunk.
+            let written = self
+                .compress_uninit(
+                    decompressed,
+                    unsafe { chunk.as_uninit_slice_mut() },
+                    num_bits,
+                )
+                .len();
+            // SAFETY: the `written` first bytes of the chunk have been initialized.
+            unsafe { buf.advance_mut(written) };
+        } else {
+            let mut scratch = [0u8; 4 * MAX_BLOCK_LEN];
+            let written = self.compress(decompressed, &mut scratch, num_bits);
+            buf.put_slice(&scratch[..written]);
+        }
+    }
+
+    /// Decompresses a block read from `buf`, and advances `buf` past it.
+    ///
+    /// The block is decompressed directly from the current chunk of `buf` if it holds
+    /// the whole block, and copied to a scratch block otherwise.
+    ///
+    /// Returns the amount of bytes that were consumed.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `buf` does not hold a whole compressed block.
+    /// - Panics if the decompressed array is too short.
+    #[cfg(feature = "bytes")]
+    fn decode_from_buf<B: bytes::Buf>(
+        &self,
+        buf: &mut B,
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize {
+        let compressed_len = Self::compressed_block_size(num_bits);
+        if buf.chunk().len() >= compressed_len {
+            let read = self.decompress(&buf.chunk()[..compressed_len], decompressed, num_bits);
+            buf.advance(read);
+            read
+        } else {
+            let mut scratch = [0u8; 4 * MAX_BLOCK_LEN];
+            buf.copy_to_slice(&mut scratch[..compressed_len]);
+            self.decompress(&scratch[..compressed_len], decompressed, num_bits)
+        }
+    }
+
+    /// Compresses `decompressed - reference`, the subtraction being fused into the
+    /// packing kernel.
+    ///
+    /// Values lower than `reference` wrap around, and will most likely not fit
+    /// in `num_bits`. `reference` is typically the minimum of the block.
+    ///
+    /// The block is decompressed with `.decompress_scaled(reference, 1, ..)`.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    /// - Panics if `compressed` is not large enough to receive the compressed data.
+    /// - Panics if `num_bits` is > 32.
+    fn compress_for(
+        &self,
+        reference: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+        num_bits: u8,
+    ) -> usize;
+
+    /// Same as `.decompress_strictly_sorted(..)`, for compressed blocks coming from an
+    /// untrusted source that must decode into a strictly sorted block.
+    ///
+    /// See `.decompress_sorted_checked(..)`: integrating the deltas must produce strictly
+    /// increasing integers, all greater than `initial`, if any.
+    fn decompress_strictly_sorted_checked(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> Result<usize, DecompressError> {
+        check_decompress_args::<Self>(compressed, decompressed, num_bits)?;
+        let read = self.decompress_strictly_sorted(initial, compressed, decompressed, num_bits);
+        let block = &decompressed[..Self::BLOCK_LEN];
+        if let Some(index) = first_unsorted(initial, true, block) {
+            return Err(DecompressError::Overflow { index });
+        }
+        Ok(read)
+    }
+
+    /// Decompress the `compress` array to the `decompressed` array, mapping
+    /// each decoded integer `x` to `offset + factor * x`.
+    ///
+    /// The multiplication and the addition are wrapping, and are applied to the
+    /// registers as they are unpacked.
+    ///
+    /// Returns the amount of bytes that were consumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short, or the decompressed array is too short.
+    fn decompress_scaled(
+        &self,
+        offset: u32,
+        factor: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        num_bits: u8,
+    ) -> usize;
+
+    /// Same as `.compress(..)`, for a block of `u8`.
+    ///
+    /// The integers are widened to `u32` in SIMD registers, as they are loaded.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let pixels: Vec<u8> = (0..128).map(|i| (i % 64) as u8).collect();
+    /// let mut compressed = [0u8; 4 * 32];
+    /// let len = bitpacker.compress_u8(&pixels, &mut compressed, 6);
+    /// assert_eq!(len, BitPacker4x::compressed_block_size(6));
+    ///
+    /// let mut decompressed = [0u8; 128];
+    /// bitpacker.decompress_into_u8(&compressed, &mut decompressed, 6);
+    /// assert_eq!(&pixels[..], &decompressed[..]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `num_bits` is greater than 8.
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`, or if `compressed`
+    ///   is too short.
+    fn compress_u8(&self, decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize;
+
+    /// Same as `.compress(..)`, for a block of `u16`.
+    ///
+    /// The integers are widened to `u32` in SIMD registers, as they are loaded.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `num_bits` is greater than 16.
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`, or if `compressed`
+    ///   is too short.
+    fn compress_u16(&self, decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize;
+
+    /// Same as `.decompress(..)`, writing the integers as `u8`.
+    ///
+    /// The integers are narrowed in SIMD registers, before they are stored.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `num_bits` is greater than 8.
+    /// - Panics if the compressed array is too short, or the decompressed array is too
+    ///   short.
+    fn decompress_into_u8(&self, compressed: &[u8], decompressed: &mut [u8], num_bits: u8)
+        -> usize;
+
+    /// Same as `.decompress(..)`, writing the integers as `u16`.
+    ///
+    /// The integers are narrowed in SIMD registers, before they are stored.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `num_bits` is greater than 16.
+    /// - Panics if the compressed array is too short, or the decompressed array is too
+    ///   short.
+    fn decompress_into_u16(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u16],
+        num_bits: u8,
+    ) -> usize;
+
+    /// Same as `.decompress(..)`, writing the integers as `u64`, and adding `base` to
+    /// them.
+    ///
+    /// The integers are widened, and `base` is added, in SIMD registers. The addition is
+    /// wrapping.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let doc_ids: Vec<u32> = (0..128).map(|i| i * 3).collect();
+    /// let mut compressed = [0u8; 4 * 128];
+    /// let num_bits = bitpacker.num_bits(&doc_ids);
+    /// bitpacker.compress(&doc_ids, &mut compressed, num_bits);
+    ///
+    /// let segment_base = 5_000_000_000u64;
+    /// let mut global_doc_ids = [0u64; 128];
+    /// bitpacker.decompress_into_u64(segment_base, &compressed, &mut global_doc_ids, num_bits);
+    /// assert_eq!(global_doc_ids[1], segment_base + 3);
+    /// assert_eq!(global_doc_ids[127], segment_base + 381);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short, or the decompressed array is too
+    /// short.
+    fn decompress_into_u64(
+        &self,
+        base: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize;
+
+    /// Same as `.decompress_sorted(..)`, writing the integers as `u64`, with a `u64`
+    /// initial value.
+    ///
+    /// The deltas are integrated relative to `initial`, and widened in SIMD registers.
+    /// The block must be compressed from sorted integers, so that its deltas sum to
+    /// less than `2^32`. A block compressed with `.compress_sorted(init, ..)` decodes
+    /// into its integers plus `base` when `initial` is `base + init`.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let doc_ids: Vec<u32> = (0..128).map(|i| 10 + i * 3).collect();
+    /// let mut compressed = [0u8; 4 * 128];
+    /// let num_bits = bitpacker.num_bits_sorted(7, &doc_ids);
+    /// bitpacker.compress_sorted(7, &doc_ids, &mut compressed, num_bits);
+    ///
+    /// let segment_base = 5_000_000_000u64;
+    /// let mut global_doc_ids = [0u64; 128];
+    /// bitpacker.decompress_sorted_into_u64(
+    ///     segment_base + 7,
+    ///     &compressed,
+    ///     &mut global_doc_ids,
+    ///     num_bits,
+    /// );
+    /// assert_eq!(global_doc_ids[0], segment_base + 10);
+    /// assert_eq!(global_doc_ids[127], segment_base + 391);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short, or the decompressed array is too
+    /// short.
+    fn decompress_sorted_into_u64(
+        &self,
+        initial: u64,
+        compressed: &[u8],
+        decompressed: &mut [u64],
+        num_bits: u8,
+    ) -> usize;
+
+    /// Decompresses the `compressed` block, handing its integers over to `sink` one
+    /// SIMD register at a time, rather than writing them to an array.
+    ///
+    /// See [`Sink`] for the order of the integers.
+    ///
+    /// Returns the amount of bytes that were consumed.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..128).map(|i| i % 10).collect();
+    /// let mut compressed = [0u8; 4 * 128];
+    /// let (num_bits, _) = bitpacker.compress_auto(&values, &mut compressed);
+    ///
+    /// // Builds a histogram, without decompressing the block to an array.
+    /// let mut histogram = [0usize; 10];
+    /// let mut sink = |chunk: &[u32]| {
+    ///     assert_eq!(chunk.len(), BitPacker4x::BLOCK_LEN / 32);
+    ///     for &val in chunk {
+    ///         histogram[val as usize] += 1;
+    ///     }
+    /// };
+    /// bitpacker.decompress_to_sink(&compressed, &mut sink, num_bits);
+    /// assert_eq!(histogram[0], 13);
+    /// assert_eq!(histogram[9], 12);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short.
+    fn decompress_to_sink<TSink: Sink>(
+        &self,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize;
+
+    /// Same as `.decompress_to_sink(..)` for a block compressed with
+    /// `.compress_sorted(..)`: the sink receives the integrated integers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short.
+    fn decompress_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize;
+
+    /// Same as `.decompress_to_sink(..)` for a block compressed with
+    /// `.compress_strictly_sorted(..)`: the sink receives the integrated integers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressed array is too short.
+    fn decompress_strictly_sorted_to_sink<TSink: Sink>(
+        &self,
+        initial: Option<u32>,
+        compressed: &[u8],
+        sink: &mut TSink,
+        num_bits: u8,
+    ) -> usize;
+
     /// Returns the minimum number of bits used to represent the largest integer in the
     /// `decompressed` block.
     ///
@@ -333,6 +1104,192 @@ pub trait BitPacker: Sized + Clone + Copy {
     /// Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
     fn num_bits_strictly_sorted(&self, initial: Option<u32>, decompressed: &[u32]) -> u8;
 
+    /// Same as `.num_bits_sorted(..)`, but checks that `decompressed` is sorted and that
+    /// `initial` is lower or equal to `decompressed[0]`.
+    ///
+    /// The order is checked in-register while computing the deltas, which makes it cheap
+    /// enough to validate every block in debug builds.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x, CompressError};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let mut values: Vec<u32> = (0..128).map(|i| i * 3).collect();
+    /// assert_eq!(bitpacker.num_bits_sorted_checked(0, &values), Ok(2));
+    ///
+    /// values[100] = 1;
+    /// assert_eq!(
+    ///     bitpacker.num_bits_sorted_checked(0, &values),
+    ///     Err(CompressError::NotSorted { index: 100 })
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    fn num_bits_sorted_checked(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError>;
+
+    /// Same as `.num_bits_strictly_sorted(..)`, but checks that `decompressed` is
+    /// strictly increasing and that `initial`, if any, is lower than `decompressed[0]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    fn num_bits_strictly_sorted_checked(
+        &self,
+        initial: Option<u32>,
+        decompressed: &[u32],
+    ) -> Result<u8, CompressError>;
+
+    /// Returns the number of bits of the block in the plain, sorted, strictly sorted and
+    /// frame-of-reference modes, computing them all in a single pass over the block.
+    ///
+    /// The sorted modes use `initial` as the integer preceding the block. The strictly
+    /// sorted width is the one of `.num_bits_strictly_sorted(Some(initial), ..)`.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..128).map(|i| 1_000 + i).collect();
+    /// let widths = bitpacker.num_bits_all_modes(999, &values);
+    /// assert_eq!(widths.plain, 11);
+    /// assert_eq!(widths.sorted, 1);
+    /// assert_eq!(widths.strictly_sorted, 0);
+    /// assert_eq!(widths.frame_of_reference, 7);
+    /// assert_eq!(widths.min, 1_000);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    fn num_bits_all_modes(&self, initial: u32, decompressed: &[u32]) -> BlockWidths;
+
+    /// Returns the exact amount of bytes needed to compress `decompressed` block after
+    /// block, along with the histogram of the number of bits of the blocks.
+    ///
+    /// `decompressed` can have any length. Sorted blocks are chained: each block uses the
+    /// last integer of the previous one as its initial value. The trailing
+    /// `len % BLOCK_LEN` integers, if any, count as one more block, padded with
+    /// integers that do not widen it: zeros, or the last integer repeated (sorted) or
+    /// incremented (strictly sorted).
+    ///
+    /// The number of bits of each block is computed with the `.num_bits*(..)` kernels, in
+    /// a single pass and without compressing anything.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x, DeltaMode};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..300).map(|i| i % 16).collect();
+    /// let estimate = bitpacker.estimate_compressed_len(&values, DeltaMode::NoDelta);
+    /// assert_eq!(estimate.compressed_len, 3 * BitPacker4x::compressed_block_size(4));
+    /// assert_eq!(estimate.num_bits_histogram[4], 3);
+    /// ```
+    fn estimate_compressed_len(
+        &self,
+        decompressed: &[u32],
+        mode: DeltaMode,
+    ) -> CompressedLenEstimate {
+        estimate::estimate_compressed_len(self, decompressed, mode)
+    }
+
+    /// Returns an iterator over the first `len` integers of a stream of compressed
+    /// blocks, the block `i` having `num_bits[i]` bits.
+    ///
+    /// The blocks are decoded lazily, one at a time. Sorted blocks are chained: each block
+    /// uses the last integer of the previous one as its initial value. `nth(..)` and
+    /// `skip(..)` jump over whole blocks, without decoding them when they are not sorted.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x, DeltaMode};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let values: Vec<u32> = (0..256).map(|i| i * 3).collect();
+    /// let mut compressed = vec![0u8; 4 * values.len()];
+    /// let mut num_bits = Vec::new();
+    /// let mut compressed_len = 0;
+    /// let mut initial = 0;
+    /// for block in values.chunks(BitPacker4x::BLOCK_LEN) {
+    ///     let (block_num_bits, block_len) =
+    ///         bitpacker.compress_sorted_auto(initial, block, &mut compressed[compressed_len..]);
+    ///     num_bits.push(block_num_bits);
+    ///     compressed_len += block_len;
+    ///     initial = block[block.len() - 1];
+    /// }
+    ///
+    /// let mode = DeltaMode::Sorted { initial: 0 };
+    /// let mut iter = bitpacker.iter(mode, &compressed, &num_bits, 200);
+    /// assert_eq!(iter.len(), 200);
+    /// assert_eq!(iter.nth(150), Some(450));
+    /// assert_eq!(iter.sum::<u32>(), (151..200).map(|i| i * 3).sum());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `num_bits`'s len is not `len.div_ceil(BLOCK_LEN)`.
+    /// - Panics if `compressed` is smaller than the sum of the block sizes.
+    fn iter<'a>(
+        &self,
+        mode: DeltaMode,
+        compressed: &'a [u8],
+        num_bits: &'a [u8],
+        len: usize,
+    ) -> BlockIter<'a, Self> {
+        BlockIter::new(*self, mode, compressed, num_bits, len)
+    }
+
+    /// Decompresses a block compressed with `num_bits == 1`, writing the positions of
+    /// its set bits, in increasing order, to `positions`.
+    ///
+    /// The lanes of the block are transposed back into a plain bitmap, which is walked
+    /// with `trailing_zeros`, rather than decoding one `u32` per bit.
+    ///
+    /// Returns the number of positions written.
+    ///
+    /// ```
+    /// use bitpacking::{BitPacker, BitPacker4x};
+    ///
+    /// let bitpacker = BitPacker4x::new();
+    /// let mut compressed = [0u8; 16];
+    /// bitpacker.compress_positions(&[3, 64, 127], &mut compressed);
+    ///
+    /// let mut flags = [0u32; 128];
+    /// bitpacker.decompress(&compressed, &mut flags, 1);
+    /// assert_eq!((flags[3], flags[4], flags[64], flags[127]), (1, 0, 1, 1));
+    ///
+    /// let mut positions = [0u32; 128];
+    /// let num_positions = bitpacker.decompress_positions(&compressed, &mut positions);
+    /// assert_eq!(&positions[..num_positions], &[3, 64, 127]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `compressed` is shorter than `compressed_block_size(1)`.
+    /// - Panics if `positions` is shorter than the number of set bits.
+    fn decompress_positions(&self, compressed: &[u8], positions: &mut [u32]) -> usize {
+        bitmap::decompress_positions::<Self>(compressed, positions)
+    }
+
+    /// Compresses the block whose integers are `1` at `positions` and `0` elsewhere, with
+    /// `num_bits == 1`.
+    ///
+    /// `positions` can be in any order, and may hold duplicates.
+    ///
+    /// Returns the amount of bytes written, `compressed_block_size(1)`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if a position is not smaller than `BLOCK_LEN`.
+    /// - Panics if `compressed` is shorter than `compressed_block_size(1)`.
+    fn compress_positions(&self, positions: &[u32], compressed: &mut [u8]) -> usize {
+        bitmap::compress_positions::<Self>(positions, compressed)
+    }
+
     /// Returns the size of a compressed block.
     #[must_use]
     fn compressed_block_size(num_bits: u8) -> usize {
@@ -340,6 +1297,84 @@ pub trait BitPacker: Sized + Clone + Copy {
     }
 }
 
+/// Largest `BLOCK_LEN` among the `BitPacker` implementations.
+///
+/// Useful to allocate scratch blocks on the stack in code that is generic over the
+/// bitpacker.
+const MAX_BLOCK_LEN: usize = 1024;
+
+/// Returns the error for the first integer of `decompressed` that is lower than (or
+/// equal to, if `strict`) the previous one. `initial` is the integer before the block.
+///
+/// Only called once a violation has been detected.
+fn unsorted_error(initial: Option<u32>, strict: bool, decompressed: &[u32]) -> CompressError {
+    let index = first_unsorted(initial, strict, decompressed).expect("The block is sorted");
+    CompressError::NotSorted { index }
+}
+
+/// Returns the position of the first integer of `decompressed` that is lower than (or
+/// equal to, if `strict`) the previous one, if any.
+fn first_unsorted(initial: Option<u32>, strict: bool, decompressed: &[u32]) -> Option<usize> {
+    let mut previous = initial;
+    for (index, &value) in decompressed.iter().enumerate() {
+        if let Some(previous) = previous {
+            if value < previous || (strict && value == previous) {
+                return Some(index);
+            }
+        }
+        previous = Some(value);
+    }
+    None
+}
+
+/// Checks the arguments of the checked decompression methods, so that decompressing
+/// can neither panic nor read out of bounds.
+fn check_decompress_args<TBitPacker: BitPacker>(
+    compressed: &[u8],
+    decompressed: &[u32],
+    num_bits: u8,
+) -> Result<(), DecompressError> {
+    if num_bits > 32 {
+        return Err(DecompressError::InvalidNumBits { num_bits });
+    }
+    let expected_len = TBitPacker::compressed_block_size(num_bits);
+    if compressed.len() < expected_len {
+        return Err(DecompressError::Truncated {
+            len: compressed.len(),
+            expected_len,
+        });
+    }
+    if decompressed.len() < TBitPacker::BLOCK_LEN {
+        return Err(DecompressError::OutputTooShort {
+            len: decompressed.len(),
+            expected_len: TBitPacker::BLOCK_LEN,
+        });
+    }
+    Ok(())
+}
+
+/// Views an initialized buffer as a possibly uninitialized one, to hand it over to the
+/// kernels writing through `MaybeUninit` buffers.
+///
+/// # Safety
+///
+/// Only initialized values may be written through the returned slice.
+#[inline]
+unsafe fn as_uninit<T>(buf: &mut [T]) -> &mut [MaybeUninit<T>] {
+    &mut *(buf as *mut [T] as *mut [MaybeUninit<T>])
+}
+
+/// Returns the `len` first items of `buf`.
+///
+/// # Safety
+///
+/// The `len` first items of `buf` must have been initialized.
+#[inline]
+unsafe fn assume_init_prefix<T>(buf: &mut [MaybeUninit<T>], len: usize) -> &mut [T] {
+    assert!(len <= buf.len());
+    slice::from_raw_parts_mut(buf.as_mut_ptr().cast::<T>(), len)
+}
+
 /// Returns the most significant bit.&self,
 fn most_significant_bit(v: u32) -> u8 {
     if v == 0 {
@@ -349,9 +1384,13 @@ fn most_significant_bit(v: u32) -> u8 {
     }
 }
 
+// In debug test builds, these are only compiled to be tested: the public types come
+// from the `_simple` variants.
 #[cfg(all(feature = "bitpacker1x", any(test, not(debug_assertions))))]
+#[cfg_attr(debug_assertions, allow(dead_code))]
 mod bitpacker1x;
 #[cfg(all(feature = "bitpacker4x", any(test, not(debug_assertions))))]
+#[cfg_attr(debug_assertions, allow(dead_code))]
 mod bitpacker4x;
 
 #[cfg(all(feature = "bitpacker1x", debug_assertions))]
@@ -362,6 +1401,9 @@ mod bitpacker4x_simple;
 #[cfg(feature = "bitpacker8x")]
 mod bitpacker8x;
 
+#[cfg(feature = "bitpackerfastlanes")]
+mod bitpackerfastlanes;
+
 #[cfg(all(feature = "bitpacker1x", not(debug_assertions)))]
 pub use bitpacker1x::BitPacker1x;
 #[cfg(all(feature = "bitpacker1x", debug_assertions))]
@@ -375,10 +1417,51 @@ pub use bitpacker4x_simple::BitPacker4x;
 #[cfg(feature = "bitpacker8x")]
 pub use bitpacker8x::BitPacker8x;
 
+#[cfg(feature = "bitpackerfastlanes")]
+pub use bitpackerfastlanes::BitPackerFastLanes;
+
+mod adaptive;
+mod bitmap;
+mod checksum;
+mod delta_mode;
+#[cfg(feature = "bitpacker1x")]
+mod elias_fano;
+mod error;
+mod estimate;
+mod gcd;
+mod iter;
+mod linear;
+mod lucene;
+#[cfg(feature = "bitpacker1x")]
+mod parquet;
+mod simple;
+mod stream;
+mod streamvbyte;
+mod transcode;
+
+pub use adaptive::{AdaptiveCodec, BlockMode, BlockWidths};
+pub use checksum::{ChecksummedCodec, Crc32c};
+pub use delta_mode::DeltaMode;
+#[cfg(feature = "bitpacker1x")]
+pub use elias_fano::{EliasFano, EliasFanoInt, EliasFanoIter};
+pub use error::{CompressError, DecompressError};
+pub use estimate::CompressedLenEstimate;
+pub use gcd::GcdCodec;
+pub use iter::BlockIter;
+pub use linear::LinearCodec;
+pub use lucene::{LuceneForDeltaUtil, LuceneForUtil, LucenePForUtil};
+#[cfg(feature = "bitpacker1x")]
+pub use parquet::RleBitPackingHybrid;
+pub use simple::{Simple16, Simple8b};
+pub use stream::{BitPackReader, BitPackWriter};
+pub use streamvbyte::StreamVByte;
+pub use transcode::Transcoder;
+
 #[cfg(test)]
 mod tests_unit {
     use super::*;
 
+    #[cfg(feature = "bitpacker4x")]
     #[test]
     #[should_panic(expected = "`decompressed`'s len is not `BLOCK_LEN=128`")]
     fn test_num_bits_block_too_long() {
@@ -387,6 +1470,7 @@ mod tests_unit {
         bit_packer.num_bits(&v[..]);
     }
 
+    #[cfg(feature = "bitpacker4x")]
     #[test]
     #[should_panic(expected = "`decompressed`'s len is not `BLOCK_LEN=128`")]
     fn test_num_bits_block_too_short() {
@@ -394,21 +1478,335 @@ mod tests_unit {
         let v = vec![0u32; BitPacker4x::BLOCK_LEN - 1];
         bit_packer.num_bits(&v[..]);
     }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    #[should_panic(expected = "Num bits must be <= 8 for `u8` integers. Was 9.")]
+    fn test_decompress_into_u8_too_wide() {
+        let bit_packer = BitPacker4x::new();
+        let compressed = vec![0u8; BitPacker4x::compressed_block_size(9)];
+        let mut decompressed = vec![0u8; BitPacker4x::BLOCK_LEN];
+        bit_packer.decompress_into_u8(&compressed, &mut decompressed, 9);
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    #[should_panic(expected = "Num bits must be <= 16 for `u16` integers. Was 17.")]
+    fn test_compress_u16_too_wide() {
+        let bit_packer = BitPacker4x::new();
+        let decompressed = vec![0u16; BitPacker4x::BLOCK_LEN];
+        let mut compressed = vec![0u8; BitPacker4x::compressed_block_size(17)];
+        bit_packer.compress_u16(&decompressed, &mut compressed, 17);
+    }
+
+    /// Checks the compressed bytes against a layout computed byte by byte, which does
+    /// not depend on the endianness of the target. `BitPackerFastLanes` reorders its
+    /// rows: its bytes are checked against FastLanes' own packing in its module.
+    #[cfg(any(
+        feature = "bitpacker1x",
+        feature = "bitpacker4x",
+        feature = "bitpacker8x"
+    ))]
+    fn test_util_little_endian<TBitPacker: BitPacker>() {
+        let num_lanes = TBitPacker::BLOCK_LEN / 32;
+        let values: Vec<u32> = (0..TBitPacker::BLOCK_LEN as u32)
+            .map(|i| i.wrapping_mul(2_654_435_761))
+            .collect();
+        let bit_packer = TBitPacker::new();
+        // Odd widths have values straddling two words.
+        for num_bits in [5, 8, 13, 16, 32] {
+            let masked: Vec<u32> = values
+                .iter()
+                .map(|&val| val & (u64::from(u32::MAX) >> (32 - num_bits)) as u32)
+                .collect();
+            let mut compressed = vec![0u8; TBitPacker::compressed_block_size(num_bits)];
+            bit_packer.compress(&masked, &mut compressed, num_bits);
+            // Lane `i % num_lanes` holds the value `i` at the bits `(i / num_lanes) *
+            // num_bits..`, and the words of the lanes are interleaved.
+            let mut expected = vec![0u8; compressed.len()];
+            for (i, &val) in masked.iter().enumerate() {
+                let lane = i % num_lanes;
+                for bit in 0..num_bits as usize {
+                    if (val >> bit) & 1 == 1 {
+                        let lane_bit = (i / num_lanes) * num_bits as usize + bit;
+                        let byte = ((lane_bit / 32) * num_lanes + lane) * 4 + (lane_bit % 32) / 8;
+                        expected[byte] |= 1 << (lane_bit % 8);
+                    }
+                }
+            }
+            assert_eq!(compressed, expected, "num_bits {}", num_bits);
+            let mut decompressed = vec![0u32; TBitPacker::BLOCK_LEN];
+            bit_packer.decompress(&compressed, &mut decompressed, num_bits);
+            assert_eq!(decompressed, masked);
+        }
+    }
+
+    #[cfg(any(
+        feature = "bitpacker1x",
+        feature = "bitpacker4x",
+        feature = "bitpacker8x"
+    ))]
+    #[test]
+    fn test_little_endian() {
+        #[cfg(feature = "bitpacker1x")]
+        test_util_little_endian::<BitPacker1x>();
+        #[cfg(feature = "bitpacker4x")]
+        test_util_little_endian::<BitPacker4x>();
+        #[cfg(feature = "bitpacker8x")]
+        test_util_little_endian::<BitPacker8x>();
+    }
+
+    /// The compressed arrays have the exact length of a block, so that reading out of
+    /// bounds is caught under Miri.
+    fn test_util_decompress_checked<TBitPacker: BitPacker>() {
+        let bit_packer = TBitPacker::new();
+        let mut decompressed = vec![0u32; TBitPacker::BLOCK_LEN];
+        for num_bits in 0..=32 {
+            let len = TBitPacker::compressed_block_size(num_bits);
+            let compressed: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+            assert_eq!(
+                bit_packer.decompress_checked(&compressed, &mut decompressed, num_bits),
+                Ok(len)
+            );
+            if len > 0 {
+                assert_eq!(
+                    bit_packer.decompress_checked(&compressed[1..], &mut decompressed, num_bits),
+                    Err(DecompressError::Truncated {
+                        len: len - 1,
+                        expected_len: len
+                    })
+                );
+            }
+            assert_eq!(
+                bit_packer.decompress_checked(&compressed, &mut decompressed[1..], num_bits),
+                Err(DecompressError::OutputTooShort {
+                    len: TBitPacker::BLOCK_LEN - 1,
+                    expected_len: TBitPacker::BLOCK_LEN
+                })
+            );
+        }
+        assert_eq!(
+            bit_packer.decompress_checked(&[0u8; 256], &mut decompressed, 33),
+            Err(DecompressError::InvalidNumBits { num_bits: 33 })
+        );
+
+        let sorted: Vec<u32> = (0..TBitPacker::BLOCK_LEN as u32).map(|i| i * 3).collect();
+        let mut compressed = vec![0u8; TBitPacker::compressed_block_size(2)];
+        bit_packer.compress_sorted(0, &sorted, &mut compressed, 2);
+        assert_eq!(
+            bit_packer.decompress_sorted_checked(0, &compressed, &mut decompressed, 2),
+            Ok(compressed.len())
+        );
+        assert_eq!(decompressed, sorted);
+        assert_eq!(
+            bit_packer.decompress_sorted_checked(u32::MAX - 10, &compressed, &mut decompressed, 2),
+            Err(DecompressError::Overflow { index: 4 })
+        );
+        bit_packer.compress_strictly_sorted(None, &sorted, &mut compressed, 2);
+        assert_eq!(
+            bit_packer.decompress_strictly_sorted_checked(None, &compressed, &mut decompressed, 2),
+            Ok(compressed.len())
+        );
+        assert_eq!(
+            bit_packer.decompress_strictly_sorted_checked(
+                Some(u32::MAX),
+                &compressed,
+                &mut decompressed,
+                2
+            ),
+            Err(DecompressError::Overflow { index: 0 })
+        );
+    }
+
+    /// Under Miri, reading the uninitialized buffers is reported as undefined behavior.
+    fn test_util_uninit<TBitPacker: BitPacker>() {
+        let bit_packer = TBitPacker::new();
+        let mut compressed = Vec::new();
+        let mut decompressed = Vec::new();
+        let all_num_bits = [0, 1, 5, 8, 13, 16, 25, 31, 32];
+        for num_bits in all_num_bits {
+            let values: Vec<u32> = (0..TBitPacker::BLOCK_LEN as u32)
+                .map(|i| {
+                    i.wrapping_mul(2_654_435_761) & (u64::from(u32::MAX) >> (32 - num_bits)) as u32
+                })
+                .collect();
+            let mut expected = vec![0u8; TBitPacker::compressed_block_size(num_bits)];
+            bit_packer.compress(&values, &mut expected, num_bits);
+
+            let mut uninit = vec![MaybeUninit::uninit(); 4 * TBitPacker::BLOCK_LEN];
+            let block = bit_packer.compress_uninit(&values, &mut uninit, num_bits);
+            assert_eq!(block, &expected[..]);
+            let mut uninit = vec![MaybeUninit::uninit(); TBitPacker::BLOCK_LEN];
+            assert_eq!(
+                bit_packer.decompress_uninit(&expected, &mut uninit, num_bits),
+                &values[..]
+            );
+
+            assert_eq!(
+                bit_packer.compress_append(&values, num_bits, &mut compressed),
+                expected.len()
+            );
+            assert_eq!(
+                bit_packer.decompress_append(&expected, num_bits, &mut decompressed),
+                expected.len()
+            );
+            assert_eq!(
+                &decompressed[decompressed.len() - TBitPacker::BLOCK_LEN..],
+                &values[..]
+            );
+        }
+        assert_eq!(
+            compressed.len(),
+            all_num_bits
+                .map(TBitPacker::compressed_block_size)
+                .iter()
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_uninit() {
+        #[cfg(feature = "bitpacker1x")]
+        test_util_uninit::<BitPacker1x>();
+        #[cfg(feature = "bitpacker4x")]
+        test_util_uninit::<BitPacker4x>();
+        #[cfg(feature = "bitpacker8x")]
+        test_util_uninit::<BitPacker8x>();
+        #[cfg(feature = "bitpackerfastlanes")]
+        test_util_uninit::<BitPackerFastLanes>();
+    }
+
+    #[cfg(feature = "bytes")]
+    fn test_util_bytes<TBitPacker: BitPacker>() {
+        use bytes::{Buf, BytesMut};
+
+        let bit_packer = TBitPacker::new();
+        let values: Vec<u32> = (0..3 * TBitPacker::BLOCK_LEN as u32)
+            .map(|i| i.wrapping_mul(2_654_435_761) >> 19)
+            .collect();
+        let mut buf = BytesMut::new();
+        let mut vec = Vec::new();
+        for block in values.chunks(TBitPacker::BLOCK_LEN) {
+            let compressed = bit_packer.encode_to_vec(block, 13);
+            assert_eq!(compressed.len(), TBitPacker::compressed_block_size(13));
+            bit_packer.encode_to_buf(block, 13, &mut buf);
+            bit_packer.encode_to_buf(block, 13, &mut vec);
+            assert_eq!(&buf[buf.len() - compressed.len()..], &compressed[..]);
+        }
+        assert_eq!(&buf[..], &vec[..]);
+
+        // The compressed blocks straddle the chunks of the chained buffers.
+        let (head, tail) = vec.split_at(TBitPacker::compressed_block_size(13) + 7);
+        let mut chain = head.chain(tail);
+        let mut decompressed = vec![0u32; values.len()];
+        for block in decompressed.chunks_mut(TBitPacker::BLOCK_LEN) {
+            assert_eq!(
+                bit_packer.decode_from_buf(&mut chain, block, 13),
+                TBitPacker::compressed_block_size(13)
+            );
+        }
+        assert!(!chain.has_remaining());
+        assert_eq!(values, decompressed);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes() {
+        test_util_bytes::<BitPacker1x>();
+        test_util_bytes::<BitPacker4x>();
+        test_util_bytes::<BitPacker8x>();
+        test_util_bytes::<BitPackerFastLanes>();
+    }
+
+    #[test]
+    fn test_decompress_checked() {
+        #[cfg(feature = "bitpacker1x")]
+        test_util_decompress_checked::<BitPacker1x>();
+        #[cfg(feature = "bitpacker4x")]
+        test_util_decompress_checked::<BitPacker4x>();
+        #[cfg(feature = "bitpacker8x")]
+        test_util_decompress_checked::<BitPacker8x>();
+        #[cfg(feature = "bitpackerfastlanes")]
+        test_util_decompress_checked::<BitPackerFastLanes>();
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "bitpacker4x"))]
 mod functional_tests {
-    use crate::{BitPacker, BitPacker4x};
+    use crate::{BitPacker, BitPacker4x, CompressError};
     use proptest::prelude::*;
 
     proptest! {
+        #[test]
+        fn check_compress_checked(
+            values in prop::collection::vec(
+                prop_oneof![9 => 0u32..1 << 12, 1 => any::<u32>()],
+                BitPacker4x::BLOCK_LEN,
+            ),
+            num_bits in 0u8..=32u8,
+        ) {
+            let bit_packer = BitPacker4x::new();
+            let mut block = vec![0u8; BitPacker4x::compressed_block_size(num_bits)];
+            let result = bit_packer.compress_checked(&values, &mut block, num_bits);
+            match values.iter().position(|&val| u64::from(val) >> num_bits != 0) {
+                Some(index) => prop_assert_eq!(
+                    result,
+                    Err(CompressError::ValueTooWide { index, value: values[index], num_bits })
+                ),
+                None => {
+                    prop_assert_eq!(result, Ok(block.len()));
+                    let mut decoded_values = vec![0u32; BitPacker4x::BLOCK_LEN];
+                    bit_packer.decompress(&block, &mut decoded_values, num_bits);
+                    prop_assert_eq!(values, decoded_values);
+                }
+            }
+        }
+
+        #[test]
+        fn check_compress_masked(
+            values in prop::collection::vec(any::<u32>(), BitPacker4x::BLOCK_LEN),
+            num_bits in 0u8..=32u8,
+        ) {
+            let bit_packer = BitPacker4x::new();
+            let mut block = vec![0u8; BitPacker4x::compressed_block_size(num_bits)];
+            bit_packer.compress_masked(&values, &mut block, num_bits);
+            let mut decoded_values = vec![0u32; BitPacker4x::BLOCK_LEN];
+            bit_packer.decompress(&block, &mut decoded_values, num_bits);
+            let masked: Vec<u32> = values
+                .iter()
+                .map(|&val| (u64::from(val) & ((1u64 << num_bits) - 1)) as u32)
+                .collect();
+            prop_assert_eq!(masked, decoded_values);
+        }
+
+        #[test]
+        fn check_decompress_checked(
+            compressed in prop::collection::vec(any::<u8>(), 0..=4 * BitPacker4x::BLOCK_LEN),
+            num_bits in 0u8..=40u8,
+            initial in any::<u32>(),
+        ) {
+            let bit_packer = BitPacker4x::new();
+            let mut decompressed = vec![0u32; BitPacker4x::BLOCK_LEN];
+            let mut recompressed = vec![0u8; 4 * BitPacker4x::BLOCK_LEN];
+            // Any block that is accepted is a valid block.
+            if let Ok(len) = bit_packer.decompress_checked(&compressed, &mut decompressed, num_bits) {
+                bit_packer.compress(&decompressed, &mut recompressed, num_bits);
+                prop_assert_eq!(&compressed[..len], &recompressed[..len]);
+            }
+            if let Ok(len) = bit_packer.decompress_sorted_checked(initial, &compressed, &mut decompressed, num_bits) {
+                bit_packer.compress_sorted(initial, &decompressed, &mut recompressed, num_bits);
+                prop_assert_eq!(&compressed[..len], &recompressed[..len]);
+                prop_assert!(initial <= decompressed[0]);
+            }
+        }
+
         #[test]
         #[ignore]
         fn check_block(
             values in prop::collection::vec(u32::arbitrary(), BitPacker4x::BLOCK_LEN),
         ) {
             let bit_packer = BitPacker4x::new();
-            let bit_width = bit_packer.num_bits(&*values);
+            let bit_width = bit_packer.num_bits(&values);
 
             let mut block = vec![0u8; BitPacker4x::compressed_block_size(bit_width)];
             bit_packer.compress(&values, &mut block, bit_width);
@@ -429,7 +1827,7 @@ mod functional_tests {
             }),
         ) {
             let bit_packer = BitPacker4x::new();
-            let bit_width = bit_packer.num_bits_sorted(init_value, &*values);
+            let bit_width = bit_packer.num_bits_sorted(init_value, &values);
 
             let mut block = vec![0u8; BitPacker4x::compressed_block_size(bit_width)];
             bit_packer.compress_sorted(init_value, &values, &mut block, bit_width);
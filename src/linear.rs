@@ -0,0 +1,308 @@
+//! Linear interpolation codec.
+//!
+//! Monotonic columns with a steady cadence (row offsets, timestamps...) have large
+//! deltas, but their values sit very close to a straight line.
+//!
+//! For each block, `LinearCodec` fits a line `a + b * i` going through the
+//! first and the last value of the block, and bitpacks the zigzag-encoded
+//! residuals using the underlying `BitPacker`.
+//!
+//! The line parameters are stored in a small header preceding the bitpacked residuals:
+//!
+//! | bytes     | content                                                  |
+//! |:----------|:---------------------------------------------------------|
+//! | `[0..4)`  | intercept `a`, as a little-endian `u32`                  |
+//! | `[4..12)` | slope `b`, as a little-endian `i64` in 32.32 fixed point |
+//! | `12`      | `num_bits` of the residuals                              |
+
+use crate::{BitPacker, MAX_BLOCK_LEN};
+
+/// Size of the header preceding the bitpacked residuals.
+const HEADER_LEN: usize = 4 + 8 + 1;
+
+/// Codec bitpacking the residuals of a block against a linear interpolation of
+/// its values.
+///
+/// It works with any of the `BitPacker` flavors, and shares their block length.
+/// Values are handled with wrapping arithmetic, so any block can be encoded,
+/// however the codec only pays off on nearly-linear data.
+///
+/// ```
+/// use bitpacking::{BitPacker, BitPacker4x, LinearCodec};
+///
+/// let timestamps: Vec<u32> = (0..BitPacker4x::BLOCK_LEN as u32)
+///     .map(|i| 1_600_000_000 + i * 60 + (i % 3))
+///     .collect();
+///
+/// let codec = LinearCodec::<BitPacker4x>::new();
+/// let mut compressed = vec![0u8; LinearCodec::<BitPacker4x>::max_compressed_block_size()];
+/// let compressed_len = codec.compress(&timestamps, &mut compressed);
+/// assert_eq!(compressed_len, LinearCodec::<BitPacker4x>::compressed_block_size(2));
+///
+/// let mut decompressed = vec![0u32; BitPacker4x::BLOCK_LEN];
+/// let read_len = codec.decompress(&compressed, &mut decompressed);
+/// assert_eq!(read_len, compressed_len);
+/// assert_eq!(timestamps, decompressed);
+/// ```
+#[derive(Clone, Copy)]
+pub struct LinearCodec<TBitPacker: BitPacker> {
+    bitpacker: TBitPacker,
+}
+
+impl<TBitPacker: BitPacker> Default for LinearCodec<TBitPacker> {
+    fn default() -> Self {
+        LinearCodec::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Line {
+    intercept: u32,
+    slope: i64,
+}
+
+impl Line {
+    /// Returns the line going through the first and the last value of the block.
+    fn through_extremities(values: &[u32]) -> Line {
+        let first = values[0];
+        let last = values[values.len() - 1];
+        // The difference is taken as a signed 32-bits integer, so that decreasing
+        // blocks and blocks wrapping around `u32::MAX` get a small slope.
+        let delta = last.wrapping_sub(first) as i32 as i64;
+        let slope = (delta << 32) / (values.len() as i64 - 1);
+        Line {
+            intercept: first,
+            slope,
+        }
+    }
+
+    #[inline]
+    fn eval(&self, i: usize) -> u32 {
+        let offset = ((self.slope as i128 * i as i128) >> 32) as u32;
+        self.intercept.wrapping_add(offset)
+    }
+}
+
+#[inline]
+fn zigzag(val: i32) -> u32 {
+    ((val << 1) ^ (val >> 31)) as u32
+}
+
+#[inline]
+fn unzigzag(val: u32) -> i32 {
+    ((val >> 1) as i32) ^ -((val & 1) as i32)
+}
+
+impl<TBitPacker: BitPacker> LinearCodec<TBitPacker> {
+    /// Number of `u32` per compressed block.
+    pub const BLOCK_LEN: usize = TBitPacker::BLOCK_LEN;
+
+    /// Returns a codec relying on the best available implementation of `TBitPacker`.
+    pub fn new() -> Self {
+        LinearCodec {
+            bitpacker: TBitPacker::new(),
+        }
+    }
+
+    /// Returns the size of a compressed block, header included, given the
+    /// number of bits of its residuals.
+    #[must_use]
+    pub fn compressed_block_size(num_bits: u8) -> usize {
+        HEADER_LEN + TBitPacker::compressed_block_size(num_bits)
+    }
+
+    /// Returns the size of the largest possible compressed block.
+    ///
+    /// An output buffer of this size is always large enough for `.compress(..)`.
+    #[must_use]
+    pub fn max_compressed_block_size() -> usize {
+        Self::compressed_block_size(32)
+    }
+
+    /// Returns the number of bits that will be used for each residual of the block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    pub fn num_bits(&self, decompressed: &[u32]) -> u8 {
+        let mut residuals = [0u32; MAX_BLOCK_LEN];
+        let residuals = &mut residuals[..TBitPacker::BLOCK_LEN];
+        compute_residuals(decompressed, residuals);
+        self.bitpacker.num_bits(residuals)
+    }
+
+    /// Compress a block of `u32`.
+    ///
+    /// Returns the amount of bytes of the compressed block, header included.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    /// - Panics if `compressed` is too small to receive the compressed block.
+    pub fn compress(&self, decompressed: &[u32], compressed: &mut [u8]) -> usize {
+        let mut residuals = [0u32; MAX_BLOCK_LEN];
+        let residuals = &mut residuals[..TBitPacker::BLOCK_LEN];
+        let line = compute_residuals(decompressed, residuals);
+        let num_bits = self.bitpacker.num_bits(residuals);
+        let compressed_len = Self::compressed_block_size(num_bits);
+        assert!(
+            compressed.len() >= compressed_len,
+            "Output array too small (numbits {}). {} < {}",
+            num_bits,
+            compressed.len(),
+            compressed_len
+        );
+        compressed[0..4].copy_from_slice(&line.intercept.to_le_bytes());
+        compressed[4..12].copy_from_slice(&line.slope.to_le_bytes());
+        compressed[12] = num_bits;
+        HEADER_LEN
+            + self
+                .bitpacker
+                .compress(residuals, &mut compressed[HEADER_LEN..], num_bits)
+    }
+
+    /// Decompress a block compressed with `.compress(..)`.
+    ///
+    /// Returns the amount of bytes that were consumed, header included.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed array is too short.
+    /// - Panics if the decompressed array is too short.
+    pub fn decompress(&self, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+        assert!(
+            compressed.len() >= HEADER_LEN,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            HEADER_LEN
+        );
+        let line = Line {
+            intercept: u32::from_le_bytes(compressed[0..4].try_into().unwrap()),
+            slope: i64::from_le_bytes(compressed[4..12].try_into().unwrap()),
+        };
+        let num_bits = compressed[12];
+        let read_len = self
+            .bitpacker
+            .decompress(&compressed[HEADER_LEN..], decompressed, num_bits);
+        for (i, val) in decompressed[..TBitPacker::BLOCK_LEN].iter_mut().enumerate() {
+            *val = line.eval(i).wrapping_add(unzigzag(*val) as u32);
+        }
+        HEADER_LEN + read_len
+    }
+}
+
+/// Fits a line through the block and writes the zigzag-encoded residuals into `residuals`.
+///
+/// The intercept is shifted so that residuals are centered around the line, which
+/// saves one bit compared to a line going exactly through the first value.
+fn compute_residuals(decompressed: &[u32], residuals: &mut [u32]) -> Line {
+    assert_eq!(
+        decompressed.len(),
+        residuals.len(),
+        "`decompressed`'s len is not `BLOCK_LEN={}`",
+        residuals.len()
+    );
+    let mut line = Line::through_extremities(decompressed);
+    let mut min_residual = i32::MAX as i64;
+    let mut max_residual = i32::MIN as i64;
+    for (i, &val) in decompressed.iter().enumerate() {
+        let residual = val.wrapping_sub(line.eval(i)) as i32 as i64;
+        min_residual = min_residual.min(residual);
+        max_residual = max_residual.max(residual);
+    }
+    let center = (min_residual + max_residual) >> 1;
+    line.intercept = line.intercept.wrapping_add(center as u32);
+    for (i, (&val, residual)) in decompressed.iter().zip(residuals.iter_mut()).enumerate() {
+        *residual = zigzag(val.wrapping_sub(line.eval(i)) as i32);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unzigzag, zigzag, LinearCodec};
+    use crate::tests::generate_array;
+    use crate::BitPacker;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+
+    fn test_util_roundtrip<TBitPacker: BitPacker>(values: &[u32], expected_num_bits: Option<u8>) {
+        let codec = LinearCodec::<TBitPacker>::new();
+        let num_bits = codec.num_bits(values);
+        if let Some(expected_num_bits) = expected_num_bits {
+            assert_eq!(num_bits, expected_num_bits);
+        }
+        let mut compressed = vec![0u8; LinearCodec::<TBitPacker>::max_compressed_block_size()];
+        let compressed_len = codec.compress(values, &mut compressed);
+        assert_eq!(
+            compressed_len,
+            LinearCodec::<TBitPacker>::compressed_block_size(num_bits)
+        );
+        let mut decompressed = vec![0u32; TBitPacker::BLOCK_LEN];
+        let read_len = codec.decompress(&compressed[..compressed_len], &mut decompressed);
+        assert_eq!(read_len, compressed_len);
+        assert_eq!(values, &decompressed[..]);
+    }
+
+    fn test_suite_linear<TBitPacker: BitPacker>() {
+        let n = TBitPacker::BLOCK_LEN as u32;
+        let perfect_line: Vec<u32> = (0..n).map(|i| 1_000_000 + 17 * i).collect();
+        test_util_roundtrip::<TBitPacker>(&perfect_line, Some(0));
+        let decreasing: Vec<u32> = (0..n).map(|i| 2_000_000 - 1_000 * i).collect();
+        test_util_roundtrip::<TBitPacker>(&decreasing, Some(0));
+        let jittered: Vec<u32> = (0..n)
+            .map(|i| 3_000_000_000 + 1_000 * i + (i % 4 == 1) as u32)
+            .collect();
+        test_util_roundtrip::<TBitPacker>(&jittered, Some(2));
+        let wrapping: Vec<u32> = (0..n)
+            .map(|i| (u32::MAX - 100).wrapping_add(3 * i))
+            .collect();
+        test_util_roundtrip::<TBitPacker>(&wrapping, Some(0));
+        let constant = vec![u32::MAX; TBitPacker::BLOCK_LEN];
+        test_util_roundtrip::<TBitPacker>(&constant, Some(0));
+        for num_bits in [1, 7, 31, 32] {
+            let random = generate_array(TBitPacker::BLOCK_LEN, num_bits);
+            test_util_roundtrip::<TBitPacker>(&random, None);
+        }
+    }
+
+    #[test]
+    fn test_zigzag() {
+        for val in [0, 1, -1, 2, -2, i32::MAX, i32::MIN] {
+            assert_eq!(unzigzag(zigzag(val)), val);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(zigzag(i32::MIN), u32::MAX);
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_linear_bitpacker1x() {
+        test_suite_linear::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_linear_bitpacker4x() {
+        test_suite_linear::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_linear_bitpacker8x() {
+        test_suite_linear::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_linear_bitpackerfastlanes() {
+        test_suite_linear::<BitPackerFastLanes>();
+    }
+}
@@ -0,0 +1,857 @@
+//! Lucene postings block formats.
+//!
+//! Lucene 9.0 to 9.11 (`Lucene90PostingsFormat` and `Lucene99PostingsFormat`) encode
+//! postings in blocks of 128 integers, using three utilities:
+//!
+//! - `ForUtil` bitpacks a block with a fixed number of bits per value.
+//! - `ForDeltaUtil` delta-encodes sorted doc ids, and writes the number of bits
+//!   per value in a leading byte. Blocks of consecutive doc ids take a single byte.
+//! - `PForUtil` patches up to 7 exceptions, so that a few large term frequencies
+//!   do not inflate the number of bits of the whole block.
+//!
+//! `ForUtil` works over `u64` registers, seen as 8, 16 or 32-bits lanes depending
+//! on the number of bits per value. Like `pack_unpack_with_bits!`, its kernels are
+//! unrolled into one module per number of bits, with constant shifts and masks. The
+//! bit order differs from the `BitPacker`s' though, so they have their own macro:
+//! registers are packed by rows from the most significant bits of the lanes, and
+//! the registers left over are streamed into their least significant bits. The
+//! words are written in little-endian, as Lucene 9's `DataOutput::writeLong` does.
+
+use crate::most_significant_bit;
+
+/// Number of integers in a Lucene block.
+const BLOCK_LEN: usize = 128;
+
+/// Maximum number of exceptions of a `PForUtil` block.
+const MAX_EXCEPTIONS: usize = 7;
+
+/// Returns the width of the lanes used for the given number of bits per value.
+fn lane_bits(bits_per_value: u8) -> u32 {
+    match bits_per_value {
+        0..=8 => 8,
+        9..=16 => 16,
+        _ => 32,
+    }
+}
+
+/// A register of `ForUtil`.
+type DataType = u64;
+
+fn left_shift<const N: i32>(el: DataType) -> DataType {
+    el << N
+}
+
+fn right_shift<const N: i32>(el: DataType) -> DataType {
+    el >> N
+}
+
+fn op_or(left: DataType, right: DataType) -> DataType {
+    left | right
+}
+
+fn op_and(left: DataType, right: DataType) -> DataType {
+    left & right
+}
+
+/// Returns a mask of the `num_bits` least significant bits of each `lane_bits`-bits lane.
+const fn lane_mask(lane_bits: usize, num_bits: usize) -> DataType {
+    let lane = (1u64 << num_bits) - 1;
+    let mut mask = 0;
+    let mut shift = 0;
+    while shift < 64 {
+        mask |= lane << shift;
+        shift += lane_bits;
+    }
+    mask
+}
+
+/// Returns the greatest common divisor of `a` and `b`.
+const fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Declares the kernels packing the `2 * lane_bits` registers of a block over
+/// `num_bits` bits per value into `2 * num_bits` registers, and unpacking them.
+macro_rules! for_util_with_bits {
+    ($name:ident, $n:expr, $lane_bits:tt) => {
+        mod $name {
+            use super::{gcd, lane_mask, left_shift, op_and, op_or, right_shift, DataType};
+            use crunchy::unroll;
+
+            const NUM_BITS: usize = $n;
+            const LANE_BITS: usize = $lane_bits;
+            const NUM_LONGS: usize = 2 * LANE_BITS;
+            const NUM_PACKED: usize = 2 * NUM_BITS;
+            const MASK: DataType = lane_mask(LANE_BITS, NUM_BITS);
+
+            // Rows of `NUM_PACKED` registers are packed from the most significant
+            // bits of the lanes.
+            const NUM_ROWS: usize = LANE_BITS / NUM_BITS;
+
+            // The registers left over are streamed, most significant bits first,
+            // into the `REMAINING_BITS` least significant bits of the lanes. The
+            // stream repeats a period of `PERIOD_LONGS` registers packed into
+            // `PERIOD_PACKED` registers, which is unrolled.
+            const FIRST_REMAINING: usize = NUM_ROWS * NUM_PACKED;
+            const REMAINING_BITS: usize = LANE_BITS - NUM_ROWS * NUM_BITS;
+            const REMAINING_MASK: DataType = lane_mask(LANE_BITS, REMAINING_BITS);
+            const NUM_PERIODS: usize = if REMAINING_BITS > 0 {
+                2 * gcd(NUM_BITS, REMAINING_BITS)
+            } else {
+                1
+            };
+            const PERIOD_PACKED: usize = if REMAINING_BITS > 0 {
+                NUM_PACKED / NUM_PERIODS
+            } else {
+                0
+            };
+            const PERIOD_LONGS: usize = (NUM_LONGS - FIRST_REMAINING) / NUM_PERIODS;
+
+            pub(super) fn pack(longs: &[DataType], packed: &mut [DataType]) {
+                assert!(longs.len() >= NUM_LONGS);
+                assert!(packed.len() >= NUM_PACKED);
+                unroll! {
+                    for row in 0..8 {
+                        if row < NUM_ROWS {
+                            const shift: usize = if row < NUM_ROWS {
+                                LANE_BITS - (row + 1) * NUM_BITS
+                            } else {
+                                0
+                            };
+                            for i in 0..NUM_PACKED {
+                                let in_register =
+                                    left_shift::<{ shift as i32 }>(longs[row * NUM_PACKED + i]);
+                                packed[i] = if row == 0 {
+                                    in_register
+                                } else {
+                                    op_or(packed[i], in_register)
+                                };
+                            }
+                        }
+                    }
+                }
+                for period in 0..NUM_PERIODS {
+                    let longs = &longs[FIRST_REMAINING + period * PERIOD_LONGS..];
+                    let packed = &mut packed[period * PERIOD_PACKED..];
+                    unroll! {
+                        for i in 0..$lane_bits {
+                            if i < PERIOD_PACKED {
+                                const bits_filled: usize = i * REMAINING_BITS;
+                                const idx: usize = bits_filled / NUM_BITS;
+                                // Bits of the register `idx` left to pack.
+                                const remaining: usize = (idx + 1) * NUM_BITS - bits_filled;
+                                const fits: bool = remaining >= REMAINING_BITS;
+                                const shift: usize = if fits {
+                                    remaining - REMAINING_BITS
+                                } else {
+                                    REMAINING_BITS - remaining
+                                };
+                                let out_register = if fits {
+                                    let bits = right_shift::<{ shift as i32 }>(longs[idx]);
+                                    op_and(bits, REMAINING_MASK)
+                                } else {
+                                    // The register `idx` ends here, and the register
+                                    // `idx + 1` starts.
+                                    let head = op_and(longs[idx], lane_mask(LANE_BITS, remaining));
+                                    let tail = longs[idx + 1];
+                                    let tail = right_shift::<{ (NUM_BITS - shift) as i32 }>(tail);
+                                    op_or(
+                                        left_shift::<{ shift as i32 }>(head),
+                                        op_and(tail, lane_mask(LANE_BITS, shift)),
+                                    )
+                                };
+                                packed[i] = op_or(packed[i], out_register);
+                            }
+                        }
+                    }
+                }
+            }
+
+            pub(super) fn unpack(packed: &[DataType], longs: &mut [DataType]) {
+                assert!(packed.len() >= NUM_PACKED);
+                assert!(longs.len() >= NUM_LONGS);
+                unroll! {
+                    for row in 0..8 {
+                        if row < NUM_ROWS {
+                            const shift: usize = if row < NUM_ROWS {
+                                LANE_BITS - (row + 1) * NUM_BITS
+                            } else {
+                                0
+                            };
+                            for i in 0..NUM_PACKED {
+                                longs[row * NUM_PACKED + i] =
+                                    op_and(right_shift::<{ shift as i32 }>(packed[i]), MASK);
+                            }
+                        }
+                    }
+                }
+                longs[FIRST_REMAINING..NUM_LONGS].fill(0);
+                for period in 0..NUM_PERIODS {
+                    let packed = &packed[period * PERIOD_PACKED..];
+                    let longs = &mut longs[FIRST_REMAINING + period * PERIOD_LONGS..];
+                    unroll! {
+                        for i in 0..$lane_bits {
+                            if i < PERIOD_PACKED {
+                                const bits_filled: usize = i * REMAINING_BITS;
+                                const idx: usize = bits_filled / NUM_BITS;
+                                const remaining: usize = (idx + 1) * NUM_BITS - bits_filled;
+                                const fits: bool = remaining >= REMAINING_BITS;
+                                const shift: usize = if fits {
+                                    remaining - REMAINING_BITS
+                                } else {
+                                    REMAINING_BITS - remaining
+                                };
+                                let in_register = packed[i];
+                                if fits {
+                                    let bits = op_and(in_register, REMAINING_MASK);
+                                    let bits = left_shift::<{ shift as i32 }>(bits);
+                                    longs[idx] = op_or(longs[idx], bits);
+                                } else {
+                                    let head = op_and(
+                                        right_shift::<{ shift as i32 }>(in_register),
+                                        lane_mask(LANE_BITS, remaining),
+                                    );
+                                    longs[idx] = op_or(longs[idx], head);
+                                    let tail = op_and(in_register, lane_mask(LANE_BITS, shift));
+                                    let tail = left_shift::<{ (NUM_BITS - shift) as i32 }>(tail);
+                                    longs[idx + 1] = op_or(longs[idx + 1], tail);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+}
+
+for_util_with_bits!(for_util_with_bits_1, 1, 8);
+for_util_with_bits!(for_util_with_bits_2, 2, 8);
+for_util_with_bits!(for_util_with_bits_3, 3, 8);
+for_util_with_bits!(for_util_with_bits_4, 4, 8);
+for_util_with_bits!(for_util_with_bits_5, 5, 8);
+for_util_with_bits!(for_util_with_bits_6, 6, 8);
+for_util_with_bits!(for_util_with_bits_7, 7, 8);
+for_util_with_bits!(for_util_with_bits_8, 8, 8);
+for_util_with_bits!(for_util_with_bits_9, 9, 16);
+for_util_with_bits!(for_util_with_bits_10, 10, 16);
+for_util_with_bits!(for_util_with_bits_11, 11, 16);
+for_util_with_bits!(for_util_with_bits_12, 12, 16);
+for_util_with_bits!(for_util_with_bits_13, 13, 16);
+for_util_with_bits!(for_util_with_bits_14, 14, 16);
+for_util_with_bits!(for_util_with_bits_15, 15, 16);
+for_util_with_bits!(for_util_with_bits_16, 16, 16);
+for_util_with_bits!(for_util_with_bits_17, 17, 32);
+for_util_with_bits!(for_util_with_bits_18, 18, 32);
+for_util_with_bits!(for_util_with_bits_19, 19, 32);
+for_util_with_bits!(for_util_with_bits_20, 20, 32);
+for_util_with_bits!(for_util_with_bits_21, 21, 32);
+for_util_with_bits!(for_util_with_bits_22, 22, 32);
+for_util_with_bits!(for_util_with_bits_23, 23, 32);
+for_util_with_bits!(for_util_with_bits_24, 24, 32);
+for_util_with_bits!(for_util_with_bits_25, 25, 32);
+for_util_with_bits!(for_util_with_bits_26, 26, 32);
+for_util_with_bits!(for_util_with_bits_27, 27, 32);
+for_util_with_bits!(for_util_with_bits_28, 28, 32);
+for_util_with_bits!(for_util_with_bits_29, 29, 32);
+for_util_with_bits!(for_util_with_bits_30, 30, 32);
+for_util_with_bits!(for_util_with_bits_31, 31, 32);
+for_util_with_bits!(for_util_with_bits_32, 32, 32);
+
+/// Lucene's `PackedInts.bitsRequired`: at least one bit, even for 0.
+fn bits_required(val: u32) -> u8 {
+    most_significant_bit(val).max(1)
+}
+
+fn write_vint(mut val: u32, output: &mut [u8]) -> usize {
+    let mut len = 0;
+    while val >= 0x80 {
+        output[len] = (val as u8 & 0x7f) | 0x80;
+        val >>= 7;
+        len += 1;
+    }
+    output[len] = val as u8;
+    len + 1
+}
+
+fn read_vint(input: &[u8]) -> (u32, usize) {
+    let mut val = 0u32;
+    for (i, &byte) in input.iter().take(5).enumerate() {
+        val |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return (val, i + 1);
+        }
+    }
+    panic!("Invalid vint");
+}
+
+/// Lucene 9 `ForUtil` block format.
+///
+/// ```
+/// use bitpacking::LuceneForUtil;
+///
+/// let values: Vec<u32> = (0..128).map(|i| i % 7).collect();
+/// let mut compressed = [0u8; LuceneForUtil::MAX_COMPRESSED_BLOCK_SIZE];
+/// let compressed_len = LuceneForUtil.encode(&values, 3, &mut compressed);
+/// assert_eq!(compressed_len, LuceneForUtil::compressed_block_size(3));
+///
+/// let mut decompressed = [0u32; LuceneForUtil::BLOCK_LEN];
+/// LuceneForUtil.decode(3, &compressed, &mut decompressed);
+/// assert_eq!(&values[..], &decompressed[..]);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct LuceneForUtil;
+
+impl LuceneForUtil {
+    /// Number of integers per block.
+    pub const BLOCK_LEN: usize = BLOCK_LEN;
+
+    /// Size of a block of 32-bits integers.
+    pub const MAX_COMPRESSED_BLOCK_SIZE: usize = BLOCK_LEN * 4;
+
+    /// Returns the size of a block, given its number of bits per value.
+    #[must_use]
+    pub fn compressed_block_size(bits_per_value: u8) -> usize {
+        BLOCK_LEN * bits_per_value as usize / 8
+    }
+
+    /// Encodes a block of 128 integers over `bits_per_value` bits.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    /// - Panics if `bits_per_value` is not within `1..=32`.
+    /// - Panics if `compressed` is too small to receive the block.
+    pub fn encode(&self, decompressed: &[u32], bits_per_value: u8, compressed: &mut [u8]) -> usize {
+        assert_eq!(
+            decompressed.len(),
+            BLOCK_LEN,
+            "`decompressed`'s len is not `BLOCK_LEN={}`",
+            BLOCK_LEN
+        );
+        assert!(
+            (1..=32).contains(&bits_per_value),
+            "Num bits must be within 1..=32. Was {}.",
+            bits_per_value
+        );
+        let num_bytes = Self::compressed_block_size(bits_per_value);
+        assert!(
+            compressed.len() >= num_bytes,
+            "Output array too small (numbits {}). {} < {}",
+            bits_per_value,
+            compressed.len(),
+            num_bytes
+        );
+        let lane_bits = lane_bits(bits_per_value);
+        let num_longs = 2 * lane_bits as usize;
+        let num_lanes = 64 / lane_bits as usize;
+
+        // Lane `k` of the `i`-th register, starting from the most significant lane,
+        // holds the value `k * num_longs + i`.
+        let mut longs = [0u64; BLOCK_LEN / 2];
+        for (i, long) in longs[..num_longs].iter_mut().enumerate() {
+            *long = (0..num_lanes).fold(0u64, |acc, k| {
+                (acc << lane_bits) | decompressed[k * num_longs + i] as u64
+            });
+        }
+
+        let num_packed = 2 * bits_per_value as usize;
+        let mut packed = [0u64; BLOCK_LEN / 2];
+        match bits_per_value {
+            1 => for_util_with_bits_1::pack(&longs, &mut packed),
+            2 => for_util_with_bits_2::pack(&longs, &mut packed),
+            3 => for_util_with_bits_3::pack(&longs, &mut packed),
+            4 => for_util_with_bits_4::pack(&longs, &mut packed),
+            5 => for_util_with_bits_5::pack(&longs, &mut packed),
+            6 => for_util_with_bits_6::pack(&longs, &mut packed),
+            7 => for_util_with_bits_7::pack(&longs, &mut packed),
+            8 => for_util_with_bits_8::pack(&longs, &mut packed),
+            9 => for_util_with_bits_9::pack(&longs, &mut packed),
+            10 => for_util_with_bits_10::pack(&longs, &mut packed),
+            11 => for_util_with_bits_11::pack(&longs, &mut packed),
+            12 => for_util_with_bits_12::pack(&longs, &mut packed),
+            13 => for_util_with_bits_13::pack(&longs, &mut packed),
+            14 => for_util_with_bits_14::pack(&longs, &mut packed),
+            15 => for_util_with_bits_15::pack(&longs, &mut packed),
+            16 => for_util_with_bits_16::pack(&longs, &mut packed),
+            17 => for_util_with_bits_17::pack(&longs, &mut packed),
+            18 => for_util_with_bits_18::pack(&longs, &mut packed),
+            19 => for_util_with_bits_19::pack(&longs, &mut packed),
+            20 => for_util_with_bits_20::pack(&longs, &mut packed),
+            21 => for_util_with_bits_21::pack(&longs, &mut packed),
+            22 => for_util_with_bits_22::pack(&longs, &mut packed),
+            23 => for_util_with_bits_23::pack(&longs, &mut packed),
+            24 => for_util_with_bits_24::pack(&longs, &mut packed),
+            25 => for_util_with_bits_25::pack(&longs, &mut packed),
+            26 => for_util_with_bits_26::pack(&longs, &mut packed),
+            27 => for_util_with_bits_27::pack(&longs, &mut packed),
+            28 => for_util_with_bits_28::pack(&longs, &mut packed),
+            29 => for_util_with_bits_29::pack(&longs, &mut packed),
+            30 => for_util_with_bits_30::pack(&longs, &mut packed),
+            31 => for_util_with_bits_31::pack(&longs, &mut packed),
+            32 => for_util_with_bits_32::pack(&longs, &mut packed),
+            _ => unreachable!(),
+        }
+
+        for (dest, long) in compressed[..num_bytes]
+            .chunks_exact_mut(8)
+            .zip(&packed[..num_packed])
+        {
+            dest.copy_from_slice(&long.to_le_bytes());
+        }
+        num_bytes
+    }
+
+    /// Decodes a block of 128 integers packed over `bits_per_value` bits.
+    ///
+    /// Returns the amount of bytes consumed.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `bits_per_value` is not within `1..=32`.
+    /// - Panics if the compressed array is too short.
+    /// - Panics if the decompressed array is too short.
+    pub fn decode(&self, bits_per_value: u8, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+        assert!(
+            (1..=32).contains(&bits_per_value),
+            "Num bits must be within 1..=32. Was {}.",
+            bits_per_value
+        );
+        let num_bytes = Self::compressed_block_size(bits_per_value);
+        assert!(
+            compressed.len() >= num_bytes,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            num_bytes
+        );
+        assert!(
+            decompressed.len() >= BLOCK_LEN,
+            "The output array is not large enough : ({} >= {})",
+            decompressed.len(),
+            BLOCK_LEN
+        );
+        let lane_bits = lane_bits(bits_per_value);
+        let num_longs = 2 * lane_bits as usize;
+        let num_lanes = 64 / lane_bits as usize;
+
+        let num_packed = 2 * bits_per_value as usize;
+        let mut packed = [0u64; BLOCK_LEN / 2];
+        for (long, src) in packed[..num_packed]
+            .iter_mut()
+            .zip(compressed[..num_bytes].chunks_exact(8))
+        {
+            *long = u64::from_le_bytes(src.try_into().unwrap());
+        }
+
+        let mut longs = [0u64; BLOCK_LEN / 2];
+        match bits_per_value {
+            1 => for_util_with_bits_1::unpack(&packed, &mut longs),
+            2 => for_util_with_bits_2::unpack(&packed, &mut longs),
+            3 => for_util_with_bits_3::unpack(&packed, &mut longs),
+            4 => for_util_with_bits_4::unpack(&packed, &mut longs),
+            5 => for_util_with_bits_5::unpack(&packed, &mut longs),
+            6 => for_util_with_bits_6::unpack(&packed, &mut longs),
+            7 => for_util_with_bits_7::unpack(&packed, &mut longs),
+            8 => for_util_with_bits_8::unpack(&packed, &mut longs),
+            9 => for_util_with_bits_9::unpack(&packed, &mut longs),
+            10 => for_util_with_bits_10::unpack(&packed, &mut longs),
+            11 => for_util_with_bits_11::unpack(&packed, &mut longs),
+            12 => for_util_with_bits_12::unpack(&packed, &mut longs),
+            13 => for_util_with_bits_13::unpack(&packed, &mut longs),
+            14 => for_util_with_bits_14::unpack(&packed, &mut longs),
+            15 => for_util_with_bits_15::unpack(&packed, &mut longs),
+            16 => for_util_with_bits_16::unpack(&packed, &mut longs),
+            17 => for_util_with_bits_17::unpack(&packed, &mut longs),
+            18 => for_util_with_bits_18::unpack(&packed, &mut longs),
+            19 => for_util_with_bits_19::unpack(&packed, &mut longs),
+            20 => for_util_with_bits_20::unpack(&packed, &mut longs),
+            21 => for_util_with_bits_21::unpack(&packed, &mut longs),
+            22 => for_util_with_bits_22::unpack(&packed, &mut longs),
+            23 => for_util_with_bits_23::unpack(&packed, &mut longs),
+            24 => for_util_with_bits_24::unpack(&packed, &mut longs),
+            25 => for_util_with_bits_25::unpack(&packed, &mut longs),
+            26 => for_util_with_bits_26::unpack(&packed, &mut longs),
+            27 => for_util_with_bits_27::unpack(&packed, &mut longs),
+            28 => for_util_with_bits_28::unpack(&packed, &mut longs),
+            29 => for_util_with_bits_29::unpack(&packed, &mut longs),
+            30 => for_util_with_bits_30::unpack(&packed, &mut longs),
+            31 => for_util_with_bits_31::unpack(&packed, &mut longs),
+            32 => for_util_with_bits_32::unpack(&packed, &mut longs),
+            _ => unreachable!(),
+        }
+
+        let lane_value_mask = (1u64 << lane_bits) - 1;
+        for (i, &long) in longs[..num_longs].iter().enumerate() {
+            for k in 0..num_lanes {
+                let shift = 64 - (k as u32 + 1) * lane_bits;
+                decompressed[k * num_longs + i] = ((long >> shift) & lane_value_mask) as u32;
+            }
+        }
+        num_bytes
+    }
+}
+
+/// Lucene 9 `ForDeltaUtil` block format, for strictly sorted doc ids.
+///
+/// ```
+/// use bitpacking::LuceneForDeltaUtil;
+///
+/// let doc_ids: Vec<u32> = (0..128).map(|i| 1_000 + 3 * i).collect();
+/// let mut compressed = [0u8; LuceneForDeltaUtil::MAX_COMPRESSED_BLOCK_SIZE];
+/// let compressed_len = LuceneForDeltaUtil.encode(997, &doc_ids, &mut compressed);
+/// assert_eq!(compressed_len, 1 + 2 * 16);
+///
+/// let mut decompressed = [0u32; LuceneForDeltaUtil::BLOCK_LEN];
+/// LuceneForDeltaUtil.decode(997, &compressed, &mut decompressed);
+/// assert_eq!(&doc_ids[..], &decompressed[..]);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct LuceneForDeltaUtil;
+
+impl LuceneForDeltaUtil {
+    /// Number of integers per block.
+    pub const BLOCK_LEN: usize = BLOCK_LEN;
+
+    /// Size of the largest possible block.
+    pub const MAX_COMPRESSED_BLOCK_SIZE: usize = 1 + LuceneForUtil::MAX_COMPRESSED_BLOCK_SIZE;
+
+    /// Delta-encodes a block of 128 sorted integers, `initial` being the value
+    /// preceding the block.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    /// - Panics if `compressed` is too small to receive the block.
+    pub fn encode(&self, initial: u32, decompressed: &[u32], compressed: &mut [u8]) -> usize {
+        assert_eq!(
+            decompressed.len(),
+            BLOCK_LEN,
+            "`decompressed`'s len is not `BLOCK_LEN={}`",
+            BLOCK_LEN
+        );
+        let mut deltas = [0u32; BLOCK_LEN];
+        let mut previous = initial;
+        for (delta, &val) in deltas.iter_mut().zip(decompressed) {
+            *delta = val.wrapping_sub(previous);
+            previous = val;
+        }
+        let accumulator = deltas.iter().fold(0u32, |acc, &delta| acc | delta);
+        if accumulator == 1 {
+            compressed[0] = 0;
+            return 1;
+        }
+        let bits_per_value = bits_required(accumulator);
+        compressed[0] = bits_per_value;
+        1 + LuceneForUtil.encode(&deltas, bits_per_value, &mut compressed[1..])
+    }
+
+    /// Decodes a block encoded with `.encode(..)`.
+    ///
+    /// Returns the amount of bytes consumed.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed array is too short.
+    /// - Panics if the decompressed array is too short.
+    pub fn decode(&self, initial: u32, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+        assert!(
+            decompressed.len() >= BLOCK_LEN,
+            "The output array is not large enough : ({} >= {})",
+            decompressed.len(),
+            BLOCK_LEN
+        );
+        let bits_per_value = compressed[0];
+        let decompressed = &mut decompressed[..BLOCK_LEN];
+        let num_bytes = if bits_per_value == 0 {
+            decompressed.fill(1);
+            1
+        } else {
+            1 + LuceneForUtil.decode(bits_per_value, &compressed[1..], decompressed)
+        };
+        let mut cumul = initial;
+        for val in decompressed.iter_mut() {
+            cumul = cumul.wrapping_add(*val);
+            *val = cumul;
+        }
+        num_bytes
+    }
+}
+
+/// Lucene 9 `PForUtil` block format: `ForUtil` with up to 7 patched exceptions.
+///
+/// ```
+/// use bitpacking::LucenePForUtil;
+///
+/// let mut term_freqs: Vec<u32> = (0..128).map(|i| 1 + i % 3).collect();
+/// term_freqs[17] = 1_000;
+/// let mut compressed = [0u8; LucenePForUtil::MAX_COMPRESSED_BLOCK_SIZE];
+/// let compressed_len = LucenePForUtil.encode(&term_freqs, &mut compressed);
+/// // The token, 128 values over 2 bits, and the exception.
+/// assert_eq!(compressed_len, 1 + 2 * 16 + 2);
+///
+/// let mut decompressed = [0u32; LucenePForUtil::BLOCK_LEN];
+/// LucenePForUtil.decode(&compressed, &mut decompressed);
+/// assert_eq!(&term_freqs[..], &decompressed[..]);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct LucenePForUtil;
+
+impl LucenePForUtil {
+    /// Number of integers per block.
+    pub const BLOCK_LEN: usize = BLOCK_LEN;
+
+    /// Size of the largest possible block.
+    pub const MAX_COMPRESSED_BLOCK_SIZE: usize =
+        1 + LuceneForUtil::MAX_COMPRESSED_BLOCK_SIZE + 2 * MAX_EXCEPTIONS;
+
+    /// Encodes a block of 128 integers.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `decompressed`'s len is not exactly `BLOCK_LEN`.
+    /// - Panics if the block requires 32 bits per value, even after patching: like
+    ///   Lucene's, the format only supports 31-bits integers.
+    /// - Panics if `compressed` is too small to receive the block.
+    pub fn encode(&self, decompressed: &[u32], compressed: &mut [u8]) -> usize {
+        assert_eq!(
+            decompressed.len(),
+            BLOCK_LEN,
+            "`decompressed`'s len is not `BLOCK_LEN={}`",
+            BLOCK_LEN
+        );
+        let mut sorted = [0u32; BLOCK_LEN];
+        sorted.copy_from_slice(decompressed);
+        sorted.sort_unstable();
+        let top_value = sorted[BLOCK_LEN - 1 - MAX_EXCEPTIONS];
+        let max_bits_required = bits_required(sorted[BLOCK_LEN - 1]);
+        // Exceptions are stored on a byte: they cannot save more than 8 bits.
+        let patched_bits_required =
+            bits_required(top_value).max(max_bits_required.saturating_sub(8));
+        assert!(
+            patched_bits_required <= 31,
+            "The token cannot encode more than 31 bits per value"
+        );
+        let max_unpatched_value = (1u64 << patched_bits_required) - 1;
+
+        let mut values = [0u32; BLOCK_LEN];
+        values.copy_from_slice(decompressed);
+        let mut exceptions = [0u8; 2 * MAX_EXCEPTIONS];
+        let mut num_exceptions = 0;
+        for (i, val) in values.iter_mut().enumerate() {
+            if *val as u64 > max_unpatched_value {
+                exceptions[2 * num_exceptions] = i as u8;
+                exceptions[2 * num_exceptions + 1] = (*val >> patched_bits_required) as u8;
+                *val &= max_unpatched_value as u32;
+                num_exceptions += 1;
+            }
+        }
+
+        let mut offset = 1;
+        if values.iter().all(|&val| val == values[0]) && max_bits_required <= 8 {
+            // The block is stored with 0 bits per value: exceptions are pre-shifted.
+            for exception in exceptions[..2 * num_exceptions]
+                .iter_mut()
+                .skip(1)
+                .step_by(2)
+            {
+                *exception <<= patched_bits_required;
+            }
+            compressed[0] = (num_exceptions << 5) as u8;
+            offset += write_vint(values[0], &mut compressed[offset..]);
+        } else {
+            compressed[0] = (num_exceptions << 5) as u8 | patched_bits_required;
+            offset +=
+                LuceneForUtil.encode(&values, patched_bits_required, &mut compressed[offset..]);
+        }
+        compressed[offset..offset + 2 * num_exceptions]
+            .copy_from_slice(&exceptions[..2 * num_exceptions]);
+        offset + 2 * num_exceptions
+    }
+
+    /// Decodes a block encoded with `.encode(..)`.
+    ///
+    /// Returns the amount of bytes consumed.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the compressed array is too short.
+    /// - Panics if the decompressed array is too short.
+    pub fn decode(&self, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+        assert!(
+            decompressed.len() >= BLOCK_LEN,
+            "The output array is not large enough : ({} >= {})",
+            decompressed.len(),
+            BLOCK_LEN
+        );
+        let token = compressed[0];
+        let bits_per_value = token & 0x1f;
+        let num_exceptions = (token >> 5) as usize;
+        let mut offset = 1;
+        if bits_per_value == 0 {
+            let (val, len) = read_vint(&compressed[offset..]);
+            decompressed[..BLOCK_LEN].fill(val);
+            offset += len;
+        } else {
+            offset += LuceneForUtil.decode(bits_per_value, &compressed[offset..], decompressed);
+        }
+        let exceptions = &compressed[offset..offset + 2 * num_exceptions];
+        for exception in exceptions.chunks_exact(2) {
+            decompressed[exception[0] as usize] |= (exception[1] as u32) << bits_per_value;
+        }
+        offset + 2 * num_exceptions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LuceneForDeltaUtil, LuceneForUtil, LucenePForUtil, BLOCK_LEN};
+    use crate::tests::generate_array;
+
+    /// Encoded blocks produced by `testdata/LuceneGolden.java`, a copy of Lucene 9's
+    /// encoders, one `name hex` pair per line.
+    const GOLDEN: &str = include_str!("../testdata/lucene90_golden.txt");
+
+    fn golden(name: &str) -> Vec<u8> {
+        let hex = GOLDEN
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+            .unwrap();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Input of the golden vectors: a multiplicative hash of `i + seed`, keeping its
+    /// `num_bits` most significant bits.
+    fn input(num_bits: u32, seed: u32) -> Vec<u32> {
+        (0..BLOCK_LEN as u32)
+            .map(|i| (i + seed).wrapping_mul(2_654_435_761) >> (32 - num_bits))
+            .collect()
+    }
+
+    fn check_for(values: &[u32], bits_per_value: u8, expected: &[u8]) {
+        let mut compressed = [0u8; LuceneForUtil::MAX_COMPRESSED_BLOCK_SIZE];
+        let compressed_len = LuceneForUtil.encode(values, bits_per_value, &mut compressed);
+        assert_eq!(&compressed[..compressed_len], expected);
+        let mut decompressed = [0u32; BLOCK_LEN];
+        assert_eq!(
+            LuceneForUtil.decode(bits_per_value, expected, &mut decompressed),
+            expected.len()
+        );
+        assert_eq!(&decompressed[..], values);
+    }
+
+    fn check_pfor(values: &[u32], expected: &[u8]) {
+        let mut compressed = [0u8; LucenePForUtil::MAX_COMPRESSED_BLOCK_SIZE];
+        let compressed_len = LucenePForUtil.encode(values, &mut compressed);
+        assert_eq!(&compressed[..compressed_len], expected);
+        let mut decompressed = [0u32; BLOCK_LEN];
+        assert_eq!(
+            LucenePForUtil.decode(expected, &mut decompressed),
+            expected.len()
+        );
+        assert_eq!(&decompressed[..], values);
+    }
+
+    fn check_delta(initial: u32, values: &[u32], expected: &[u8]) {
+        let mut compressed = [0u8; LuceneForDeltaUtil::MAX_COMPRESSED_BLOCK_SIZE];
+        let compressed_len = LuceneForDeltaUtil.encode(initial, values, &mut compressed);
+        assert_eq!(&compressed[..compressed_len], expected);
+        let mut decompressed = [0u32; BLOCK_LEN];
+        assert_eq!(
+            LuceneForDeltaUtil.decode(initial, expected, &mut decompressed),
+            expected.len()
+        );
+        assert_eq!(&decompressed[..], values);
+    }
+
+    #[test]
+    fn test_for_golden() {
+        for bits_per_value in [1, 5, 11, 19, 27, 32] {
+            check_for(
+                &input(bits_per_value, 0),
+                bits_per_value as u8,
+                &golden(&format!("for {bits_per_value}")),
+            );
+        }
+    }
+
+    #[test]
+    fn test_for_delta_golden() {
+        let mut doc_ids = input(6, 3);
+        let mut doc_id = 41;
+        for val in doc_ids.iter_mut() {
+            doc_id += *val + 1;
+            *val = doc_id;
+        }
+        check_delta(41, &doc_ids, &golden("delta"));
+        let consecutive: Vec<u32> = (0..BLOCK_LEN as u32).map(|i| 1_001 + i).collect();
+        check_delta(1_000, &consecutive, &golden("delta_ones"));
+    }
+
+    #[test]
+    fn test_pfor_golden() {
+        let mut values = input(3, 7);
+        values[5] = 1_000;
+        values[77] = 70_000;
+        values[100] = 300;
+        check_pfor(&values, &golden("pfor"));
+
+        let mut values = vec![1u32; BLOCK_LEN];
+        values[9] = 200;
+        values[10] = 3;
+        check_pfor(&values, &golden("pfor_const"));
+
+        check_pfor(&[5u32; BLOCK_LEN], &golden("pfor_vlong"));
+
+        let mut values = vec![3u32; BLOCK_LEN];
+        values[64] = 131;
+        check_pfor(&values, &golden("pfor_vlong_exception"));
+
+        check_pfor(&[300u32; BLOCK_LEN], &golden("pfor_constant_9_bits"));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for bits_per_value in 1u8..=32 {
+            let values = generate_array(BLOCK_LEN, bits_per_value);
+            let mut compressed = [0u8; LucenePForUtil::MAX_COMPRESSED_BLOCK_SIZE];
+            let compressed_len = LuceneForUtil.encode(&values, bits_per_value, &mut compressed);
+            assert_eq!(
+                compressed_len,
+                LuceneForUtil::compressed_block_size(bits_per_value)
+            );
+            let mut decompressed = [0u32; BLOCK_LEN];
+            LuceneForUtil.decode(bits_per_value, &compressed, &mut decompressed);
+            assert_eq!(&decompressed[..], &values[..]);
+
+            if bits_per_value == 32 {
+                continue;
+            }
+            let compressed_len = LucenePForUtil.encode(&values, &mut compressed);
+            assert_eq!(
+                LucenePForUtil.decode(&compressed[..compressed_len], &mut decompressed),
+                compressed_len
+            );
+            assert_eq!(&decompressed[..], &values[..]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The token cannot encode more than 31 bits per value")]
+    fn test_pfor_32_bits() {
+        let mut compressed = [0u8; LucenePForUtil::MAX_COMPRESSED_BLOCK_SIZE];
+        LucenePForUtil.encode(&[u32::MAX; BLOCK_LEN], &mut compressed);
+    }
+}
@@ -6,6 +6,7 @@ macro_rules! pack_unpack_with_bits {
         mod $name {
 
             use crunchy::unroll;
+            use std::mem::MaybeUninit;
             use super::BLOCK_LEN;
             use super::{Sink, Transformer};
             use super::{DataType,
@@ -15,13 +16,14 @@ macro_rules! pack_unpack_with_bits {
                 op_or,
                 op_and,
                 load_unaligned,
-                store_unaligned};
+                load_compressed,
+                store_compressed};
 
             const NUM_BITS: usize = $n;
             const NUM_BYTES_PER_BLOCK: usize = NUM_BITS * BLOCK_LEN / 8;
 
             #[$cpufeature]
-            pub(crate) unsafe fn pack<TDeltaComputer: Transformer>(input_arr: &[u32], output_arr: &mut [u8], mut delta_computer: TDeltaComputer) -> usize {
+            pub(crate) unsafe fn pack<TDeltaComputer: Transformer>(input_arr: &[u32], output_arr: &mut [MaybeUninit<u8>], mut delta_computer: TDeltaComputer) -> usize {
                 assert_eq!(input_arr.len(), BLOCK_LEN, "Input block too small {}, (expected {})", input_arr.len(), BLOCK_LEN);
                 assert!(output_arr.len() >= NUM_BYTES_PER_BLOCK, "Output array too small (numbits {}). {} <= {}", NUM_BITS, output_arr.len(), NUM_BYTES_PER_BLOCK);
 
@@ -48,7 +50,7 @@ macro_rules! pack_unpack_with_bits {
                             };
 
                         if remaining <= NUM_BITS {
-                            store_unaligned(output_ptr, out_register);
+                            store_compressed(output_ptr, out_register);
                             output_ptr = output_ptr.add(1);
                             if 0 < remaining && remaining < NUM_BITS {
                                 out_register = right_shift_32::<{remaining as i32}>(in_register);
@@ -62,7 +64,7 @@ macro_rules! pack_unpack_with_bits {
                 } else {
                     op_or(out_register, in_register)
                 };
-                store_unaligned(output_ptr, out_register);
+                store_compressed(output_ptr, out_register);
 
                 NUM_BYTES_PER_BLOCK
             }
@@ -77,7 +79,7 @@ macro_rules! pack_unpack_with_bits {
                 let mask_scalar: u32 = ((1u64 << NUM_BITS) - 1u64) as u32;
                 let mask = set1(mask_scalar as i32);
 
-                let mut in_register: DataType = load_unaligned(input_ptr);
+                let mut in_register: DataType = load_compressed(input_ptr);
 
                 let out_register = op_and(in_register, mask);
                 output.process(out_register);
@@ -100,7 +102,7 @@ macro_rules! pack_unpack_with_bits {
                         // We therefore read another one.
                         if inner_capacity <= NUM_BITS && i != 31 {
                             input_ptr = input_ptr.add(1);
-                            in_register = load_unaligned(input_ptr);
+                            in_register = load_compressed(input_ptr);
 
                             // This quadruplets is actually cutting one of
                             // our `DataType`. We need to read the next one.
@@ -130,9 +132,10 @@ macro_rules! pack_unpack_with_bits_32 {
     ($cpufeature:meta) => {
         mod pack_unpack_with_bits_32 {
             use super::BLOCK_LEN;
-            use super::{load_unaligned, store_unaligned, DataType};
+            use super::{load_compressed, load_unaligned, store_compressed, DataType};
             use super::{Sink, Transformer};
             use crunchy::unroll;
+            use std::mem::MaybeUninit;
 
             const NUM_BITS: usize = 32;
             const NUM_BYTES_PER_BLOCK: usize = NUM_BITS * BLOCK_LEN / 8;
@@ -140,7 +143,7 @@ macro_rules! pack_unpack_with_bits_32 {
             #[$cpufeature]
             pub(crate) unsafe fn pack<TDeltaComputer: Transformer>(
                 input_arr: &[u32],
-                output_arr: &mut [u8],
+                output_arr: &mut [MaybeUninit<u8>],
                 mut delta_computer: TDeltaComputer,
             ) -> usize {
                 assert_eq!(
@@ -166,7 +169,7 @@ macro_rules! pack_unpack_with_bits_32 {
                         let output_offset_ptr = output_ptr.add(i);
                         let input_register = load_unaligned(input_offset_ptr);
                         let output_register = delta_computer.transform(input_register);
-                        store_unaligned(output_offset_ptr, output_register);
+                        store_compressed(output_offset_ptr, output_register);
                     }
                 }
                 NUM_BYTES_PER_BLOCK
@@ -186,7 +189,7 @@ macro_rules! pack_unpack_with_bits_32 {
                 let input_ptr = compressed.as_ptr().cast::<DataType>();
                 for i in 0..32 {
                     let input_offset_ptr = input_ptr.add(i);
-                    let in_register: DataType = load_unaligned(input_offset_ptr);
+                    let in_register: DataType = load_compressed(input_offset_ptr);
                     output.process(in_register);
                 }
                 NUM_BYTES_PER_BLOCK
@@ -197,9 +200,15 @@ macro_rules! pack_unpack_with_bits_32 {
 
 macro_rules! declare_bitpacker {
     ($cpufeature:meta) => {
+        declare_bitpacker!($cpufeature, UnsafeBitPackerImpl);
+    };
+    // `$tested` is the implementation run through the test suite, when it wraps
+    // `UnsafeBitPackerImpl` in another layout.
+    ($cpufeature:meta, $tested:ident) => {
         use super::super::UnsafeBitPacker;
-        use crate::most_significant_bit;
+        use crate::{as_uninit, most_significant_bit, unsorted_error, BlockWidths, CompressError};
         use crunchy::unroll;
+        use std::mem::MaybeUninit;
 
         pack_unpack_with_bits!(pack_unpack_with_bits_1, 1, $cpufeature);
         pack_unpack_with_bits!(pack_unpack_with_bits_2, 2, $cpufeature);
@@ -236,7 +245,7 @@ macro_rules! declare_bitpacker {
 
         unsafe fn compress_generic<DeltaComputer: Transformer>(
             decompressed: &[u32],
-            compressed: &mut [u8],
+            compressed: &mut [MaybeUninit<u8>],
             num_bits: u8,
             delta_computer: DeltaComputer,
         ) -> usize {
@@ -319,6 +328,37 @@ macro_rules! declare_bitpacker {
             }
         }
 
+        struct ForComputer {
+            pub reference: DataType,
+        }
+
+        impl Transformer for ForComputer {
+            #[inline]
+            unsafe fn transform(&mut self, current: DataType) -> DataType {
+                sub(current, self.reference)
+            }
+        }
+
+        struct MaskComputer {
+            pub mask: DataType,
+        }
+
+        impl MaskComputer {
+            unsafe fn new(num_bits: u8) -> MaskComputer {
+                let mask_scalar: u32 = ((1u64 << num_bits) - 1u64) as u32;
+                MaskComputer {
+                    mask: set1(mask_scalar as i32),
+                }
+            }
+        }
+
+        impl Transformer for MaskComputer {
+            #[inline]
+            unsafe fn transform(&mut self, current: DataType) -> DataType {
+                op_and(current, self.mask)
+            }
+        }
+
         pub trait Sink {
             unsafe fn process(&mut self, data_type: DataType);
         }
@@ -387,6 +427,124 @@ macro_rules! declare_bitpacker {
             }
         }
 
+        struct StoreU8 {
+            output_ptr: *mut u8,
+        }
+
+        impl Sink for StoreU8 {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                store_u8(self.output_ptr, out_register);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        struct StoreU16 {
+            output_ptr: *mut u16,
+        }
+
+        impl Sink for StoreU16 {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                store_u16(self.output_ptr, out_register);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        struct StoreU64 {
+            base: u64,
+            output_ptr: *mut u64,
+        }
+
+        impl Sink for StoreU64 {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                store_u64(self.output_ptr, self.base, out_register);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        /// Integrates the deltas in `u32`, relative to the initial value: the deltas of a
+        /// block compressed from sorted `u32` sum to less than `2^32`.
+        struct DeltaIntegrateU64 {
+            current: DataType,
+            base: u64,
+            output_ptr: *mut u64,
+        }
+
+        impl Sink for DeltaIntegrateU64 {
+            #[inline]
+            unsafe fn process(&mut self, delta: DataType) {
+                self.current = integrate_delta(self.current, delta);
+                store_u64(self.output_ptr, self.base, self.current);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        /// Hands each register over to a public `crate::Sink`.
+        struct Forward<'a, TSink: crate::Sink> {
+            sink: &'a mut TSink,
+        }
+
+        impl<TSink: crate::Sink> Sink for Forward<'_, TSink> {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                let mut values = [0u32; BLOCK_LEN / 32];
+                store_unaligned(values.as_mut_ptr().cast::<DataType>(), out_register);
+                self.sink.process(&values);
+            }
+        }
+
+        /// Integrates the deltas, and hands each register over to a public `crate::Sink`.
+        struct DeltaForward<'a, TSink: crate::Sink> {
+            current: DataType,
+            increment: DataType,
+            sink: Forward<'a, TSink>,
+        }
+
+        impl<'a, TSink: crate::Sink> DeltaForward<'a, TSink> {
+            unsafe fn new(initial: u32, strict: bool, sink: &'a mut TSink) -> Self {
+                DeltaForward {
+                    current: set1(initial as i32),
+                    increment: set1(strict as i32),
+                    sink: Forward { sink },
+                }
+            }
+        }
+
+        impl<TSink: crate::Sink> Sink for DeltaForward<'_, TSink> {
+            #[inline]
+            unsafe fn process(&mut self, delta: DataType) {
+                self.current = integrate_delta(self.current, add(delta, self.increment));
+                self.sink.process(self.current);
+            }
+        }
+
+        struct Scale {
+            offset: DataType,
+            factor: DataType,
+            output_ptr: *mut DataType,
+        }
+
+        impl Scale {
+            unsafe fn new(offset: u32, factor: u32, output_ptr: *mut DataType) -> Scale {
+                Scale {
+                    offset: set1(offset as i32),
+                    factor: set1(factor as i32),
+                    output_ptr,
+                }
+            }
+        }
+
+        impl Sink for Scale {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                let scaled = add(self.offset, mul(out_register, self.factor));
+                store_unaligned(self.output_ptr, scaled);
+                self.output_ptr = self.output_ptr.add(1);
+            }
+        }
+
         #[inline]
         unsafe fn decompress_to<Output: Sink>(
             compressed: &[u8],
@@ -439,6 +597,45 @@ macro_rules! declare_bitpacker {
             }
         }
 
+        /// Loads and transforms the registers of the block once, OR-accumulating them to
+        /// compute the number of bits. The transformed registers are staged in `staged`,
+        /// so that they can be packed without reading the block again.
+        #[inline]
+        unsafe fn stage<TTransformer: Transformer>(
+            decompressed: &[u32],
+            staged: &mut [DataType; 32],
+            mut transformer: TTransformer,
+        ) -> u8 {
+            assert_eq!(
+                decompressed.len(),
+                BLOCK_LEN,
+                "`decompressed`'s len is not `BLOCK_LEN={}`",
+                BLOCK_LEN
+            );
+            let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+            let mut accumulator = set1(0);
+            for (i, register) in staged.iter_mut().enumerate() {
+                let transformed = transformer.transform(load_unaligned(data.add(i)));
+                accumulator = op_or(accumulator, transformed);
+                *register = transformed;
+            }
+            most_significant_bit(or_collapse_to_u32(accumulator))
+        }
+
+        #[inline]
+        unsafe fn compress_auto_generic<TTransformer: Transformer>(
+            decompressed: &[u32],
+            compressed: &mut [u8],
+            transformer: TTransformer,
+        ) -> (u8, usize) {
+            let mut staged = [set1(0); 32];
+            let num_bits = stage(decompressed, &mut staged, transformer);
+            let staged_values =
+                std::slice::from_raw_parts(staged.as_ptr().cast::<u32>(), BLOCK_LEN);
+            let written = compress_generic(staged_values, as_uninit(compressed), num_bits, NoDelta);
+            (num_bits, written)
+        }
+
         pub struct UnsafeBitPackerImpl;
 
         impl UnsafeBitPacker for UnsafeBitPackerImpl {
@@ -446,6 +643,15 @@ macro_rules! declare_bitpacker {
 
             #[$cpufeature]
             unsafe fn compress(decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+                compress_generic(decompressed, as_uninit(compressed), num_bits, NoDelta)
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_uninit(
+                decompressed: &[u32],
+                compressed: &mut [MaybeUninit<u8>],
+                num_bits: u8,
+            ) -> usize {
                 compress_generic(decompressed, compressed, num_bits, NoDelta)
             }
 
@@ -459,7 +665,7 @@ macro_rules! declare_bitpacker {
                 let delta_computer = DeltaComputer {
                     previous: set1(initial as i32),
                 };
-                compress_generic(decompressed, compressed, num_bits, delta_computer)
+                compress_generic(decompressed, as_uninit(compressed), num_bits, delta_computer)
             }
 
             #[$cpufeature]
@@ -476,7 +682,63 @@ macro_rules! declare_bitpacker {
                 let delta_computer = StrictDeltaComputer {
                     previous: set1(initial as i32),
                 };
-                compress_generic(decompressed, compressed, num_bits, delta_computer)
+                compress_generic(decompressed, as_uninit(compressed), num_bits, delta_computer)
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_for(
+                reference: u32,
+                decompressed: &[u32],
+                compressed: &mut [u8],
+                num_bits: u8,
+            ) -> usize {
+                let for_computer = ForComputer {
+                    reference: set1(reference as i32),
+                };
+                compress_generic(decompressed, as_uninit(compressed), num_bits, for_computer)
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_masked(
+                decompressed: &[u32],
+                compressed: &mut [u8],
+                num_bits: u8,
+            ) -> usize {
+                compress_generic(
+                    decompressed,
+                    as_uninit(compressed),
+                    num_bits,
+                    MaskComputer::new(num_bits),
+                )
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_auto(decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+                compress_auto_generic(decompressed, compressed, NoDelta)
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_sorted_auto(
+                initial: u32,
+                decompressed: &[u32],
+                compressed: &mut [u8],
+            ) -> (u8, usize) {
+                let delta_computer = DeltaComputer {
+                    previous: set1(initial as i32),
+                };
+                compress_auto_generic(decompressed, compressed, delta_computer)
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_strictly_sorted_auto(
+                initial: Option<u32>,
+                decompressed: &[u32],
+                compressed: &mut [u8],
+            ) -> (u8, usize) {
+                let delta_computer = StrictDeltaComputer {
+                    previous: set1(initial.unwrap_or(u32::MAX) as i32),
+                };
+                compress_auto_generic(decompressed, compressed, delta_computer)
             }
 
             #[$cpufeature]
@@ -484,6 +746,15 @@ macro_rules! declare_bitpacker {
                 compressed: &[u8],
                 decompressed: &mut [u32],
                 num_bits: u8,
+            ) -> usize {
+                Self::decompress_uninit(compressed, as_uninit(decompressed), num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_uninit(
+                compressed: &[u8],
+                decompressed: &mut [MaybeUninit<u32>],
+                num_bits: u8,
             ) -> usize {
                 assert!(
                     decompressed.len() >= BLOCK_LEN,
@@ -533,6 +804,184 @@ macro_rules! declare_bitpacker {
                 decompress_to(compressed, output, num_bits)
             }
 
+            #[$cpufeature]
+            unsafe fn decompress_scaled(
+                offset: u32,
+                factor: u32,
+                compressed: &[u8],
+                decompressed: &mut [u32],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output_ptr = decompressed.as_mut_ptr().cast::<DataType>();
+                let output = Scale::new(offset, factor, output_ptr);
+                decompress_to(compressed, output, num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_u8(decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+                assert!(
+                    num_bits <= 8,
+                    "Num bits must be <= 8 for `u8` integers. Was {}.",
+                    num_bits
+                );
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let mut widened = [set1(0); 32];
+                for (i, register) in widened.iter_mut().enumerate() {
+                    *register = load_u8(decompressed.as_ptr().add(i * BLOCK_LEN / 32));
+                }
+                let widened = std::slice::from_raw_parts(widened.as_ptr().cast::<u32>(), BLOCK_LEN);
+                compress_generic(widened, as_uninit(compressed), num_bits, NoDelta)
+            }
+
+            #[$cpufeature]
+            unsafe fn compress_u16(decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+                assert!(
+                    num_bits <= 16,
+                    "Num bits must be <= 16 for `u16` integers. Was {}.",
+                    num_bits
+                );
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let mut widened = [set1(0); 32];
+                for (i, register) in widened.iter_mut().enumerate() {
+                    *register = load_u16(decompressed.as_ptr().add(i * BLOCK_LEN / 32));
+                }
+                let widened = std::slice::from_raw_parts(widened.as_ptr().cast::<u32>(), BLOCK_LEN);
+                compress_generic(widened, as_uninit(compressed), num_bits, NoDelta)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_into_u8(
+                compressed: &[u8],
+                decompressed: &mut [u8],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    num_bits <= 8,
+                    "Num bits must be <= 8 for `u8` integers. Was {}.",
+                    num_bits
+                );
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = StoreU8 {
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_into_u16(
+                compressed: &[u8],
+                decompressed: &mut [u16],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    num_bits <= 16,
+                    "Num bits must be <= 16 for `u16` integers. Was {}.",
+                    num_bits
+                );
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = StoreU16 {
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_into_u64(
+                base: u64,
+                compressed: &[u8],
+                decompressed: &mut [u64],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = StoreU64 {
+                    base,
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_sorted_into_u64(
+                initial: u64,
+                compressed: &[u8],
+                decompressed: &mut [u64],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = DeltaIntegrateU64 {
+                    current: set1(0),
+                    base: initial,
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_to_sink<TSink: crate::Sink>(
+                compressed: &[u8],
+                sink: &mut TSink,
+                num_bits: u8,
+            ) -> usize {
+                decompress_to(compressed, Forward { sink }, num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_sorted_to_sink<TSink: crate::Sink>(
+                initial: u32,
+                compressed: &[u8],
+                sink: &mut TSink,
+                num_bits: u8,
+            ) -> usize {
+                decompress_to(compressed, DeltaForward::new(initial, false, sink), num_bits)
+            }
+
+            #[$cpufeature]
+            unsafe fn decompress_strictly_sorted_to_sink<TSink: crate::Sink>(
+                initial: Option<u32>,
+                compressed: &[u8],
+                sink: &mut TSink,
+                num_bits: u8,
+            ) -> usize {
+                let initial = initial.unwrap_or(u32::MAX);
+                decompress_to(compressed, DeltaForward::new(initial, true, sink), num_bits)
+            }
+
             #[$cpufeature]
             unsafe fn num_bits(decompressed: &[u32]) -> u8 {
                 assert_eq!(
@@ -614,12 +1063,128 @@ macro_rules! declare_bitpacker {
                 accumulator = op_or(accumulator, delta);
                 most_significant_bit(or_collapse_to_u32(accumulator))
             }
+
+            #[$cpufeature]
+            unsafe fn num_bits_sorted_checked(
+                initial: u32,
+                decompressed: &[u32],
+            ) -> Result<u8, CompressError> {
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+                let mut previous = set1(initial as i32);
+                let mut accumulator = set1(0);
+                let mut violations = set1(0);
+                for i in 0..32 {
+                    let current = load_unaligned(data.add(i));
+                    let delta = compute_delta(current, previous);
+                    accumulator = op_or(accumulator, delta);
+                    // The delta wraps around, and exceeds the current integer, iff the
+                    // current integer is lower than the previous one.
+                    violations = op_or(violations, op_gt_u32(delta, current));
+                    previous = current;
+                }
+                if or_collapse_to_u32(violations) != 0 {
+                    return Err(unsorted_error(Some(initial), false, decompressed));
+                }
+                Ok(most_significant_bit(or_collapse_to_u32(accumulator)))
+            }
+
+            #[$cpufeature]
+            unsafe fn num_bits_strictly_sorted_checked(
+                initial: Option<u32>,
+                decompressed: &[u32],
+            ) -> Result<u8, CompressError> {
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+                let one = set1(1);
+                let mut previous = set1(initial.unwrap_or(u32::MAX) as i32);
+                let mut accumulator = set1(0);
+                // The first integer is unconstrained without an initial value: this sets
+                // its lane only.
+                let mut first_lane = if initial.is_none() {
+                    compute_delta(set1(0), one)
+                } else {
+                    set1(0)
+                };
+                // Lanes stay all ones as long as the integers are strictly increasing.
+                let mut valid = set1(-1);
+                for i in 0..32 {
+                    let current = load_unaligned(data.add(i));
+                    let delta = sub(compute_delta(current, previous), one);
+                    accumulator = op_or(accumulator, delta);
+                    valid = op_and(valid, op_or(op_gt_u32(current, delta), first_lane));
+                    first_lane = set1(0);
+                    previous = current;
+                }
+                if or_collapse_to_u32(add(valid, one)) != 0 {
+                    return Err(unsorted_error(initial, true, decompressed));
+                }
+                Ok(most_significant_bit(or_collapse_to_u32(accumulator)))
+            }
+
+            #[$cpufeature]
+            unsafe fn num_bits_all_modes(initial: u32, decompressed: &[u32]) -> BlockWidths {
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+                let one = set1(1);
+                let mut previous = set1(initial as i32);
+                let mut plain = set1(0);
+                let mut sorted = set1(0);
+                let mut strictly_sorted = set1(0);
+                let mut min = set1(-1);
+                let mut max = set1(0);
+                for i in 0..32 {
+                    let current = load_unaligned(data.add(i));
+                    let delta = compute_delta(current, previous);
+                    plain = op_or(plain, current);
+                    sorted = op_or(sorted, delta);
+                    strictly_sorted = op_or(strictly_sorted, sub(delta, one));
+                    // Branchless unsigned min and max: the difference is only applied
+                    // to the lanes where the comparison holds.
+                    min = sub(min, op_and(sub(min, current), op_gt_u32(min, current)));
+                    max = add(max, op_and(sub(current, max), op_gt_u32(current, max)));
+                    previous = current;
+                }
+                let mut mins = [0u32; BLOCK_LEN / 32];
+                let mut maxs = [0u32; BLOCK_LEN / 32];
+                store_unaligned(mins.as_mut_ptr().cast::<DataType>(), min);
+                store_unaligned(maxs.as_mut_ptr().cast::<DataType>(), max);
+                let min = mins.iter().copied().min().unwrap_or(0);
+                let max = maxs.iter().copied().max().unwrap_or(0);
+                BlockWidths {
+                    plain: most_significant_bit(or_collapse_to_u32(plain)),
+                    sorted: most_significant_bit(or_collapse_to_u32(sorted)),
+                    strictly_sorted: most_significant_bit(or_collapse_to_u32(strictly_sorted)),
+                    frame_of_reference: most_significant_bit(max - min),
+                    min,
+                }
+            }
         }
 
         #[cfg(test)]
         mod tests {
-            use super::UnsafeBitPackerImpl;
-            use crate::tests::{test_suite_compress_decompress, DeltaKind};
+            use super::$tested as UnsafeBitPackerImpl;
+            use crate::tests::{
+                test_suite_compress_decompress, test_util_compress_auto, test_util_compress_for,
+                test_util_compress_masked, test_util_decompress_scaled, test_util_decompress_to_sink,
+                test_util_decompress_u64, test_util_narrow, test_util_num_bits_all_modes,
+                test_util_num_bits_sorted_checked, DeltaKind,
+            };
             use crate::Available;
             use crate::UnsafeBitPacker;
 
@@ -661,6 +1226,69 @@ macro_rules! declare_bitpacker {
                     test_suite_compress_decompress::<UnsafeBitPackerImpl>(DeltaKind::StrictDelta);
                 }
             }
+
+            #[test]
+            fn test_bitpacker_scaled() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_decompress_scaled::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_bitpacker_for() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_compress_for::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_bitpacker_masked() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_compress_masked::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_num_bits_sorted_checked() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_num_bits_sorted_checked::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_compress_auto() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_compress_auto::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_decompress_to_sink() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_decompress_to_sink::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_narrow() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_narrow::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_decompress_u64() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_decompress_u64::<UnsafeBitPackerImpl>();
+                }
+            }
+
+            #[test]
+            fn test_num_bits_all_modes() {
+                if UnsafeBitPackerImpl::available() {
+                    test_util_num_bits_all_modes::<UnsafeBitPackerImpl>();
+                }
+            }
         }
     };
 }
@@ -4,11 +4,12 @@ macro_rules! declare_bitpacker_simple {
             (num_bits * BLOCK_LEN) / 8
         }
         use super::UnsafeBitPacker;
-        use crate::most_significant_bit;
+        use crate::{as_uninit, most_significant_bit, unsorted_error, BlockWidths, CompressError};
+        use std::mem::MaybeUninit;
 
         pub unsafe fn pack<TDeltaComputer: Transformer>(
             input_arr: &[u32],
-            output_arr: &mut [u8],
+            output_arr: &mut [MaybeUninit<u8>],
             num_bits: usize,
             mut delta_computer: TDeltaComputer,
         ) -> usize {
@@ -48,7 +49,7 @@ macro_rules! declare_bitpacker_simple {
                 };
 
                 if remaining <= num_bits {
-                    store_unaligned(output_ptr, out_register);
+                    store_compressed(output_ptr, out_register);
                     output_ptr = output_ptr.add(1);
                     if remaining < num_bits {
                         out_register = right_shift_32(in_register, remaining as i32);
@@ -60,13 +61,13 @@ macro_rules! declare_bitpacker_simple {
 
             let shifted = left_shift_32(in_register, 32 - num_bits as i32);
             out_register = op_or(out_register, shifted);
-            store_unaligned(output_ptr, out_register);
+            store_compressed(output_ptr, out_register);
             num_bytes_per_block
         }
 
         pub unsafe fn pack_32<TDeltaComputer: Transformer>(
             input_arr: &[u32],
-            output_arr: &mut [u8],
+            output_arr: &mut [MaybeUninit<u8>],
             mut delta_computer: TDeltaComputer,
         ) -> usize {
             assert_eq!(
@@ -92,7 +93,7 @@ macro_rules! declare_bitpacker_simple {
                 let output_offset_ptr = output_ptr.add(i);
                 let input_register = load_unaligned(input_offset_ptr);
                 let output_register = delta_computer.transform(input_register);
-                store_unaligned(output_offset_ptr, output_register);
+                store_compressed(output_offset_ptr, output_register);
             }
             num_bytes_per_block
         }
@@ -115,7 +116,7 @@ macro_rules! declare_bitpacker_simple {
             let mask_scalar: u32 = ((1u64 << num_bits) - 1u64) as u32;
             let mask = set1(mask_scalar as i32);
 
-            let mut in_register: DataType = load_unaligned(input_ptr);
+            let mut in_register: DataType = load_compressed(input_ptr);
 
             let out_register = op_and(in_register, mask);
             output.process(out_register);
@@ -133,7 +134,7 @@ macro_rules! declare_bitpacker_simple {
                 // We therefore read another one.
                 if inner_capacity <= num_bits && i != 31 {
                     input_ptr = input_ptr.add(1);
-                    in_register = load_unaligned(input_ptr);
+                    in_register = load_compressed(input_ptr);
 
                     // This quadruplets is actually cutting one of
                     // our `DataType`. We need to read the next one.
@@ -161,7 +162,7 @@ macro_rules! declare_bitpacker_simple {
             let input_ptr = compressed.as_ptr().cast::<DataType>();
             for i in 0..32 {
                 let input_offset_ptr = input_ptr.add(i);
-                let in_register: DataType = load_unaligned(input_offset_ptr);
+                let in_register: DataType = load_compressed(input_offset_ptr);
                 output.process(in_register);
             }
             num_bytes_per_block
@@ -204,6 +205,37 @@ macro_rules! declare_bitpacker_simple {
             }
         }
 
+        struct ForComputer {
+            pub reference: DataType,
+        }
+
+        impl Transformer for ForComputer {
+            #[inline]
+            unsafe fn transform(&mut self, current: DataType) -> DataType {
+                sub(current, self.reference)
+            }
+        }
+
+        struct MaskComputer {
+            pub mask: DataType,
+        }
+
+        impl MaskComputer {
+            unsafe fn new(num_bits: u8) -> MaskComputer {
+                let mask_scalar: u32 = ((1u64 << num_bits) - 1u64) as u32;
+                MaskComputer {
+                    mask: set1(mask_scalar as i32),
+                }
+            }
+        }
+
+        impl Transformer for MaskComputer {
+            #[inline]
+            unsafe fn transform(&mut self, current: DataType) -> DataType {
+                op_and(current, self.mask)
+            }
+        }
+
         pub trait Sink {
             unsafe fn process(&mut self, data_type: DataType);
         }
@@ -272,12 +304,199 @@ macro_rules! declare_bitpacker_simple {
             }
         }
 
+        struct StoreU8 {
+            output_ptr: *mut u8,
+        }
+
+        impl Sink for StoreU8 {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                store_u8(self.output_ptr, out_register);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        struct StoreU16 {
+            output_ptr: *mut u16,
+        }
+
+        impl Sink for StoreU16 {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                store_u16(self.output_ptr, out_register);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        struct StoreU64 {
+            base: u64,
+            output_ptr: *mut u64,
+        }
+
+        impl Sink for StoreU64 {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                store_u64(self.output_ptr, self.base, out_register);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        /// Integrates the deltas in `u32`, relative to the initial value: the deltas of a
+        /// block compressed from sorted `u32` sum to less than `2^32`.
+        struct DeltaIntegrateU64 {
+            current: DataType,
+            base: u64,
+            output_ptr: *mut u64,
+        }
+
+        impl Sink for DeltaIntegrateU64 {
+            #[inline]
+            unsafe fn process(&mut self, delta: DataType) {
+                self.current = integrate_delta(self.current, delta);
+                store_u64(self.output_ptr, self.base, self.current);
+                self.output_ptr = self.output_ptr.add(BLOCK_LEN / 32);
+            }
+        }
+
+        /// Hands each register over to a public `crate::Sink`.
+        struct Forward<'a, TSink: crate::Sink> {
+            sink: &'a mut TSink,
+        }
+
+        impl<TSink: crate::Sink> Sink for Forward<'_, TSink> {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                let mut values = [0u32; BLOCK_LEN / 32];
+                store_unaligned(values.as_mut_ptr().cast::<DataType>(), out_register);
+                self.sink.process(&values);
+            }
+        }
+
+        /// Integrates the deltas, and hands each register over to a public `crate::Sink`.
+        struct DeltaForward<'a, TSink: crate::Sink> {
+            current: DataType,
+            increment: DataType,
+            sink: Forward<'a, TSink>,
+        }
+
+        impl<'a, TSink: crate::Sink> DeltaForward<'a, TSink> {
+            unsafe fn new(initial: u32, strict: bool, sink: &'a mut TSink) -> Self {
+                DeltaForward {
+                    current: set1(initial as i32),
+                    increment: set1(strict as i32),
+                    sink: Forward { sink },
+                }
+            }
+        }
+
+        impl<TSink: crate::Sink> Sink for DeltaForward<'_, TSink> {
+            #[inline]
+            unsafe fn process(&mut self, delta: DataType) {
+                self.current = integrate_delta(self.current, add(delta, self.increment));
+                self.sink.process(self.current);
+            }
+        }
+
+        struct Scale {
+            offset: DataType,
+            factor: DataType,
+            output_ptr: *mut DataType,
+        }
+
+        impl Scale {
+            unsafe fn new(offset: u32, factor: u32, output_ptr: *mut DataType) -> Scale {
+                Scale {
+                    offset: set1(offset as i32),
+                    factor: set1(factor as i32),
+                    output_ptr,
+                }
+            }
+        }
+
+        impl Sink for Scale {
+            #[inline]
+            unsafe fn process(&mut self, out_register: DataType) {
+                let scaled = add(self.offset, mul(out_register, self.factor));
+                store_unaligned(self.output_ptr, scaled);
+                self.output_ptr = self.output_ptr.add(1);
+            }
+        }
+
+        #[inline]
+        unsafe fn decompress_to<Output: Sink>(
+            compressed: &[u8],
+            mut output: Output,
+            num_bits: u8,
+        ) -> usize {
+            if num_bits == 0u8 {
+                let zero = set1(0i32);
+                for _ in 0..32 {
+                    output.process(zero);
+                }
+                return 0;
+            }
+            if num_bits == 32u8 {
+                return unpack_32(compressed, output);
+            }
+            unpack(compressed, output, num_bits as usize)
+        }
+
+        /// Loads and transforms the registers of the block once, OR-accumulating them to
+        /// compute the number of bits. The transformed registers are staged in `staged`,
+        /// so that they can be packed without reading the block again.
+        #[inline]
+        unsafe fn stage<TTransformer: Transformer>(
+            decompressed: &[u32],
+            staged: &mut [DataType; 32],
+            mut transformer: TTransformer,
+        ) -> u8 {
+            assert_eq!(
+                decompressed.len(),
+                BLOCK_LEN,
+                "`decompressed`'s len is not `BLOCK_LEN={}`",
+                BLOCK_LEN
+            );
+            let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+            let mut accumulator = set1(0);
+            for (i, register) in staged.iter_mut().enumerate() {
+                let transformed = transformer.transform(load_unaligned(data.add(i)));
+                accumulator = op_or(accumulator, transformed);
+                *register = transformed;
+            }
+            most_significant_bit(or_collapse_to_u32(accumulator))
+        }
+
+        unsafe fn compress_auto_generic<TTransformer: Transformer>(
+            decompressed: &[u32],
+            compressed: &mut [u8],
+            transformer: TTransformer,
+        ) -> (u8, usize) {
+            let mut staged = [set1(0); 32];
+            let num_bits = stage(decompressed, &mut staged, transformer);
+            let staged_values =
+                std::slice::from_raw_parts(staged.as_ptr().cast::<u32>(), BLOCK_LEN);
+            let written = match num_bits {
+                0 => 0,
+                32 => pack_32(staged_values, as_uninit(compressed), NoDelta),
+                _ => pack(staged_values, as_uninit(compressed), num_bits as usize, NoDelta),
+            };
+            (num_bits, written)
+        }
+
         pub struct UnsafeBitPackerImpl;
 
         impl UnsafeBitPacker for UnsafeBitPackerImpl {
             const BLOCK_LEN: usize = BLOCK_LEN;
 
             unsafe fn compress(decompressed: &[u32], compressed: &mut [u8], num_bits: u8) -> usize {
+                Self::compress_uninit(decompressed, as_uninit(compressed), num_bits)
+            }
+
+            unsafe fn compress_uninit(
+                decompressed: &[u32],
+                compressed: &mut [MaybeUninit<u8>],
+                num_bits: u8,
+            ) -> usize {
                 if num_bits == 0u8 {
                     return 0;
                 }
@@ -300,9 +519,9 @@ macro_rules! declare_bitpacker_simple {
                     previous: set1(initial as i32),
                 };
                 if num_bits == 32u8 {
-                    return pack_32(decompressed, compressed, delta_computer);
+                    return pack_32(decompressed, as_uninit(compressed), delta_computer);
                 }
-                pack(decompressed, compressed, num_bits as usize, delta_computer)
+                pack(decompressed, as_uninit(compressed), num_bits as usize, delta_computer)
             }
 
             unsafe fn compress_strictly_sorted(
@@ -319,15 +538,86 @@ macro_rules! declare_bitpacker_simple {
                     previous: set1(initial as i32),
                 };
                 if num_bits == 32u8 {
-                    return pack_32(decompressed, compressed, delta_computer);
+                    return pack_32(decompressed, as_uninit(compressed), delta_computer);
+                }
+                pack(decompressed, as_uninit(compressed), num_bits as usize, delta_computer)
+            }
+
+            unsafe fn compress_for(
+                reference: u32,
+                decompressed: &[u32],
+                compressed: &mut [u8],
+                num_bits: u8,
+            ) -> usize {
+                if num_bits == 0u8 {
+                    return 0;
+                }
+                let for_computer = ForComputer {
+                    reference: set1(reference as i32),
+                };
+                if num_bits == 32u8 {
+                    return pack_32(decompressed, as_uninit(compressed), for_computer);
+                }
+                pack(decompressed, as_uninit(compressed), num_bits as usize, for_computer)
+            }
+
+            unsafe fn compress_masked(
+                decompressed: &[u32],
+                compressed: &mut [u8],
+                num_bits: u8,
+            ) -> usize {
+                if num_bits == 0u8 {
+                    return 0;
+                }
+                if num_bits == 32u8 {
+                    return pack_32(decompressed, as_uninit(compressed), NoDelta);
                 }
-                pack(decompressed, compressed, num_bits as usize, delta_computer)
+                pack(
+                    decompressed,
+                    as_uninit(compressed),
+                    num_bits as usize,
+                    MaskComputer::new(num_bits),
+                )
+            }
+
+            unsafe fn compress_auto(decompressed: &[u32], compressed: &mut [u8]) -> (u8, usize) {
+                compress_auto_generic(decompressed, compressed, NoDelta)
+            }
+
+            unsafe fn compress_sorted_auto(
+                initial: u32,
+                decompressed: &[u32],
+                compressed: &mut [u8],
+            ) -> (u8, usize) {
+                let delta_computer = DeltaComputer {
+                    previous: set1(initial as i32),
+                };
+                compress_auto_generic(decompressed, compressed, delta_computer)
+            }
+
+            unsafe fn compress_strictly_sorted_auto(
+                initial: Option<u32>,
+                decompressed: &[u32],
+                compressed: &mut [u8],
+            ) -> (u8, usize) {
+                let delta_computer = StrictDeltaComputer {
+                    previous: set1(initial.unwrap_or(u32::MAX) as i32),
+                };
+                compress_auto_generic(decompressed, compressed, delta_computer)
             }
 
             unsafe fn decompress(
                 compressed: &[u8],
                 decompressed: &mut [u32],
                 num_bits: u8,
+            ) -> usize {
+                Self::decompress_uninit(compressed, as_uninit(decompressed), num_bits)
+            }
+
+            unsafe fn decompress_uninit(
+                compressed: &[u8],
+                decompressed: &mut [MaybeUninit<u32>],
+                num_bits: u8,
             ) -> usize {
                 assert!(
                     decompressed.len() >= BLOCK_LEN,
@@ -405,6 +695,190 @@ macro_rules! declare_bitpacker_simple {
                 unpack(compressed, output, num_bits as usize)
             }
 
+            unsafe fn decompress_scaled(
+                offset: u32,
+                factor: u32,
+                compressed: &[u8],
+                decompressed: &mut [u32],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output_ptr = decompressed.as_mut_ptr().cast::<DataType>();
+                let mut output = Scale::new(offset, factor, output_ptr);
+                if num_bits == 0u8 {
+                    let zero = set1(0i32);
+                    for _ in 0..32 {
+                        output.process(zero);
+                    }
+                    return 0;
+                }
+                if num_bits == 32u8 {
+                    return unpack_32(compressed, output);
+                }
+                unpack(compressed, output, num_bits as usize)
+            }
+
+            unsafe fn compress_u8(decompressed: &[u8], compressed: &mut [u8], num_bits: u8) -> usize {
+                assert!(
+                    num_bits <= 8,
+                    "Num bits must be <= 8 for `u8` integers. Was {}.",
+                    num_bits
+                );
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let mut widened = [set1(0); 32];
+                for (i, register) in widened.iter_mut().enumerate() {
+                    *register = load_u8(decompressed.as_ptr().add(i * BLOCK_LEN / 32));
+                }
+                let widened = std::slice::from_raw_parts(widened.as_ptr().cast::<u32>(), BLOCK_LEN);
+                if num_bits == 0u8 {
+                    return 0;
+                }
+                pack(widened, as_uninit(compressed), num_bits as usize, NoDelta)
+            }
+
+            unsafe fn compress_u16(decompressed: &[u16], compressed: &mut [u8], num_bits: u8) -> usize {
+                assert!(
+                    num_bits <= 16,
+                    "Num bits must be <= 16 for `u16` integers. Was {}.",
+                    num_bits
+                );
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let mut widened = [set1(0); 32];
+                for (i, register) in widened.iter_mut().enumerate() {
+                    *register = load_u16(decompressed.as_ptr().add(i * BLOCK_LEN / 32));
+                }
+                let widened = std::slice::from_raw_parts(widened.as_ptr().cast::<u32>(), BLOCK_LEN);
+                if num_bits == 0u8 {
+                    return 0;
+                }
+                pack(widened, as_uninit(compressed), num_bits as usize, NoDelta)
+            }
+
+            unsafe fn decompress_into_u8(
+                compressed: &[u8],
+                decompressed: &mut [u8],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    num_bits <= 8,
+                    "Num bits must be <= 8 for `u8` integers. Was {}.",
+                    num_bits
+                );
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = StoreU8 {
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            unsafe fn decompress_into_u16(
+                compressed: &[u8],
+                decompressed: &mut [u16],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    num_bits <= 16,
+                    "Num bits must be <= 16 for `u16` integers. Was {}.",
+                    num_bits
+                );
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = StoreU16 {
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            unsafe fn decompress_into_u64(
+                base: u64,
+                compressed: &[u8],
+                decompressed: &mut [u64],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = StoreU64 {
+                    base,
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            unsafe fn decompress_sorted_into_u64(
+                initial: u64,
+                compressed: &[u8],
+                decompressed: &mut [u64],
+                num_bits: u8,
+            ) -> usize {
+                assert!(
+                    decompressed.len() >= BLOCK_LEN,
+                    "The output array is not large enough : ({} >= {})",
+                    decompressed.len(),
+                    BLOCK_LEN
+                );
+                let output = DeltaIntegrateU64 {
+                    current: set1(0),
+                    base: initial,
+                    output_ptr: decompressed.as_mut_ptr(),
+                };
+                decompress_to(compressed, output, num_bits)
+            }
+
+            unsafe fn decompress_to_sink<TSink: crate::Sink>(
+                compressed: &[u8],
+                sink: &mut TSink,
+                num_bits: u8,
+            ) -> usize {
+                decompress_to(compressed, Forward { sink }, num_bits)
+            }
+
+            unsafe fn decompress_sorted_to_sink<TSink: crate::Sink>(
+                initial: u32,
+                compressed: &[u8],
+                sink: &mut TSink,
+                num_bits: u8,
+            ) -> usize {
+                decompress_to(compressed, DeltaForward::new(initial, false, sink), num_bits)
+            }
+
+            unsafe fn decompress_strictly_sorted_to_sink<TSink: crate::Sink>(
+                initial: Option<u32>,
+                compressed: &[u8],
+                sink: &mut TSink,
+                num_bits: u8,
+            ) -> usize {
+                let initial = initial.unwrap_or(u32::MAX);
+                decompress_to(compressed, DeltaForward::new(initial, true, sink), num_bits)
+            }
+
             unsafe fn num_bits(decompressed: &[u32]) -> u8 {
                 assert_eq!(
                     decompressed.len(),
@@ -462,12 +936,125 @@ macro_rules! declare_bitpacker_simple {
                 accumulator = op_or(accumulator, delta);
                 most_significant_bit(or_collapse_to_u32(accumulator))
             }
+
+            unsafe fn num_bits_sorted_checked(
+                initial: u32,
+                decompressed: &[u32],
+            ) -> Result<u8, CompressError> {
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+                let mut previous = set1(initial as i32);
+                let mut accumulator = set1(0);
+                let mut violations = set1(0);
+                for i in 0..32 {
+                    let current = load_unaligned(data.add(i));
+                    let delta = compute_delta(current, previous);
+                    accumulator = op_or(accumulator, delta);
+                    // The delta wraps around, and exceeds the current integer, iff the
+                    // current integer is lower than the previous one.
+                    violations = op_or(violations, op_gt_u32(delta, current));
+                    previous = current;
+                }
+                if or_collapse_to_u32(violations) != 0 {
+                    return Err(unsorted_error(Some(initial), false, decompressed));
+                }
+                Ok(most_significant_bit(or_collapse_to_u32(accumulator)))
+            }
+
+            unsafe fn num_bits_strictly_sorted_checked(
+                initial: Option<u32>,
+                decompressed: &[u32],
+            ) -> Result<u8, CompressError> {
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+                let one = set1(1);
+                let mut previous = set1(initial.unwrap_or(u32::MAX) as i32);
+                let mut accumulator = set1(0);
+                // The first integer is unconstrained without an initial value: this sets
+                // its lane only.
+                let mut first_lane = if initial.is_none() {
+                    compute_delta(set1(0), one)
+                } else {
+                    set1(0)
+                };
+                // Lanes stay all ones as long as the integers are strictly increasing.
+                let mut valid = set1(-1);
+                for i in 0..32 {
+                    let current = load_unaligned(data.add(i));
+                    let delta = sub(compute_delta(current, previous), one);
+                    accumulator = op_or(accumulator, delta);
+                    valid = op_and(valid, op_or(op_gt_u32(current, delta), first_lane));
+                    first_lane = set1(0);
+                    previous = current;
+                }
+                if or_collapse_to_u32(add(valid, one)) != 0 {
+                    return Err(unsorted_error(initial, true, decompressed));
+                }
+                Ok(most_significant_bit(or_collapse_to_u32(accumulator)))
+            }
+
+            unsafe fn num_bits_all_modes(initial: u32, decompressed: &[u32]) -> BlockWidths {
+                assert_eq!(
+                    decompressed.len(),
+                    BLOCK_LEN,
+                    "`decompressed`'s len is not `BLOCK_LEN={}`",
+                    BLOCK_LEN
+                );
+                let data: *const DataType = decompressed.as_ptr().cast::<DataType>();
+                let one = set1(1);
+                let mut previous = set1(initial as i32);
+                let mut plain = set1(0);
+                let mut sorted = set1(0);
+                let mut strictly_sorted = set1(0);
+                let mut min = set1(-1);
+                let mut max = set1(0);
+                for i in 0..32 {
+                    let current = load_unaligned(data.add(i));
+                    let delta = compute_delta(current, previous);
+                    plain = op_or(plain, current);
+                    sorted = op_or(sorted, delta);
+                    strictly_sorted = op_or(strictly_sorted, sub(delta, one));
+                    // Branchless unsigned min and max: the difference is only applied
+                    // to the lanes where the comparison holds.
+                    min = sub(min, op_and(sub(min, current), op_gt_u32(min, current)));
+                    max = add(max, op_and(sub(current, max), op_gt_u32(current, max)));
+                    previous = current;
+                }
+                let mut mins = [0u32; BLOCK_LEN / 32];
+                let mut maxs = [0u32; BLOCK_LEN / 32];
+                store_unaligned(mins.as_mut_ptr().cast::<DataType>(), min);
+                store_unaligned(maxs.as_mut_ptr().cast::<DataType>(), max);
+                let min = mins.iter().copied().min().unwrap_or(0);
+                let max = maxs.iter().copied().max().unwrap_or(0);
+                BlockWidths {
+                    plain: most_significant_bit(or_collapse_to_u32(plain)),
+                    sorted: most_significant_bit(or_collapse_to_u32(sorted)),
+                    strictly_sorted: most_significant_bit(or_collapse_to_u32(strictly_sorted)),
+                    frame_of_reference: most_significant_bit(max - min),
+                    min,
+                }
+            }
         }
 
         #[cfg(test)]
         mod tests {
             use super::UnsafeBitPackerImpl;
-            use crate::tests::{test_suite_compress_decompress, DeltaKind};
+            use crate::tests::{
+                test_suite_compress_decompress, test_util_compress_auto, test_util_compress_for,
+                test_util_compress_masked, test_util_decompress_scaled, test_util_decompress_to_sink,
+                test_util_decompress_u64, test_util_narrow, test_util_num_bits_all_modes,
+                test_util_num_bits_sorted_checked, DeltaKind,
+            };
             use crate::UnsafeBitPacker;
 
             #[test]
@@ -500,6 +1087,51 @@ macro_rules! declare_bitpacker_simple {
             fn test_bitpacker_strict_delta() {
                 test_suite_compress_decompress::<UnsafeBitPackerImpl>(DeltaKind::StrictDelta);
             }
+
+            #[test]
+            fn test_bitpacker_scaled() {
+                test_util_decompress_scaled::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_bitpacker_for() {
+                test_util_compress_for::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_bitpacker_masked() {
+                test_util_compress_masked::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_num_bits_sorted_checked() {
+                test_util_num_bits_sorted_checked::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_compress_auto() {
+                test_util_compress_auto::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_decompress_to_sink() {
+                test_util_decompress_to_sink::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_narrow() {
+                test_util_narrow::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_decompress_u64() {
+                test_util_decompress_u64::<UnsafeBitPackerImpl>();
+            }
+
+            #[test]
+            fn test_num_bits_all_modes() {
+                test_util_num_bits_all_modes::<UnsafeBitPackerImpl>();
+            }
         }
     };
 }
@@ -0,0 +1,398 @@
+//! Parquet RLE/bit-packing hybrid encoding.
+//!
+//! Parquet encodes repetition and definition levels, dictionary indices and booleans
+//! as a sequence of runs, each prefixed by a ULEB128 varint header:
+//!
+//! - `header = count << 1`: a RLE run. `count` repetitions of a single value, stored
+//!   on `ceil(bit_width / 8)` little-endian bytes.
+//! - `header = num_groups << 1 | 1`: a bit-packed run of `num_groups * 8` values, packed
+//!   LSB-first over `num_groups * bit_width` bytes.
+//!
+//! Bit-packed runs use the same layout as `BitPacker1x`: the 32 values of a
+//! `BitPacker1x` block are exactly 4 groups of a bit-packed run. Runs are therefore
+//! packed and unpacked 32 values at a time with the `BitPacker1x` kernels, and only
+//! their last, partial, block goes through a scratch buffer.
+//!
+//! The bit width and the number of values are not part of the encoding: Parquet stores
+//! them in the page or column metadata.
+
+use crate::{most_significant_bit, BitPacker, BitPacker1x};
+
+/// Number of values in a group of a bit-packed run.
+const GROUP_LEN: usize = 8;
+
+/// Minimum length of a run for it to be RLE-encoded.
+const MIN_RLE_RUN_LEN: usize = 8;
+
+/// Maximum number of bytes of a ULEB128-encoded `u32`.
+const MAX_VARINT_LEN: usize = 5;
+
+/// Encoder and decoder for the Parquet RLE/bit-packing hybrid encoding.
+///
+/// ```
+/// use bitpacking::RleBitPackingHybrid;
+///
+/// // Definition levels of a nullable column: mostly defined, with a few nulls.
+/// let mut levels = vec![1u32; 100];
+/// levels[3] = 0;
+/// levels[42] = 0;
+///
+/// let hybrid = RleBitPackingHybrid::new();
+/// let mut encoded = vec![0u8; RleBitPackingHybrid::max_compressed_len(levels.len(), 1)];
+/// let encoded_len = hybrid.encode(&levels, 1, &mut encoded);
+///
+/// let mut decoded = vec![0u32; levels.len()];
+/// assert_eq!(hybrid.decode(&encoded[..encoded_len], 1, &mut decoded), encoded_len);
+/// assert_eq!(levels, decoded);
+/// ```
+#[derive(Clone, Copy)]
+pub struct RleBitPackingHybrid {
+    bitpacker: BitPacker1x,
+}
+
+impl Default for RleBitPackingHybrid {
+    fn default() -> Self {
+        RleBitPackingHybrid::new()
+    }
+}
+
+fn write_varint(mut val: u32, output: &mut [u8]) -> usize {
+    let mut len = 0;
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        if val == 0 {
+            output[len] = byte;
+            return len + 1;
+        }
+        output[len] = byte | 0x80;
+        len += 1;
+    }
+}
+
+fn read_varint(input: &[u8]) -> (u32, usize) {
+    let mut val = 0u32;
+    for (i, &byte) in input.iter().take(MAX_VARINT_LEN).enumerate() {
+        val |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return (val, i + 1);
+        }
+    }
+    panic!("Invalid run header");
+}
+
+fn num_value_bytes(bit_width: u8) -> usize {
+    (bit_width as usize).div_ceil(8)
+}
+
+impl RleBitPackingHybrid {
+    /// Creates a new encoder/decoder, relying on the best available implementation of
+    /// `BitPacker1x`.
+    pub fn new() -> Self {
+        RleBitPackingHybrid {
+            bitpacker: BitPacker1x::new(),
+        }
+    }
+
+    /// Returns an upper bound of the number of bytes required to encode `len`
+    /// values of `bit_width` bits.
+    #[must_use]
+    pub fn max_compressed_len(len: usize, bit_width: u8) -> usize {
+        (len.div_ceil(GROUP_LEN) + 1) * (bit_width as usize + MAX_VARINT_LEN + 4)
+    }
+
+    /// Encodes `values` into `output`.
+    ///
+    /// Runs of at least 8 repetitions of a value are RLE-encoded, and the values in
+    /// between are bit-packed. The last bit-packed run is padded with zeros to a multiple
+    /// of 8 values, as allowed by the specification.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `bit_width` is > 32, or if one of the values does not fit in
+    ///   `bit_width` bits.
+    /// - Panics if `output` is too small. A buffer of
+    ///   `max_compressed_len(values.len(), bit_width)` bytes is always large enough.
+    pub fn encode(&self, values: &[u32], bit_width: u8, output: &mut [u8]) -> usize {
+        assert!(
+            bit_width <= 32,
+            "Num bits must be <= 32. Was {}.",
+            bit_width
+        );
+        let actual_bit_width =
+            most_significant_bit(values.iter().fold(0u32, |acc, &val| acc | val));
+        assert!(
+            actual_bit_width <= bit_width,
+            "Values do not fit in {} bits",
+            bit_width
+        );
+        let mut offset = 0;
+        let mut literals_start = 0;
+        let mut run_start = 0;
+        while run_start < values.len() {
+            let val = values[run_start];
+            let run_end = values[run_start..]
+                .iter()
+                .position(|&other| other != val)
+                .map_or(values.len(), |len| run_start + len);
+            // Bit-packed runs must contain a multiple of 8 values: the first values of
+            // the run may have to complete the pending literals.
+            let num_pending = run_start - literals_start;
+            let rle_start = run_start + (GROUP_LEN - num_pending % GROUP_LEN) % GROUP_LEN;
+            if run_end >= rle_start + MIN_RLE_RUN_LEN {
+                offset += self.write_bit_packed_run(
+                    &values[literals_start..rle_start],
+                    bit_width,
+                    &mut output[offset..],
+                );
+                offset += write_rle_run(val, run_end - rle_start, bit_width, &mut output[offset..]);
+                literals_start = run_end;
+            }
+            run_start = run_end;
+        }
+        offset +=
+            self.write_bit_packed_run(&values[literals_start..], bit_width, &mut output[offset..]);
+        offset
+    }
+
+    /// Bit-packs `values`, padding them with zeros to a multiple of 8 values.
+    fn write_bit_packed_run(&self, values: &[u32], bit_width: u8, output: &mut [u8]) -> usize {
+        if values.is_empty() {
+            return 0;
+        }
+        let num_groups = values.len().div_ceil(GROUP_LEN);
+        let mut offset = write_varint(((num_groups as u32) << 1) | 1, output);
+        let mut chunks = values.chunks_exact(BitPacker1x::BLOCK_LEN);
+        for chunk in chunks.by_ref() {
+            offset += self
+                .bitpacker
+                .compress(chunk, &mut output[offset..], bit_width);
+        }
+        let remainder = chunks.remainder();
+        if !remainder.is_empty() {
+            let mut block = [0u32; BitPacker1x::BLOCK_LEN];
+            block[..remainder.len()].copy_from_slice(remainder);
+            let mut packed = [0u8; 4 * BitPacker1x::BLOCK_LEN];
+            self.bitpacker.compress(&block, &mut packed, bit_width);
+            let num_bytes = remainder.len().div_ceil(GROUP_LEN) * bit_width as usize;
+            output[offset..offset + num_bytes].copy_from_slice(&packed[..num_bytes]);
+            offset += num_bytes;
+        }
+        offset
+    }
+
+    /// Decodes `output.len()` values of `bit_width` bits from `input`.
+    ///
+    /// The values of the last run exceeding `output.len()`, if any, are skipped.
+    ///
+    /// Returns the number of bytes consumed.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `bit_width` is > 32.
+    /// - Panics if `input` is too short, or if a run header is invalid.
+    pub fn decode(&self, input: &[u8], bit_width: u8, output: &mut [u32]) -> usize {
+        assert!(
+            bit_width <= 32,
+            "Num bits must be <= 32. Was {}.",
+            bit_width
+        );
+        let mut offset = 0;
+        let mut cursor = 0;
+        while cursor < output.len() {
+            let (header, header_len) = read_varint(&input[offset..]);
+            offset += header_len;
+            let remaining = &mut output[cursor..];
+            if header & 1 == 0 {
+                let count = (header >> 1) as usize;
+                let num_bytes = num_value_bytes(bit_width);
+                assert!(
+                    input.len() >= offset + num_bytes,
+                    "Compressed array seems too small. ({} < {}) ",
+                    input.len(),
+                    offset + num_bytes
+                );
+                let mut val_bytes = [0u8; 4];
+                val_bytes[..num_bytes].copy_from_slice(&input[offset..offset + num_bytes]);
+                offset += num_bytes;
+                let len = count.min(remaining.len());
+                remaining[..len].fill(u32::from_le_bytes(val_bytes));
+                cursor += len;
+            } else {
+                let num_groups = (header >> 1) as usize;
+                let num_bytes = num_groups * bit_width as usize;
+                assert!(
+                    input.len() >= offset + num_bytes,
+                    "Compressed array seems too small. ({} < {}) ",
+                    input.len(),
+                    offset + num_bytes
+                );
+                let len = (num_groups * GROUP_LEN).min(remaining.len());
+                self.read_bit_packed_run(
+                    &input[offset..offset + num_bytes],
+                    bit_width,
+                    &mut remaining[..len],
+                );
+                offset += num_bytes;
+                cursor += len;
+            }
+        }
+        offset
+    }
+
+    /// Unpacks the first `output.len()` values of a bit-packed run.
+    fn read_bit_packed_run(&self, input: &[u8], bit_width: u8, output: &mut [u32]) {
+        let block_num_bytes = BitPacker1x::compressed_block_size(bit_width);
+        let mut offset = 0;
+        let mut chunks = output.chunks_exact_mut(BitPacker1x::BLOCK_LEN);
+        for chunk in chunks.by_ref() {
+            offset += self
+                .bitpacker
+                .decompress(&input[offset..], chunk, bit_width);
+        }
+        let remainder = chunks.into_remainder();
+        if !remainder.is_empty() {
+            let mut packed = [0u8; 4 * BitPacker1x::BLOCK_LEN];
+            let num_bytes = (input.len() - offset).min(block_num_bytes);
+            packed[..num_bytes].copy_from_slice(&input[offset..offset + num_bytes]);
+            let mut block = [0u32; BitPacker1x::BLOCK_LEN];
+            self.bitpacker.decompress(&packed, &mut block, bit_width);
+            remainder.copy_from_slice(&block[..remainder.len()]);
+        }
+    }
+}
+
+fn write_rle_run(val: u32, count: usize, bit_width: u8, output: &mut [u8]) -> usize {
+    let offset = write_varint((count as u32) << 1, output);
+    let num_bytes = num_value_bytes(bit_width);
+    output[offset..offset + num_bytes].copy_from_slice(&val.to_le_bytes()[..num_bytes]);
+    offset + num_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_varint, write_varint, RleBitPackingHybrid};
+    use crate::tests::generate_array;
+
+    fn encode(values: &[u32], bit_width: u8) -> Vec<u8> {
+        let hybrid = RleBitPackingHybrid::new();
+        let mut encoded =
+            vec![0u8; RleBitPackingHybrid::max_compressed_len(values.len(), bit_width)];
+        let encoded_len = hybrid.encode(values, bit_width, &mut encoded);
+        encoded.truncate(encoded_len);
+        encoded
+    }
+
+    fn decode(encoded: &[u8], bit_width: u8, len: usize) -> Vec<u32> {
+        let mut decoded = vec![u32::MAX; len];
+        let read_len = RleBitPackingHybrid::new().decode(encoded, bit_width, &mut decoded);
+        assert_eq!(read_len, encoded.len());
+        decoded
+    }
+
+    fn test_util_roundtrip(values: &[u32], bit_width: u8) {
+        let encoded = encode(values, bit_width);
+        assert_eq!(decode(&encoded, bit_width, values.len()), values);
+    }
+
+    #[test]
+    fn test_varint() {
+        let mut buffer = [0u8; 5];
+        for val in [0u32, 1, 127, 128, 300, 1 << 21, u32::MAX] {
+            let len = write_varint(val, &mut buffer);
+            assert_eq!(read_varint(&buffer[..len]), (val, len));
+        }
+        assert_eq!(write_varint(300, &mut buffer), 2);
+        assert_eq!(&buffer[..2], &[0xac, 0x02]);
+    }
+
+    // Example of the Parquet specification: 0..8 bit-packed over 3 bits.
+    #[test]
+    fn test_spec_bit_packed() {
+        let values: Vec<u32> = (0..8).collect();
+        let expected = [0x03, 0b1000_1000, 0b1100_0110, 0b1111_1010];
+        assert_eq!(encode(&values, 3), expected);
+        assert_eq!(decode(&expected, 3, 8), values);
+    }
+
+    #[test]
+    fn test_spec_rle() {
+        // 100 times 4, over 3 bits.
+        let expected = [0xc8, 0x01, 0x04];
+        assert_eq!(encode(&[4; 100], 3), expected);
+        assert_eq!(decode(&expected, 3, 100), vec![4; 100]);
+        // 50 zeros followed by 50 ones, over 1 bit.
+        let mut values = vec![0u32; 50];
+        values.resize(100, 1);
+        let expected = [0x64, 0x00, 0x64, 0x01];
+        assert_eq!(encode(&values, 1), expected);
+        assert_eq!(decode(&expected, 1, 100), values);
+        // Values span several bytes.
+        let expected = [0x10, 0xff, 0xff, 0x01];
+        assert_eq!(encode(&[0x1ffff; 8], 17), expected);
+    }
+
+    #[test]
+    fn test_spec_mixed() {
+        // A bit-packed run, followed by a RLE run of zeros.
+        let mut values: Vec<u32> = (0..8).collect();
+        values.resize(58, 0);
+        let expected = [0x03, 0x88, 0xc6, 0xfa, 0x64, 0x00];
+        assert_eq!(encode(&values, 3), expected);
+        assert_eq!(decode(&expected, 3, 58), values);
+    }
+
+    #[test]
+    fn test_padded_last_run() {
+        // 3 values, padded to a group of 8.
+        let expected = [0x03, 0b1101_0001, 0x00, 0x00];
+        assert_eq!(encode(&[1, 2, 3], 3), expected);
+        assert_eq!(decode(&expected, 3, 3), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_decode_long_rle_header() {
+        // Writers are free to use non-minimal varints.
+        let encoded = [0x90, 0x80, 0x00, 0x07];
+        assert_eq!(decode(&encoded, 3, 8), vec![7; 8]);
+    }
+
+    #[test]
+    fn test_run_alignment() {
+        // The run of 5s has to complete the 3 pending literals before being RLE-encoded.
+        let mut values = vec![1u32, 2, 3];
+        values.resize(3 + 20, 5);
+        values.push(6);
+        let encoded = encode(&values, 3);
+        // 1 group of literals, a RLE run of 15, 1 padded group of literals.
+        assert_eq!(encoded.len(), (1 + 3) + (1 + 1) + (1 + 3));
+        assert_eq!(decode(&encoded, 3, values.len()), values);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for bit_width in 0..=32 {
+            for len in [0, 1, 7, 8, 31, 32, 33, 100, 1_000] {
+                let values = generate_array(len, bit_width);
+                test_util_roundtrip(&values, bit_width);
+                let runs: Vec<u32> = values.iter().map(|&val| values[0] & val).collect();
+                test_util_roundtrip(&runs, bit_width);
+                let mut alternating = values.clone();
+                for (i, val) in alternating.iter_mut().enumerate() {
+                    if (i / 13) % 2 == 0 {
+                        *val = values[0];
+                    }
+                }
+                test_util_roundtrip(&alternating, bit_width);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Values do not fit in 3 bits")]
+    fn test_value_too_large() {
+        encode(&[8], 3);
+    }
+}
@@ -0,0 +1,474 @@
+//! Simple-8b and Simple16 word-aligned codecs.
+//!
+//! These codecs pack as many integers as possible in a single machine word, whose
+//! 4 most significant bits (the selector) describe how the remaining bits are split.
+//! They shine on streams dominated by tiny integers with occasional large outliers,
+//! like term frequencies, where a single outlier would ruin the width of a whole
+//! bitpacked block.
+//!
+//! - `Simple8b` works over `u64` words with 60 bits of payload. All the integers of
+//!   a word share the same width. It can encode any `u32`.
+//! - `Simple16` works over `u32` words with 28 bits of payload, and some of its
+//!   selectors mix two widths. It can only encode integers lower than `2^28`.
+//!
+//! Words are serialized in little-endian, and integers are packed starting from the
+//! least significant bit of the payload. Like `StreamVByte`, the number of integers
+//! is not stored: it is the length of the output slice passed to the decoder.
+
+/// Describes how the payload of a word is split, as `(count, num_bits)` runs.
+type Layout = &'static [(usize, u8)];
+
+trait SimpleScheme {
+    /// Number of bytes of a word.
+    const WORD_LEN: usize;
+    /// Number of bits of payload of a word.
+    const PAYLOAD_BITS: u32;
+    /// Layout of the payload, indexed by selector.
+    const LAYOUTS: [Layout; 16];
+}
+
+struct Simple8bScheme;
+
+impl SimpleScheme for Simple8bScheme {
+    const WORD_LEN: usize = 8;
+    const PAYLOAD_BITS: u32 = 60;
+    const LAYOUTS: [Layout; 16] = [
+        &[(240, 0)],
+        &[(120, 0)],
+        &[(60, 1)],
+        &[(30, 2)],
+        &[(20, 3)],
+        &[(15, 4)],
+        &[(12, 5)],
+        &[(10, 6)],
+        &[(8, 7)],
+        &[(7, 8)],
+        &[(6, 10)],
+        &[(5, 12)],
+        &[(4, 15)],
+        &[(3, 20)],
+        &[(2, 30)],
+        &[(1, 60)],
+    ];
+}
+
+struct Simple16Scheme;
+
+impl SimpleScheme for Simple16Scheme {
+    const WORD_LEN: usize = 4;
+    const PAYLOAD_BITS: u32 = 28;
+    const LAYOUTS: [Layout; 16] = [
+        &[(28, 1)],
+        &[(7, 2), (14, 1)],
+        &[(7, 1), (7, 2), (7, 1)],
+        &[(14, 1), (7, 2)],
+        &[(14, 2)],
+        &[(1, 4), (8, 3)],
+        &[(1, 3), (4, 4), (3, 3)],
+        &[(7, 4)],
+        &[(4, 5), (2, 4)],
+        &[(2, 4), (4, 5)],
+        &[(3, 6), (2, 5)],
+        &[(2, 5), (3, 6)],
+        &[(4, 7)],
+        &[(1, 10), (2, 9)],
+        &[(2, 14)],
+        &[(1, 28)],
+    ];
+}
+
+fn layout_len(layout: Layout) -> usize {
+    layout.iter().map(|&(count, _)| count).sum()
+}
+
+/// Returns `true` if the integers fit the layout. `vals` must contain exactly
+/// `layout_len(layout)` integers.
+fn fits(layout: Layout, vals: &[u32]) -> bool {
+    let mut vals = vals.iter();
+    layout.iter().all(|&(count, num_bits)| {
+        vals.by_ref()
+            .take(count)
+            .all(|&val| num_bits >= 32 || val >> num_bits == 0)
+    })
+}
+
+fn write_word<TScheme: SimpleScheme>(word: u64, output: &mut [u8]) {
+    output[..TScheme::WORD_LEN].copy_from_slice(&word.to_le_bytes()[..TScheme::WORD_LEN]);
+}
+
+fn read_word<TScheme: SimpleScheme>(input: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes[..TScheme::WORD_LEN].copy_from_slice(&input[..TScheme::WORD_LEN]);
+    u64::from_le_bytes(bytes)
+}
+
+fn encode_generic<TScheme: SimpleScheme>(vals: &[u32], compressed: &mut [u8]) -> usize {
+    let mut offset = 0;
+    let mut remaining = vals;
+    while !remaining.is_empty() {
+        let (selector, layout) = TScheme::LAYOUTS
+            .iter()
+            .enumerate()
+            .find(|(_, layout)| {
+                let len = layout_len(layout);
+                len <= remaining.len() && fits(layout, &remaining[..len])
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Integer {} does not fit in {} bits",
+                    remaining[0],
+                    TScheme::PAYLOAD_BITS
+                )
+            });
+        let mut word = (selector as u64) << TScheme::PAYLOAD_BITS;
+        let mut shift = 0;
+        let mut vals = remaining.iter();
+        for &(count, num_bits) in layout.iter() {
+            for &val in vals.by_ref().take(count) {
+                word |= (val as u64) << shift;
+                shift += num_bits as u32;
+            }
+        }
+        assert!(
+            compressed.len() >= offset + TScheme::WORD_LEN,
+            "Output array too small. {} < {}",
+            compressed.len(),
+            offset + TScheme::WORD_LEN
+        );
+        write_word::<TScheme>(word, &mut compressed[offset..]);
+        offset += TScheme::WORD_LEN;
+        remaining = &remaining[layout_len(layout)..];
+    }
+    offset
+}
+
+fn decode_generic<TScheme: SimpleScheme>(
+    compressed: &[u8],
+    decompressed: &mut [u32],
+    mut previous: Option<u32>,
+) -> usize {
+    let mut offset = 0;
+    let mut cursor = 0;
+    while cursor < decompressed.len() {
+        assert!(
+            compressed.len() >= offset + TScheme::WORD_LEN,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            offset + TScheme::WORD_LEN
+        );
+        let word = read_word::<TScheme>(&compressed[offset..]);
+        offset += TScheme::WORD_LEN;
+        let layout = TScheme::LAYOUTS[(word >> TScheme::PAYLOAD_BITS) as usize];
+        let mut shift = 0;
+        'word: for &(count, num_bits) in layout.iter() {
+            let mask = (1u64 << num_bits) - 1;
+            for _ in 0..count {
+                if cursor == decompressed.len() {
+                    break 'word;
+                }
+                let val = ((word >> shift) & mask) as u32;
+                shift += num_bits as u32;
+                decompressed[cursor] = match previous.as_mut() {
+                    Some(previous) => {
+                        *previous = previous.wrapping_add(val);
+                        *previous
+                    }
+                    None => val,
+                };
+                cursor += 1;
+            }
+        }
+    }
+    offset
+}
+
+fn compute_deltas(initial: u32, decompressed: &[u32]) -> Vec<u32> {
+    let mut previous = initial;
+    decompressed
+        .iter()
+        .map(|&val| {
+            let delta = val.wrapping_sub(previous);
+            previous = val;
+            delta
+        })
+        .collect()
+}
+
+macro_rules! declare_simple_codec {
+    ($name:ident, $scheme:ty, $word:literal) => {
+        impl $name {
+            /// Returns the maximum number of bytes required to encode `len` integers.
+            #[must_use]
+            pub fn max_compressed_len(len: usize) -> usize {
+                len * <$scheme as SimpleScheme>::WORD_LEN
+            }
+
+            /// Encodes `decompressed` into `compressed`.
+            ///
+            /// Returns the amount of bytes written, a multiple of the size of a word.
+            ///
+            /// # Panics
+            ///
+            #[doc = concat!("- Panics if an integer does not fit in the payload of a `", $word, "` word.")]
+            /// - Panics if `compressed` is too small. A buffer of `max_compressed_len(decompressed.len())`
+            ///   bytes is always large enough.
+            pub fn encode(&self, decompressed: &[u32], compressed: &mut [u8]) -> usize {
+                encode_generic::<$scheme>(decompressed, compressed)
+            }
+
+            /// Delta-encodes the sorted array `decompressed` into `compressed`.
+            ///
+            /// `initial` is used to compute the first delta.
+            ///
+            /// Returns the amount of bytes written, a multiple of the size of a word.
+            ///
+            /// # Panics
+            ///
+            #[doc = concat!("- Panics if a delta does not fit in the payload of a `", $word, "` word.")]
+            /// - Panics if `compressed` is too small. A buffer of `max_compressed_len(decompressed.len())`
+            ///   bytes is always large enough.
+            pub fn encode_sorted(
+                &self,
+                initial: u32,
+                decompressed: &[u32],
+                compressed: &mut [u8],
+            ) -> usize {
+                encode_generic::<$scheme>(&compute_deltas(initial, decompressed), compressed)
+            }
+
+            /// Decodes `decompressed.len()` integers from `compressed`.
+            ///
+            /// Returns the amount of bytes consumed.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `compressed` is too short.
+            pub fn decode(&self, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+                decode_generic::<$scheme>(compressed, decompressed, None)
+            }
+
+            /// Decodes `decompressed.len()` integers that were encoded with `encode_sorted`.
+            ///
+            /// `initial` must be the value that was passed when encoding.
+            ///
+            /// Returns the amount of bytes consumed.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `compressed` is too short.
+            pub fn decode_sorted(
+                &self,
+                initial: u32,
+                compressed: &[u8],
+                decompressed: &mut [u32],
+            ) -> usize {
+                decode_generic::<$scheme>(compressed, decompressed, Some(initial))
+            }
+        }
+    };
+}
+
+/// Simple-8b codec, packing integers in `u64` words.
+///
+/// ```
+/// use bitpacking::Simple8b;
+///
+/// let term_freqs: Vec<u32> = vec![1, 1, 2, 1, 1, 1, 3, 1, 1, 1, 1, 1, 1, 1, 310, 1, 1];
+///
+/// let mut compressed = vec![0u8; Simple8b::max_compressed_len(term_freqs.len())];
+/// let compressed_len = Simple8b.encode(&term_freqs, &mut compressed);
+/// assert_eq!(compressed_len, 2 * 8);
+///
+/// let mut decompressed = vec![0u32; term_freqs.len()];
+/// Simple8b.decode(&compressed[..compressed_len], &mut decompressed);
+/// assert_eq!(term_freqs, decompressed);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct Simple8b;
+
+/// Simple16 codec, packing integers lower than `2^28` in `u32` words.
+///
+/// ```
+/// use bitpacking::Simple16;
+///
+/// let term_freqs: Vec<u32> = vec![1, 1, 2, 1, 1, 1, 3, 1, 1, 1, 1, 1, 1, 1, 310, 1, 1];
+///
+/// let mut compressed = vec![0u8; Simple16::max_compressed_len(term_freqs.len())];
+/// let compressed_len = Simple16.encode(&term_freqs, &mut compressed);
+///
+/// let mut decompressed = vec![0u32; term_freqs.len()];
+/// Simple16.decode(&compressed[..compressed_len], &mut decompressed);
+/// assert_eq!(term_freqs, decompressed);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct Simple16;
+
+declare_simple_codec!(Simple8b, Simple8bScheme, "u64");
+declare_simple_codec!(Simple16, Simple16Scheme, "u32");
+
+#[cfg(test)]
+mod tests {
+    use super::{layout_len, Simple16, Simple16Scheme, Simple8b, Simple8bScheme, SimpleScheme};
+    #[cfg(feature = "bitpacker1x")]
+    use crate::{BitPacker, BitPacker1x};
+    use proptest::prelude::*;
+
+    fn check_layouts<TScheme: SimpleScheme>() {
+        for layout in TScheme::LAYOUTS {
+            let num_bits: u32 = layout
+                .iter()
+                .map(|&(count, num_bits)| count as u32 * num_bits as u32)
+                .sum();
+            assert!(num_bits <= TScheme::PAYLOAD_BITS);
+            assert!(layout_len(layout) > 0);
+        }
+    }
+
+    #[test]
+    fn test_layouts() {
+        check_layouts::<Simple8bScheme>();
+        check_layouts::<Simple16Scheme>();
+    }
+
+    #[test]
+    fn test_simple8b_runs_of_zeros() {
+        let values = vec![0u32; 1_000];
+        let mut compressed = vec![0u8; Simple8b::max_compressed_len(values.len())];
+        let compressed_len = Simple8b.encode(&values, &mut compressed);
+        // 4 words of 240 integers, and a word of 30 2-bits zeros plus one of 10 6-bits zeros.
+        assert_eq!(compressed_len, 6 * 8);
+        let mut decompressed = vec![1u32; values.len()];
+        assert_eq!(
+            Simple8b.decode(&compressed[..compressed_len], &mut decompressed),
+            compressed_len
+        );
+        assert_eq!(values, decompressed);
+    }
+
+    #[test]
+    fn test_simple8b_large_values() {
+        let values = vec![u32::MAX, 0, u32::MAX];
+        let mut compressed = vec![0u8; Simple8b::max_compressed_len(values.len())];
+        let compressed_len = Simple8b.encode(&values, &mut compressed);
+        let mut decompressed = vec![0u32; values.len()];
+        Simple8b.decode(&compressed[..compressed_len], &mut decompressed);
+        assert_eq!(values, decompressed);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit in 28 bits")]
+    fn test_simple16_too_large() {
+        let mut compressed = vec![0u8; Simple16::max_compressed_len(1)];
+        Simple16.encode(&[1 << 28], &mut compressed);
+    }
+
+    /// Checks the codec against a `BitPacker1x` roundtrip of the same blocks.
+    #[cfg(feature = "bitpacker1x")]
+    fn check_against_bitpacker1x(
+        encode: impl Fn(Option<u32>, &[u32], &mut [u8]) -> usize,
+        decode: impl Fn(Option<u32>, &[u8], &mut [u32]) -> usize,
+        values: &[u32],
+        initial: Option<u32>,
+    ) -> Result<(), TestCaseError> {
+        let bitpacker = BitPacker1x::new();
+        let mut expected = vec![0u32; values.len()];
+        let mut previous = initial.unwrap_or(0);
+        for (block, expected_block) in values
+            .chunks(BitPacker1x::BLOCK_LEN)
+            .zip(expected.chunks_mut(BitPacker1x::BLOCK_LEN))
+        {
+            let mut packed = [0u8; 4 * BitPacker1x::BLOCK_LEN];
+            if initial.is_some() {
+                let num_bits = bitpacker.num_bits_sorted(previous, block);
+                bitpacker.compress_sorted(previous, block, &mut packed, num_bits);
+                bitpacker.decompress_sorted(previous, &packed, expected_block, num_bits);
+                previous = expected_block[BitPacker1x::BLOCK_LEN - 1];
+            } else {
+                let num_bits = bitpacker.num_bits(block);
+                bitpacker.compress(block, &mut packed, num_bits);
+                bitpacker.decompress(&packed, expected_block, num_bits);
+            }
+        }
+        let mut compressed = vec![0u8; 8 * values.len()];
+        let compressed_len = encode(initial, values, &mut compressed);
+        let mut decompressed = vec![0u32; values.len()];
+        let read_len = decode(initial, &compressed[..compressed_len], &mut decompressed);
+        prop_assert_eq!(read_len, compressed_len);
+        prop_assert_eq!(&decompressed, &expected);
+        prop_assert_eq!(&decompressed[..], values);
+        Ok(())
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    fn blocks(max_value: u32) -> impl Strategy<Value = Vec<u32>> {
+        (1usize..8).prop_flat_map(move |num_blocks| {
+            prop::collection::vec(
+                prop_oneof![9 => 0u32..4, 1 => 0..=max_value],
+                num_blocks * BitPacker1x::BLOCK_LEN,
+            )
+        })
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn proptest_simple8b(values in blocks(u32::MAX)) {
+            check_against_bitpacker1x(
+                |_, vals, out| Simple8b.encode(vals, out),
+                |_, input, out| Simple8b.decode(input, out),
+                &values,
+                None,
+            )?;
+        }
+
+        #[test]
+        fn proptest_simple8b_sorted(mut values in blocks(1 << 24), initial in 0u32..1_000) {
+            values.iter_mut().fold(initial, |acc, val| { *val += acc; *val });
+            check_against_bitpacker1x(
+                |initial, vals, out| Simple8b.encode_sorted(initial.unwrap(), vals, out),
+                |initial, input, out| Simple8b.decode_sorted(initial.unwrap(), input, out),
+                &values,
+                Some(initial),
+            )?;
+        }
+
+        #[test]
+        fn proptest_simple16(values in blocks((1 << 28) - 1)) {
+            check_against_bitpacker1x(
+                |_, vals, out| Simple16.encode(vals, out),
+                |_, input, out| Simple16.decode(input, out),
+                &values,
+                None,
+            )?;
+        }
+
+        #[test]
+        fn proptest_simple16_sorted(mut values in blocks(1 << 20), initial in 0u32..1_000) {
+            values.iter_mut().fold(initial, |acc, val| { *val += acc; *val });
+            check_against_bitpacker1x(
+                |initial, vals, out| Simple16.encode_sorted(initial.unwrap(), vals, out),
+                |initial, input, out| Simple16.decode_sorted(initial.unwrap(), input, out),
+                &values,
+                Some(initial),
+            )?;
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn proptest_simple_arbitrary_len(values in prop::collection::vec(0u32..(1 << 28), 0..300)) {
+            let mut compressed = vec![0u8; Simple8b::max_compressed_len(values.len())];
+            let mut decompressed = vec![0u32; values.len()];
+            let compressed_len = Simple8b.encode(&values, &mut compressed);
+            prop_assert_eq!(Simple8b.decode(&compressed[..compressed_len], &mut decompressed), compressed_len);
+            prop_assert_eq!(&decompressed, &values);
+            let compressed_len = Simple16.encode(&values, &mut compressed);
+            prop_assert_eq!(Simple16.decode(&compressed[..compressed_len], &mut decompressed), compressed_len);
+            prop_assert_eq!(&decompressed, &values);
+        }
+    }
+}
@@ -0,0 +1,445 @@
+//! Streaming compression over `std::io`.
+//!
+//! `BitPackWriter` buffers the integers it receives into blocks, and writes each block
+//! to the underlying `Write` as soon as it is complete, with the smallest number of
+//! bits that fits it. `BitPackReader` decodes such a stream from a `Read`.
+//!
+//! The stream is a sequence of full blocks, terminated by a single final block that
+//! holds the remaining `len < BLOCK_LEN` integers, padded with zeros:
+//!
+//! | bytes         | full block                  | final block                        |
+//! |:--------------|:----------------------------|:-----------------------------------|
+//! | `[0..1)`      | `num_bits`                  | `0x80 \| num_bits`                 |
+//! | `[1..3)`      |                             | `len`, as a little-endian `u16`    |
+//! | remainder     | the compressed block        | the compressed padded block        |
+//!
+//! A stream that ends without its final block is reported as truncated.
+//!
+//! Both types also implement the byte-oriented `Write` and `Read` traits, on the
+//! little-endian representation of the integers.
+
+use std::io::{self, Read, Write};
+
+use crate::BitPacker;
+
+/// Flag of the header byte of the final block.
+const FINAL_BLOCK_FLAG: u8 = 0x80;
+
+/// Compresses a stream of `u32` into a `Write`, one block at a time.
+///
+/// `.finish()` must be called once all of the integers are written: it writes the
+/// final, partial, block without which the stream cannot be decoded.
+///
+/// ```
+/// use bitpacking::{BitPackReader, BitPackWriter, BitPacker4x};
+///
+/// let values: Vec<u32> = (0..1_000).map(|i| i % 300).collect();
+/// let mut writer = BitPackWriter::<_, BitPacker4x>::new(Vec::new());
+/// writer.write_values(&values).unwrap();
+/// let compressed = writer.finish().unwrap();
+/// assert!(compressed.len() < values.len() * 2);
+///
+/// let mut reader = BitPackReader::<_, BitPacker4x>::new(&compressed[..]);
+/// let mut decompressed = vec![0u32; values.len()];
+/// assert_eq!(reader.read_values(&mut decompressed).unwrap(), values.len());
+/// assert_eq!(values, decompressed);
+/// assert!(reader.read_block().unwrap().is_none());
+/// ```
+pub struct BitPackWriter<W: Write, TBitPacker: BitPacker> {
+    bitpacker: TBitPacker,
+    inner: W,
+    block: Vec<u32>,
+    compressed: Vec<u8>,
+    /// Bytes of a `u32` partially written through the `Write` implementation.
+    partial_value: [u8; 4],
+    partial_value_len: usize,
+}
+
+impl<W: Write, TBitPacker: BitPacker> BitPackWriter<W, TBitPacker> {
+    /// Returns a writer compressing into `inner`, relying on the best available
+    /// implementation of `TBitPacker`.
+    pub fn new(inner: W) -> Self {
+        BitPackWriter {
+            bitpacker: TBitPacker::new(),
+            inner,
+            block: Vec::with_capacity(TBitPacker::BLOCK_LEN),
+            compressed: vec![0u8; 3 + 4 * TBitPacker::BLOCK_LEN],
+            partial_value: [0u8; 4],
+            partial_value_len: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Appends `value` to the stream.
+    pub fn write_value(&mut self, value: u32) -> io::Result<()> {
+        self.write_values(&[value])
+    }
+
+    /// Appends `values` to the stream, writing the blocks they complete.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::InvalidInput` if the bytes of a `u32` were partially
+    /// written with `Write::write(..)`.
+    pub fn write_values(&mut self, mut values: &[u32]) -> io::Result<()> {
+        if self.partial_value_len != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a u32 was partially written",
+            ));
+        }
+        while !values.is_empty() {
+            let len = (TBitPacker::BLOCK_LEN - self.block.len()).min(values.len());
+            self.block.extend_from_slice(&values[..len]);
+            values = &values[len..];
+            if self.block.len() == TBitPacker::BLOCK_LEN {
+                let (num_bits, block_len) = self
+                    .bitpacker
+                    .compress_auto(&self.block, &mut self.compressed[1..]);
+                self.compressed[0] = num_bits;
+                self.inner.write_all(&self.compressed[..1 + block_len])?;
+                self.block.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the final block, flushes the underlying writer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::InvalidInput` if the bytes of a `u32` were partially
+    /// written with `Write::write(..)`.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.partial_value_len != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a u32 was partially written",
+            ));
+        }
+        let len = self.block.len();
+        self.block.resize(TBitPacker::BLOCK_LEN, 0);
+        let (num_bits, block_len) = self
+            .bitpacker
+            .compress_auto(&self.block, &mut self.compressed[3..]);
+        self.compressed[0] = FINAL_BLOCK_FLAG | num_bits;
+        self.compressed[1..3].copy_from_slice(&(len as u16).to_le_bytes());
+        self.inner.write_all(&self.compressed[..3 + block_len])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, TBitPacker: BitPacker> Write for BitPackWriter<W, TBitPacker> {
+    /// Appends the integers whose little-endian bytes are `buf` to the stream.
+    ///
+    /// The trailing bytes of an incomplete `u32` are kept until the next call.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut bytes = buf;
+        if self.partial_value_len != 0 {
+            let len = (4 - self.partial_value_len).min(bytes.len());
+            self.partial_value[self.partial_value_len..][..len].copy_from_slice(&bytes[..len]);
+            self.partial_value_len += len;
+            bytes = &bytes[len..];
+            if self.partial_value_len < 4 {
+                return Ok(buf.len());
+            }
+            self.partial_value_len = 0;
+            self.write_value(u32::from_le_bytes(self.partial_value))?;
+        }
+        let mut chunks = bytes.chunks_exact(4);
+        for chunk in chunks.by_ref() {
+            self.write_value(u32::from_le_bytes(chunk.try_into().unwrap()))?;
+        }
+        let remainder = chunks.remainder();
+        self.partial_value[..remainder.len()].copy_from_slice(remainder);
+        self.partial_value_len = remainder.len();
+        Ok(buf.len())
+    }
+
+    /// Flushes the underlying writer.
+    ///
+    /// The integers of the current, incomplete, block are not written.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decompresses a stream written by `BitPackWriter` from a `Read`.
+///
+/// See [`BitPackWriter`] for an example.
+pub struct BitPackReader<R: Read, TBitPacker: BitPacker> {
+    bitpacker: TBitPacker,
+    inner: R,
+    block: Vec<u32>,
+    compressed: Vec<u8>,
+    /// Amount of bytes of `block` already consumed.
+    offset: usize,
+    finished: bool,
+}
+
+impl<R: Read, TBitPacker: BitPacker> BitPackReader<R, TBitPacker> {
+    /// Returns a reader decompressing from `inner`, relying on the best available
+    /// implementation of `TBitPacker`.
+    pub fn new(inner: R) -> Self {
+        BitPackReader {
+            bitpacker: TBitPacker::new(),
+            inner,
+            block: Vec::with_capacity(TBitPacker::BLOCK_LEN),
+            compressed: vec![0u8; 4 * TBitPacker::BLOCK_LEN],
+            offset: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decodes the next block of the stream, and returns its integers.
+    ///
+    /// The final block may hold fewer than `BLOCK_LEN` integers. Once it is returned,
+    /// `Ok(None)` is returned.
+    ///
+    /// The integers of the current block that were not consumed by `.read_values(..)`
+    /// or by `Read::read(..)` are discarded.
+    ///
+    /// # Errors
+    ///
+    /// - Fails with `ErrorKind::UnexpectedEof` if the stream ends before its final block.
+    /// - Fails with `ErrorKind::InvalidData` if a block header is invalid.
+    pub fn read_block(&mut self) -> io::Result<Option<&[u32]>> {
+        if self.finished {
+            self.block.clear();
+            self.offset = 0;
+            return Ok(None);
+        }
+        let mut header = [0u8; 1];
+        self.inner.read_exact(&mut header)?;
+        let is_final = header[0] & FINAL_BLOCK_FLAG != 0;
+        let num_bits = header[0] & !FINAL_BLOCK_FLAG;
+        if num_bits > 32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid number of bits {num_bits}"),
+            ));
+        }
+        let len = if is_final {
+            let mut len = [0u8; 2];
+            self.inner.read_exact(&mut len)?;
+            let len = u16::from_le_bytes(len) as usize;
+            if len >= TBitPacker::BLOCK_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid final block len {len}"),
+                ));
+            }
+            len
+        } else {
+            TBitPacker::BLOCK_LEN
+        };
+        let compressed = &mut self.compressed[..TBitPacker::compressed_block_size(num_bits)];
+        self.inner.read_exact(compressed)?;
+        self.block.resize(TBitPacker::BLOCK_LEN, 0);
+        self.bitpacker
+            .decompress(compressed, &mut self.block, num_bits);
+        self.block.truncate(len);
+        self.offset = 0;
+        self.finished = is_final;
+        Ok(Some(&self.block))
+    }
+
+    /// Fills `values` with the next integers of the stream, decoding blocks as needed.
+    ///
+    /// Returns the amount of integers read, which is smaller than `values.len()` only
+    /// if the end of the stream is reached.
+    ///
+    /// # Errors
+    ///
+    /// - Fails like `.read_block()`.
+    /// - Fails with `ErrorKind::InvalidInput` if the bytes of a `u32` were partially
+    ///   read with `Read::read(..)`.
+    pub fn read_values(&mut self, values: &mut [u32]) -> io::Result<usize> {
+        if !self.offset.is_multiple_of(4) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a u32 was partially read",
+            ));
+        }
+        let mut num_read = 0;
+        while num_read < values.len() {
+            if self.offset == 4 * self.block.len() && self.read_block()?.is_none() {
+                break;
+            }
+            let block = &self.block[self.offset / 4..];
+            let len = block.len().min(values.len() - num_read);
+            values[num_read..][..len].copy_from_slice(&block[..len]);
+            self.offset += 4 * len;
+            num_read += len;
+        }
+        Ok(num_read)
+    }
+}
+
+impl<R: Read, TBitPacker: BitPacker> Read for BitPackReader<R, TBitPacker> {
+    /// Reads the little-endian bytes of the next integers of the stream.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == 4 * self.block.len() && self.read_block()?.is_none() {
+            return Ok(0);
+        }
+        let mut num_read = 0;
+        while num_read < buf.len() && self.offset < 4 * self.block.len() {
+            let bytes = self.block[self.offset / 4].to_le_bytes();
+            let bytes = &bytes[self.offset % 4..];
+            let len = bytes.len().min(buf.len() - num_read);
+            buf[num_read..][..len].copy_from_slice(&bytes[..len]);
+            self.offset += len;
+            num_read += len;
+        }
+        Ok(num_read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use super::{BitPackReader, BitPackWriter};
+    use crate::tests::generate_array;
+    use crate::BitPacker;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+    #[cfg(feature = "bitpackerfastlanes")]
+    use crate::BitPackerFastLanes;
+
+    fn test_util_stream<TBitPacker: BitPacker>(values: &[u32]) {
+        let mut writer = BitPackWriter::<_, TBitPacker>::new(Vec::new());
+        // Writes in chunks straddling the blocks.
+        for chunk in values.chunks(TBitPacker::BLOCK_LEN / 2 + 3) {
+            writer.write_values(chunk).unwrap();
+        }
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = BitPackReader::<_, TBitPacker>::new(&compressed[..]);
+        let mut decompressed = Vec::new();
+        while let Some(block) = reader.read_block().unwrap() {
+            assert!(block.len() <= TBitPacker::BLOCK_LEN);
+            decompressed.extend_from_slice(block);
+        }
+        assert_eq!(values, &decompressed[..]);
+        assert!(reader.read_block().unwrap().is_none());
+
+        let mut reader = BitPackReader::<_, TBitPacker>::new(&compressed[..]);
+        let mut decompressed = vec![0u32; values.len() + 1];
+        assert_eq!(reader.read_values(&mut decompressed).unwrap(), values.len());
+        assert_eq!(values, &decompressed[..values.len()]);
+
+        // The byte-oriented traits, with unaligned reads and writes.
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let mut writer = BitPackWriter::<_, TBitPacker>::new(Vec::new());
+        for chunk in bytes.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), compressed);
+        let mut reader = BitPackReader::<_, TBitPacker>::new(&compressed[..]);
+        let mut decompressed_bytes = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let len = reader.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            decompressed_bytes.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(bytes, decompressed_bytes);
+
+        // Every strict prefix of the stream is truncated.
+        for len in [0, compressed.len() / 2, compressed.len() - 1] {
+            let mut reader = BitPackReader::<_, TBitPacker>::new(&compressed[..len]);
+            let err = loop {
+                if let Err(err) = reader.read_block() {
+                    break err;
+                }
+            };
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    fn test_suite_stream<TBitPacker: BitPacker>() {
+        let block_len = TBitPacker::BLOCK_LEN;
+        for len in [0, 1, block_len - 1, block_len, 3 * block_len + 5] {
+            test_util_stream::<TBitPacker>(&generate_array(len, 13));
+        }
+    }
+
+    #[cfg(feature = "bitpacker1x")]
+    #[test]
+    fn test_stream_bitpacker1x() {
+        test_suite_stream::<BitPacker1x>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_stream_bitpacker4x() {
+        test_suite_stream::<BitPacker4x>();
+    }
+
+    #[cfg(feature = "bitpacker8x")]
+    #[test]
+    fn test_stream_bitpacker8x() {
+        test_suite_stream::<BitPacker8x>();
+    }
+
+    #[cfg(feature = "bitpackerfastlanes")]
+    #[test]
+    fn test_stream_bitpackerfastlanes() {
+        test_suite_stream::<BitPackerFastLanes>();
+    }
+
+    #[cfg(feature = "bitpacker4x")]
+    #[test]
+    fn test_stream_invalid() {
+        let mut writer = BitPackWriter::<_, BitPacker4x>::new(Vec::new());
+        writer.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(
+            writer.write_values(&[4]).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            writer.finish().unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        for header in [&[33u8][..], &[0x80, 128, 0]] {
+            let mut reader = BitPackReader::<_, BitPacker4x>::new(header);
+            assert_eq!(
+                reader.read_block().unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+
+        let compressed = BitPackWriter::<_, BitPacker4x>::new(Vec::new())
+            .finish()
+            .unwrap();
+        let mut reader = BitPackReader::<_, BitPacker4x>::new(&compressed[..]);
+        let mut buf = [0u8; 2];
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+}
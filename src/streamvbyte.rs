@@ -0,0 +1,560 @@
+//! StreamVByte codec.
+//!
+//! Bitpacking requires blocks of a fixed size, and a single large integer is enough
+//! to ruin the compression ratio of its whole block. StreamVByte is a byte-oriented
+//! alternative that is better suited to partial trailing blocks and to integers of
+//! wildly varying magnitudes.
+//!
+//! Each integer is stored over 1 to 4 bytes. The lengths are stored separately, as
+//! 2-bits codes packed four per control byte, so that the decoder can process four
+//! integers at a time with a single byte shuffle (`SSSE3` on `x86_64`, `NEON` on `aarch64`).
+//!
+//! The compressed stream is the concatenation of the `ceil(len / 4)` control bytes and
+//! of the data bytes. The number of integers is not stored: it is the length of the
+//! output slice passed to the decoder.
+
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_endian = "little")
+))]
+use crate::Available;
+
+trait UnsafeStreamVByte {
+    /// Decodes `output.len()` integers and returns the number of data bytes consumed.
+    ///
+    /// If `previous` is set, the decoded integers are deltas and are integrated starting
+    /// from `previous`.
+    unsafe fn decode(
+        control: &[u8],
+        data: &[u8],
+        output: &mut [u32],
+        previous: Option<u32>,
+    ) -> usize;
+}
+
+/// Returns the number of bytes required to store `val`, minus one.
+#[inline]
+fn len_code(val: u32) -> u8 {
+    ((32 - (val | 1).leading_zeros()).div_ceil(8) - 1) as u8
+}
+
+/// Number of data bytes used by a group of 4 integers, for each control byte.
+const LENGTHS: [u8; 256] = {
+    let mut lengths = [0u8; 256];
+    let mut control = 0;
+    while control < 256 {
+        let mut lane = 0;
+        while lane < 4 {
+            lengths[control] += ((control >> (2 * lane)) & 3) as u8 + 1;
+            lane += 1;
+        }
+        control += 1;
+    }
+    lengths
+};
+
+/// Byte shuffle expanding a group of 4 integers into 4 `u32`, for each control byte.
+///
+/// Indices with their high bit set select a zero, both for `pshufb` and `tbl`.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_endian = "little")
+))]
+const SHUFFLES: [[u8; 16]; 256] = {
+    let mut shuffles = [[0u8; 16]; 256];
+    let mut control = 0;
+    while control < 256 {
+        let mut offset = 0;
+        let mut lane = 0;
+        while lane < 4 {
+            let len = ((control >> (2 * lane)) & 3) + 1;
+            let mut byte = 0;
+            while byte < 4 {
+                shuffles[control][lane * 4 + byte] = if byte < len {
+                    (offset + byte) as u8
+                } else {
+                    0xFF
+                };
+                byte += 1;
+            }
+            offset += len;
+            lane += 1;
+        }
+        control += 1;
+    }
+    shuffles
+};
+
+mod scalar {
+
+    use super::UnsafeStreamVByte;
+
+    pub(super) struct UnsafeStreamVByteImpl;
+
+    impl UnsafeStreamVByte for UnsafeStreamVByteImpl {
+        unsafe fn decode(
+            control: &[u8],
+            data: &[u8],
+            output: &mut [u32],
+            previous: Option<u32>,
+        ) -> usize {
+            let mut offset = 0;
+            let mut current = previous.unwrap_or(0);
+            for (i, dest) in output.iter_mut().enumerate() {
+                let len = ((control[i / 4] >> (2 * (i % 4))) & 3) as usize + 1;
+                let mut bytes = [0u8; 4];
+                bytes[..len].copy_from_slice(&data[offset..offset + len]);
+                offset += len;
+                let val = u32::from_le_bytes(bytes);
+                *dest = if previous.is_some() {
+                    current = current.wrapping_add(val);
+                    current
+                } else {
+                    val
+                };
+            }
+            offset
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod ssse3 {
+
+    use super::{scalar, UnsafeStreamVByte, LENGTHS, SHUFFLES};
+    use crate::Available;
+
+    use std::arch::x86_64::__m128i as DataType;
+    use std::arch::x86_64::{
+        _mm_add_epi32, _mm_loadu_si128, _mm_set1_epi32, _mm_shuffle_epi32, _mm_shuffle_epi8,
+        _mm_slli_si128, _mm_storeu_si128,
+    };
+
+    #[target_feature(enable = "ssse3")]
+    #[allow(non_snake_case)]
+    #[inline]
+    unsafe fn integrate_delta(prev: DataType, delta: DataType) -> DataType {
+        let offset = _mm_shuffle_epi32(prev, 0xff);
+        let a__b__c__d_ = delta;
+        let ______a__b_ = _mm_slli_si128(delta, 8);
+        let a__b__ca_db = _mm_add_epi32(______a__b_, a__b__c__d_);
+        let ___a__b__ca = _mm_slli_si128(a__b__ca_db, 4);
+        let a_ab_abc_abcd: DataType = _mm_add_epi32(___a__b__ca, a__b__ca_db);
+        _mm_add_epi32(offset, a_ab_abc_abcd)
+    }
+
+    pub(super) struct UnsafeStreamVByteImpl;
+
+    impl UnsafeStreamVByte for UnsafeStreamVByteImpl {
+        #[target_feature(enable = "ssse3")]
+        unsafe fn decode(
+            control: &[u8],
+            data: &[u8],
+            output: &mut [u32],
+            previous: Option<u32>,
+        ) -> usize {
+            let num_groups = output.len() / 4;
+            let mut offset = 0;
+            let mut current = _mm_set1_epi32(previous.unwrap_or(0) as i32);
+            let mut group = 0;
+            // Each group loads 16 bytes, whatever its actual length.
+            while group < num_groups && offset + 16 <= data.len() {
+                let control_byte = control[group] as usize;
+                let shuffle = _mm_loadu_si128(SHUFFLES[control_byte].as_ptr().cast::<DataType>());
+                let packed = _mm_loadu_si128(data.as_ptr().add(offset).cast::<DataType>());
+                let mut unpacked = _mm_shuffle_epi8(packed, shuffle);
+                if previous.is_some() {
+                    unpacked = integrate_delta(current, unpacked);
+                    current = unpacked;
+                }
+                _mm_storeu_si128(
+                    output.as_mut_ptr().add(group * 4).cast::<DataType>(),
+                    unpacked,
+                );
+                offset += LENGTHS[control_byte] as usize;
+                group += 1;
+            }
+            let tail_previous = if previous.is_some() && group > 0 {
+                Some(output[group * 4 - 1])
+            } else {
+                previous
+            };
+            offset
+                + scalar::UnsafeStreamVByteImpl::decode(
+                    &control[group..],
+                    &data[offset..],
+                    &mut output[group * 4..],
+                    tail_previous,
+                )
+        }
+    }
+
+    impl Available for UnsafeStreamVByteImpl {
+        fn available() -> bool {
+            is_x86_feature_detected!("ssse3")
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+mod neon {
+
+    use super::{scalar, UnsafeStreamVByte, LENGTHS, SHUFFLES};
+    use crate::Available;
+
+    use std::arch::aarch64::{
+        uint32x4_t, vaddq_u32, vdupq_n_u32, vextq_u32, vgetq_lane_u32, vld1q_u8, vqtbl1q_u8,
+        vreinterpretq_u32_u8, vst1q_u32,
+    };
+
+    #[target_feature(enable = "neon")]
+    #[allow(non_snake_case)]
+    #[inline]
+    unsafe fn integrate_delta(prev: u32, delta: uint32x4_t) -> uint32x4_t {
+        let base = vdupq_n_u32(prev);
+        let zero = vdupq_n_u32(0);
+        let a__b__c__d_ = delta;
+        let ______a__b_ = vextq_u32(zero, a__b__c__d_, 2);
+        let a__b__ca_db = vaddq_u32(______a__b_, a__b__c__d_);
+        let ___a__b__ca = vextq_u32(zero, a__b__ca_db, 3);
+        let a_ab_abc_abcd = vaddq_u32(___a__b__ca, a__b__ca_db);
+        vaddq_u32(base, a_ab_abc_abcd)
+    }
+
+    pub(super) struct UnsafeStreamVByteImpl;
+
+    impl UnsafeStreamVByte for UnsafeStreamVByteImpl {
+        #[target_feature(enable = "neon")]
+        unsafe fn decode(
+            control: &[u8],
+            data: &[u8],
+            output: &mut [u32],
+            previous: Option<u32>,
+        ) -> usize {
+            let num_groups = output.len() / 4;
+            let mut offset = 0;
+            let mut current = previous.unwrap_or(0);
+            let mut group = 0;
+            // Each group loads 16 bytes, whatever its actual length.
+            while group < num_groups && offset + 16 <= data.len() {
+                let control_byte = control[group] as usize;
+                let shuffle = vld1q_u8(SHUFFLES[control_byte].as_ptr());
+                let packed = vld1q_u8(data.as_ptr().add(offset));
+                let mut unpacked = vreinterpretq_u32_u8(vqtbl1q_u8(packed, shuffle));
+                if previous.is_some() {
+                    unpacked = integrate_delta(current, unpacked);
+                    current = vgetq_lane_u32(unpacked, 3);
+                }
+                vst1q_u32(output.as_mut_ptr().add(group * 4), unpacked);
+                offset += LENGTHS[control_byte] as usize;
+                group += 1;
+            }
+            let tail_previous = if previous.is_some() && group > 0 {
+                Some(current)
+            } else {
+                previous
+            };
+            offset
+                + scalar::UnsafeStreamVByteImpl::decode(
+                    &control[group..],
+                    &data[offset..],
+                    &mut output[group * 4..],
+                    tail_previous,
+                )
+        }
+    }
+
+    impl Available for UnsafeStreamVByteImpl {
+        fn available() -> bool {
+            std::arch::is_aarch64_feature_detected!("neon")
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum InstructionSet {
+    #[cfg(target_arch = "x86_64")]
+    SSSE3,
+    #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+    NEON,
+    Scalar,
+}
+
+/// StreamVByte encoder/decoder.
+///
+/// Contrary to the `BitPacker` implementations, it accepts any number of integers.
+///
+/// ```
+/// use bitpacking::StreamVByte;
+///
+/// let tail: Vec<u32> = vec![1, 300, 70_000, 2, 16_000_000, 5, 7];
+///
+/// let stream_vbyte = StreamVByte::new();
+/// let mut compressed = vec![0u8; StreamVByte::max_compressed_len(tail.len())];
+/// let compressed_len = stream_vbyte.encode(&tail, &mut compressed);
+/// assert_eq!(compressed_len, 2 + (1 + 2 + 3 + 1 + 3 + 1 + 1));
+///
+/// let mut decompressed = vec![0u32; tail.len()];
+/// let read_len = stream_vbyte.decode(&compressed, &mut decompressed);
+/// assert_eq!(read_len, compressed_len);
+/// assert_eq!(tail, decompressed);
+/// ```
+#[derive(Clone, Copy)]
+pub struct StreamVByte(InstructionSet);
+
+impl Default for StreamVByte {
+    fn default() -> Self {
+        StreamVByte::new()
+    }
+}
+
+impl StreamVByte {
+    /// Checks the available instructions set on the current
+    /// CPU and returns the best available implementation.
+    pub fn new() -> StreamVByte {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if ssse3::UnsafeStreamVByteImpl::available() {
+                return StreamVByte(InstructionSet::SSSE3);
+            }
+        }
+        #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+        {
+            if neon::UnsafeStreamVByteImpl::available() {
+                return StreamVByte(InstructionSet::NEON);
+            }
+        }
+        StreamVByte(InstructionSet::Scalar)
+    }
+
+    /// Returns the maximum number of bytes required to encode `len` integers.
+    #[must_use]
+    pub fn max_compressed_len(len: usize) -> usize {
+        len.div_ceil(4) + 4 * len
+    }
+
+    /// Encodes `decompressed` into `compressed`.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `compressed` is too small. A buffer of
+    /// `StreamVByte::max_compressed_len(decompressed.len())` bytes is always large enough.
+    pub fn encode(&self, decompressed: &[u32], compressed: &mut [u8]) -> usize {
+        encode_generic(decompressed.iter().copied(), decompressed.len(), compressed)
+    }
+
+    /// Delta-encodes the sorted array `decompressed` into `compressed`.
+    ///
+    /// `initial` is used to compute the first delta.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `compressed` is too small. A buffer of
+    /// `StreamVByte::max_compressed_len(decompressed.len())` bytes is always large enough.
+    pub fn encode_sorted(
+        &self,
+        initial: u32,
+        decompressed: &[u32],
+        compressed: &mut [u8],
+    ) -> usize {
+        let mut previous = initial;
+        let deltas = decompressed.iter().map(|&val| {
+            let delta = val.wrapping_sub(previous);
+            previous = val;
+            delta
+        });
+        encode_generic(deltas, decompressed.len(), compressed)
+    }
+
+    /// Decodes `decompressed.len()` integers from `compressed`.
+    ///
+    /// Returns the amount of bytes consumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `compressed` is too short.
+    pub fn decode(&self, compressed: &[u8], decompressed: &mut [u32]) -> usize {
+        self.decode_generic(compressed, decompressed, None)
+    }
+
+    /// Decodes `decompressed.len()` integers that were encoded with `encode_sorted`.
+    ///
+    /// `initial` must be the value that was passed when encoding.
+    ///
+    /// Returns the amount of bytes consumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `compressed` is too short.
+    pub fn decode_sorted(
+        &self,
+        initial: u32,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+    ) -> usize {
+        self.decode_generic(compressed, decompressed, Some(initial))
+    }
+
+    fn decode_generic(
+        &self,
+        compressed: &[u8],
+        decompressed: &mut [u32],
+        previous: Option<u32>,
+    ) -> usize {
+        let control_len = decompressed.len().div_ceil(4);
+        assert!(
+            compressed.len() >= control_len,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            control_len
+        );
+        let (control, data) = compressed.split_at(control_len);
+        let num_full_groups = decompressed.len() / 4;
+        let mut data_len: usize = control[..num_full_groups]
+            .iter()
+            .map(|&control_byte| LENGTHS[control_byte as usize] as usize)
+            .sum();
+        // The control byte of a partial last group only describes its first lanes.
+        if let Some(&control_byte) = control.get(num_full_groups) {
+            data_len += (0..decompressed.len() % 4)
+                .map(|lane| ((control_byte >> (2 * lane)) & 3) as usize + 1)
+                .sum::<usize>();
+        }
+        assert!(
+            data.len() >= data_len,
+            "Compressed array seems too small. ({} < {}) ",
+            compressed.len(),
+            control_len + data_len
+        );
+        let data = &data[..data_len];
+        control_len
+            + unsafe {
+                match self.0 {
+                    #[cfg(target_arch = "x86_64")]
+                    InstructionSet::SSSE3 => {
+                        ssse3::UnsafeStreamVByteImpl::decode(control, data, decompressed, previous)
+                    }
+                    #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+                    InstructionSet::NEON => {
+                        neon::UnsafeStreamVByteImpl::decode(control, data, decompressed, previous)
+                    }
+                    InstructionSet::Scalar => {
+                        scalar::UnsafeStreamVByteImpl::decode(control, data, decompressed, previous)
+                    }
+                }
+            }
+    }
+}
+
+fn encode_generic(vals: impl Iterator<Item = u32>, len: usize, compressed: &mut [u8]) -> usize {
+    let control_len = len.div_ceil(4);
+    assert!(
+        compressed.len() >= control_len,
+        "Output array too small. {} < {}",
+        compressed.len(),
+        control_len
+    );
+    let (control, data) = compressed.split_at_mut(control_len);
+    control.fill(0u8);
+    let mut offset = 0;
+    for (i, val) in vals.enumerate() {
+        let code = len_code(val);
+        let num_bytes = code as usize + 1;
+        control[i / 4] |= code << (2 * (i % 4));
+        data[offset..offset + num_bytes].copy_from_slice(&val.to_le_bytes()[..num_bytes]);
+        offset += num_bytes;
+    }
+    control_len + offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{len_code, scalar, StreamVByte, UnsafeStreamVByte, LENGTHS};
+    use crate::tests::generate_array;
+
+    #[test]
+    fn test_len_code() {
+        assert_eq!(len_code(0), 0);
+        assert_eq!(len_code(255), 0);
+        assert_eq!(len_code(256), 1);
+        assert_eq!(len_code(65_535), 1);
+        assert_eq!(len_code(65_536), 2);
+        assert_eq!(len_code(u32::MAX), 3);
+        assert_eq!(LENGTHS[0], 4);
+        assert_eq!(LENGTHS[255], 16);
+    }
+
+    fn test_util_roundtrip(stream_vbyte: StreamVByte, values: &[u32]) {
+        let mut compressed = vec![0u8; StreamVByte::max_compressed_len(values.len())];
+        let compressed_len = stream_vbyte.encode(values, &mut compressed);
+        let mut decompressed = vec![0u32; values.len()];
+        let read_len = stream_vbyte.decode(&compressed[..compressed_len], &mut decompressed);
+        assert_eq!(read_len, compressed_len);
+        assert_eq!(values, &decompressed[..]);
+
+        let mut scalar_decompressed = vec![0u32; values.len()];
+        let control_len = values.len().div_ceil(4);
+        unsafe {
+            scalar::UnsafeStreamVByteImpl::decode(
+                &compressed[..control_len],
+                &compressed[control_len..compressed_len],
+                &mut scalar_decompressed,
+                None,
+            );
+        }
+        assert_eq!(values, &scalar_decompressed[..]);
+
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        for initial in [0u32, sorted.first().copied().unwrap_or(0)] {
+            let compressed_len = stream_vbyte.encode_sorted(initial, &sorted, &mut compressed);
+            let read_len = stream_vbyte.decode_sorted(
+                initial,
+                &compressed[..compressed_len],
+                &mut decompressed,
+            );
+            assert_eq!(read_len, compressed_len);
+            assert_eq!(sorted, decompressed);
+        }
+    }
+
+    #[test]
+    fn test_stream_vbyte() {
+        let stream_vbyte = StreamVByte::new();
+        test_util_roundtrip(stream_vbyte, &[]);
+        test_util_roundtrip(stream_vbyte, &[u32::MAX]);
+        for len in [1, 3, 4, 5, 17, 128, 1_000] {
+            for num_bits in [0, 3, 8, 9, 17, 25, 32] {
+                test_util_roundtrip(stream_vbyte, &generate_array(len, num_bits));
+            }
+        }
+        let mixed: Vec<u32> = generate_array(1_000, 32)
+            .into_iter()
+            .map(|val| val >> (val % 32))
+            .collect();
+        test_util_roundtrip(stream_vbyte, &mixed);
+    }
+
+    #[test]
+    fn test_stream_vbyte_scalar() {
+        let stream_vbyte = StreamVByte(super::InstructionSet::Scalar);
+        for len in [1, 5, 1_000] {
+            test_util_roundtrip(stream_vbyte, &generate_array(len, 20));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Compressed array seems too small")]
+    fn test_stream_vbyte_truncated() {
+        let stream_vbyte = StreamVByte::new();
+        let values = generate_array(100, 32);
+        let mut compressed = vec![0u8; StreamVByte::max_compressed_len(values.len())];
+        let compressed_len = stream_vbyte.encode(&values, &mut compressed);
+        let mut decompressed = vec![0u32; values.len()];
+        stream_vbyte.decode(&compressed[..compressed_len - 1], &mut decompressed);
+    }
+}
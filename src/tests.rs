@@ -15,19 +15,17 @@ pub fn generate_array(n: usize, max_num_bits: u8) -> Vec<u32> {
 
 fn integrate_data(initial: u32, data: &mut [u32]) {
     let mut cumul = initial;
-    let len = data.len();
-    for i in 0..len {
-        cumul = cumul.wrapping_add(data[i]);
-        data[i] = cumul;
+    for el in data.iter_mut() {
+        cumul = cumul.wrapping_add(*el);
+        *el = cumul;
     }
 }
 
 fn strict_integrate_data(initial: Option<u32>, data: &mut [u32]) {
     let mut cumul = initial.unwrap_or(u32::MAX);
-    let len = data.len();
-    for i in 0..len {
-        cumul = cumul.wrapping_add(data[i]).wrapping_add(1);
-        data[i] = cumul;
+    for el in data.iter_mut() {
+        cumul = cumul.wrapping_add(*el).wrapping_add(1);
+        *el = cumul;
     }
 }
 
@@ -48,6 +46,19 @@ pub(crate) fn test_util_compatible<TLeft: UnsafeBitPacker, TRight: UnsafeBitPack
             assert_eq!(left_len, right_len);
             assert_eq!(&output_left[..left_len], &output_right[..right_len]);
         }
+        let mut sorted = original.clone();
+        integrate_data(3, &mut sorted);
+        unsafe {
+            let num_bits_left = TLeft::num_bits_sorted(3, &sorted);
+            assert_eq!(num_bits_left, TRight::num_bits_sorted(3, &sorted));
+            let mut output_left = vec![0u8; block_len * 4];
+            let mut output_right = vec![0u8; block_len * 4];
+            let left_len =
+                TLeft::compress_sorted(3, &sorted, &mut output_left[..], num_bits_left);
+            let right_len =
+                TRight::compress_sorted(3, &sorted, &mut output_right[..], num_bits_left);
+            assert_eq!(&output_left[..left_len], &output_right[..right_len]);
+        }
     }
 }
 
@@ -193,7 +204,12 @@ pub(crate) enum DeltaKind {
 }
 
 pub(crate) fn test_suite_compress_decompress<TBitPacker: UnsafeBitPacker>(delta: DeltaKind) {
-    let num_blocks = (1 << 15) / TBitPacker::BLOCK_LEN;
+    // Miri only goes through a couple of blocks per width, in a reasonable time.
+    let num_blocks = if cfg!(miri) {
+        2
+    } else {
+        (1 << 15) / TBitPacker::BLOCK_LEN
+    };
     let n = num_blocks * TBitPacker::BLOCK_LEN;
     for num_bits in 0u8..33u8 {
         let original = generate_array(n, num_bits);
@@ -221,3 +237,339 @@ pub(crate) fn test_suite_compress_decompress<TBitPacker: UnsafeBitPacker>(delta:
         }
     }
 }
+
+pub(crate) fn test_util_decompress_scaled<TBitPacker: UnsafeBitPacker>() {
+    for num_bits in 0u8..33u8 {
+        let original = generate_array(TBitPacker::BLOCK_LEN, num_bits);
+        let mut compressed = vec![0u8; TBitPacker::BLOCK_LEN * 4];
+        let mut result = vec![0u32; TBitPacker::BLOCK_LEN];
+        for (offset, factor) in [(0u32, 1u32), (17, 1000), (u32::MAX, 3), (5, u32::MAX)] {
+            unsafe {
+                let compressed_len = TBitPacker::compress(&original, &mut compressed, num_bits);
+                let read_len = TBitPacker::decompress_scaled(
+                    offset,
+                    factor,
+                    &compressed[..compressed_len],
+                    &mut result,
+                    num_bits,
+                );
+                assert_eq!(read_len, compressed_len);
+            }
+            for (&original_val, &result_val) in original.iter().zip(result.iter()) {
+                assert_eq!(
+                    offset.wrapping_add(original_val.wrapping_mul(factor)),
+                    result_val
+                );
+            }
+        }
+    }
+}
+
+pub(crate) fn test_util_compress_for<TBitPacker: UnsafeBitPacker>() {
+    for num_bits in 0u8..33u8 {
+        let original = generate_array(TBitPacker::BLOCK_LEN, num_bits);
+        let mut compressed = vec![0u8; TBitPacker::BLOCK_LEN * 4];
+        let mut result = vec![0u32; TBitPacker::BLOCK_LEN];
+        for reference in [0u32, 17, u32::MAX] {
+            let shifted: Vec<u32> = original
+                .iter()
+                .map(|&val| val.wrapping_add(reference))
+                .collect();
+            unsafe {
+                let compressed_len =
+                    TBitPacker::compress_for(reference, &shifted, &mut compressed, num_bits);
+                assert_eq!(compressed_len, TBitPacker::BLOCK_LEN * num_bits as usize / 8);
+                TBitPacker::decompress(&compressed[..compressed_len], &mut result, num_bits);
+            }
+            assert_eq!(&original, &result);
+        }
+    }
+}
+
+fn mask(val: u32, num_bits: u8) -> u32 {
+    (u64::from(val) & ((1u64 << num_bits) - 1)) as u32
+}
+
+pub(crate) fn test_util_compress_masked<TBitPacker: UnsafeBitPacker>() {
+    let original = generate_array(TBitPacker::BLOCK_LEN, 32);
+    let mut compressed = vec![0u8; TBitPacker::BLOCK_LEN * 4];
+    let mut result = vec![0u32; TBitPacker::BLOCK_LEN];
+    for num_bits in 0u8..33u8 {
+        unsafe {
+            let compressed_len = TBitPacker::compress_masked(&original, &mut compressed, num_bits);
+            assert_eq!(compressed_len, TBitPacker::BLOCK_LEN * num_bits as usize / 8);
+            TBitPacker::decompress(&compressed[..compressed_len], &mut result, num_bits);
+        }
+        for (&original_val, &result_val) in original.iter().zip(result.iter()) {
+            assert_eq!(mask(original_val, num_bits), result_val);
+        }
+    }
+}
+
+/// Checks with proptest that `TRight::compress_masked` produces the same bytes as
+/// `TLeft::compress_masked` on arbitrary integers.
+pub(crate) fn test_util_compress_masked_compatible<
+    TLeft: UnsafeBitPacker,
+    TRight: UnsafeBitPacker,
+>(
+    block_len: usize,
+) {
+    use proptest::prelude::*;
+    use proptest::test_runner::{Config, TestRunner};
+
+    let strategy = (prop::collection::vec(any::<u32>(), block_len), 0u8..=32u8);
+    TestRunner::new(Config::with_cases(64))
+        .run(&strategy, |(values, num_bits)| {
+            let mut output_left = vec![0u8; block_len * 4];
+            let mut output_right = vec![0u8; block_len * 4];
+            let (left_len, right_len) = unsafe {
+                (
+                    TLeft::compress_masked(&values, &mut output_left, num_bits),
+                    TRight::compress_masked(&values, &mut output_right, num_bits),
+                )
+            };
+            prop_assert_eq!(left_len, right_len);
+            prop_assert_eq!(&output_left[..left_len], &output_right[..right_len]);
+            Ok(())
+        })
+        .unwrap();
+}
+
+pub(crate) fn test_util_num_bits_sorted_checked<TBitPacker: UnsafeBitPacker>() {
+    use crate::CompressError;
+
+    let block_len = TBitPacker::BLOCK_LEN;
+    let mut sorted = generate_array(block_len, 10);
+    integrate_data(100, &mut sorted);
+    let mut strictly_sorted = generate_array(block_len, 10);
+    strict_integrate_data(Some(100), &mut strictly_sorted);
+    unsafe {
+        assert_eq!(
+            TBitPacker::num_bits_sorted_checked(100, &sorted),
+            Ok(TBitPacker::num_bits_sorted(100, &sorted))
+        );
+        assert_eq!(
+            TBitPacker::num_bits_strictly_sorted_checked(Some(100), &strictly_sorted),
+            Ok(TBitPacker::num_bits_strictly_sorted(Some(100), &strictly_sorted))
+        );
+        assert_eq!(
+            TBitPacker::num_bits_sorted_checked(sorted[0] + 1, &sorted),
+            Err(CompressError::NotSorted { index: 0 })
+        );
+        assert_eq!(
+            TBitPacker::num_bits_strictly_sorted_checked(Some(strictly_sorted[0]), &strictly_sorted),
+            Err(CompressError::NotSorted { index: 0 })
+        );
+
+        // Without an initial value, a strictly sorted block may start with 0.
+        let mut from_zero = generate_array(block_len, 10);
+        from_zero[0] = 0;
+        strict_integrate_data(None, &mut from_zero);
+        assert!(TBitPacker::num_bits_strictly_sorted_checked(None, &from_zero).is_ok());
+        assert_eq!(
+            TBitPacker::num_bits_strictly_sorted_checked(Some(u32::MAX), &from_zero),
+            Err(CompressError::NotSorted { index: 0 })
+        );
+
+        for index in 1..block_len {
+            let mut unsorted = sorted.clone();
+            unsorted[index] = unsorted[index - 1] - 1;
+            assert_eq!(
+                TBitPacker::num_bits_sorted_checked(100, &unsorted),
+                Err(CompressError::NotSorted { index })
+            );
+            let mut not_strict = strictly_sorted.clone();
+            not_strict[index] = not_strict[index - 1];
+            assert_eq!(
+                TBitPacker::num_bits_strictly_sorted_checked(Some(100), &not_strict),
+                Err(CompressError::NotSorted { index })
+            );
+            // Equal integers are fine in a sorted block.
+            assert!(TBitPacker::num_bits_sorted_checked(100, &not_strict).is_ok());
+        }
+    }
+}
+
+pub(crate) fn test_util_compress_auto<TBitPacker: UnsafeBitPacker>() {
+    let block_len = TBitPacker::BLOCK_LEN;
+    let mut expected = vec![0u8; block_len * 4];
+    let mut compressed = vec![0u8; block_len * 4];
+    for num_bits in 0..=32 {
+        let data = generate_array(block_len, num_bits);
+        unsafe {
+            let expected_num_bits = TBitPacker::num_bits(&data);
+            let expected_len = TBitPacker::compress(&data, &mut expected, expected_num_bits);
+            let (auto_num_bits, auto_len) = TBitPacker::compress_auto(&data, &mut compressed);
+            assert_eq!(auto_num_bits, expected_num_bits);
+            assert_eq!(&compressed[..auto_len], &expected[..expected_len]);
+        }
+
+        let mut sorted = data.clone();
+        integrate_data(100, &mut sorted);
+        unsafe {
+            let expected_num_bits = TBitPacker::num_bits_sorted(100, &sorted);
+            let expected_len = TBitPacker::compress_sorted(100, &sorted, &mut expected, expected_num_bits);
+            let (auto_num_bits, auto_len) = TBitPacker::compress_sorted_auto(100, &sorted, &mut compressed);
+            assert_eq!(auto_num_bits, expected_num_bits);
+            assert_eq!(&compressed[..auto_len], &expected[..expected_len]);
+        }
+
+        for initial in [None, Some(100)] {
+            let mut strictly_sorted = data.clone();
+            strictly_sorted[0] = 0;
+            strict_integrate_data(initial, &mut strictly_sorted);
+            unsafe {
+                let expected_num_bits = TBitPacker::num_bits_strictly_sorted(initial, &strictly_sorted);
+                let expected_len = TBitPacker::compress_strictly_sorted(
+                    initial,
+                    &strictly_sorted,
+                    &mut expected,
+                    expected_num_bits,
+                );
+                let (auto_num_bits, auto_len) =
+                    TBitPacker::compress_strictly_sorted_auto(initial, &strictly_sorted, &mut compressed);
+                assert_eq!(auto_num_bits, expected_num_bits);
+                assert_eq!(&compressed[..auto_len], &expected[..expected_len]);
+            }
+        }
+    }
+}
+
+pub(crate) fn test_util_decompress_to_sink<TBitPacker: UnsafeBitPacker>() {
+    let block_len = TBitPacker::BLOCK_LEN;
+    let mut compressed = vec![0u8; block_len * 4];
+    let mut expected = vec![0u32; block_len];
+    let mut received: Vec<u32> = Vec::with_capacity(block_len);
+    for num_bits in 0..=32 {
+        let data = generate_array(block_len, num_bits);
+        for mode in 0..3 {
+            received.clear();
+            let mut sink = |chunk: &[u32]| {
+                assert_eq!(chunk.len(), block_len / 32);
+                received.extend_from_slice(chunk);
+            };
+            unsafe {
+                let len = match mode {
+                    0 => {
+                        let len = TBitPacker::compress(&data, &mut compressed, num_bits);
+                        TBitPacker::decompress(&compressed, &mut expected, num_bits);
+                        assert_eq!(TBitPacker::decompress_to_sink(&compressed, &mut sink, num_bits), len);
+                        len
+                    }
+                    1 => {
+                        let len = TBitPacker::compress(&data, &mut compressed, num_bits);
+                        TBitPacker::decompress_sorted(100, &compressed, &mut expected, num_bits);
+                        assert_eq!(
+                            TBitPacker::decompress_sorted_to_sink(100, &compressed, &mut sink, num_bits),
+                            len
+                        );
+                        len
+                    }
+                    _ => {
+                        let len = TBitPacker::compress(&data, &mut compressed, num_bits);
+                        TBitPacker::decompress_strictly_sorted(None, &compressed, &mut expected, num_bits);
+                        assert_eq!(
+                            TBitPacker::decompress_strictly_sorted_to_sink(None, &compressed, &mut sink, num_bits),
+                            len
+                        );
+                        len
+                    }
+                };
+                assert_eq!(len, num_bits as usize * block_len / 8);
+            }
+            assert_eq!(received, expected);
+        }
+    }
+}
+
+pub(crate) fn test_util_narrow<TBitPacker: UnsafeBitPacker>() {
+    let block_len = TBitPacker::BLOCK_LEN;
+    let mut expected = vec![0u8; block_len * 4];
+    let mut compressed = vec![0u8; block_len * 4];
+    for num_bits in 0..=16 {
+        let data = generate_array(block_len, num_bits);
+        unsafe {
+            let expected_len = TBitPacker::compress(&data, &mut expected, num_bits);
+            let data_u16: Vec<u16> = data.iter().map(|&val| val as u16).collect();
+            let len = TBitPacker::compress_u16(&data_u16, &mut compressed, num_bits);
+            assert_eq!(&compressed[..len], &expected[..expected_len]);
+            let mut decompressed_u16 = vec![0u16; block_len];
+            assert_eq!(TBitPacker::decompress_into_u16(&compressed, &mut decompressed_u16, num_bits), len);
+            assert_eq!(decompressed_u16, data_u16);
+            if num_bits <= 8 {
+                let data_u8: Vec<u8> = data.iter().map(|&val| val as u8).collect();
+                let len = TBitPacker::compress_u8(&data_u8, &mut compressed, num_bits);
+                assert_eq!(&compressed[..len], &expected[..expected_len]);
+                let mut decompressed_u8 = vec![0u8; block_len];
+                assert_eq!(TBitPacker::decompress_into_u8(&compressed, &mut decompressed_u8, num_bits), len);
+                assert_eq!(decompressed_u8, data_u8);
+            }
+        }
+    }
+}
+
+pub(crate) fn test_util_decompress_u64<TBitPacker: UnsafeBitPacker>() {
+    let block_len = TBitPacker::BLOCK_LEN;
+    let mut compressed = vec![0u8; block_len * 4];
+    let mut decompressed = vec![0u32; block_len];
+    let mut decompressed_u64 = vec![0u64; block_len];
+    for num_bits in 0..=32 {
+        let data = generate_array(block_len, num_bits);
+        for base in [0u64, 7, u64::from(u32::MAX) * 3, u64::MAX - 5] {
+            unsafe {
+                let len = TBitPacker::compress(&data, &mut compressed, num_bits);
+                assert_eq!(TBitPacker::decompress(&compressed, &mut decompressed, num_bits), len);
+                assert_eq!(TBitPacker::decompress_into_u64(base, &compressed, &mut decompressed_u64, num_bits), len);
+                for (&val, &val_u64) in decompressed.iter().zip(&decompressed_u64) {
+                    assert_eq!(val_u64, base.wrapping_add(u64::from(val)));
+                }
+            }
+        }
+        // Deltas summing to less than `2^32`, the last value overflowing `u32`.
+        let initial = u32::MAX - (block_len as u32) * 2;
+        let deltas: Vec<u32> = data.iter().map(|&val| val % 4).collect();
+        let mut sorted = deltas.clone();
+        integrate_data(initial, &mut sorted);
+        let num_bits_sorted = unsafe { TBitPacker::num_bits_sorted(initial, &sorted) };
+        for base in [0u64, u64::from(u32::MAX) * 3] {
+            unsafe {
+                let len = TBitPacker::compress_sorted(initial, &sorted, &mut compressed, num_bits_sorted);
+                let decompressed_len = TBitPacker::decompress_sorted_into_u64(
+                    base + u64::from(initial),
+                    &compressed,
+                    &mut decompressed_u64,
+                    num_bits_sorted,
+                );
+                assert_eq!(decompressed_len, len);
+                let mut expected = base + u64::from(initial);
+                for (&delta, &val_u64) in deltas.iter().zip(&decompressed_u64) {
+                    expected += u64::from(delta);
+                    assert_eq!(val_u64, expected);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn test_util_num_bits_all_modes<TBitPacker: UnsafeBitPacker>() {
+    let block_len = TBitPacker::BLOCK_LEN;
+    for num_bits in 0..=32 {
+        let data = generate_array(block_len, num_bits);
+        let mut sorted = data.clone();
+        integrate_data(100, &mut sorted);
+        let mut strictly_sorted = data.clone();
+        strict_integrate_data(Some(100), &mut strictly_sorted);
+        for (initial, block) in [(0, &data), (100, &sorted), (100, &strictly_sorted), (u32::MAX, &data)] {
+            let min = block.iter().copied().min().unwrap();
+            let max = block.iter().copied().max().unwrap();
+            unsafe {
+                let widths = TBitPacker::num_bits_all_modes(initial, block);
+                assert_eq!(widths.plain, TBitPacker::num_bits(block));
+                assert_eq!(widths.sorted, TBitPacker::num_bits_sorted(initial, block));
+                assert_eq!(widths.strictly_sorted, TBitPacker::num_bits_strictly_sorted(Some(initial), block));
+                assert_eq!(widths.frame_of_reference, most_significant_bit(max - min));
+                assert_eq!(widths.min, min);
+            }
+        }
+    }
+}
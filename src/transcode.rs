@@ -0,0 +1,763 @@
+//! Transcoding between bitpacker layouts.
+//!
+//! `BitPacker1x`, `BitPacker4x` and `BitPacker8x` share the same structure: a block is
+//! made of `BLOCK_LEN / 32` lanes, interleaved word by word. Lane `c` holds the values
+//! `c`, `c + lanes`, `c + 2 * lanes`... concatenated over `num_bits` `u32` words.
+//! `BitPacker1x` has a single lane, `BitPacker4x` 4 and `BitPacker8x` 8.
+//! `BitPackerFastLanes` does not keep its rows in order, and transposes its sorted
+//! blocks: it is not supported.
+//!
+//! The transcoder reads the source blocks one register of `lanes` values at a time,
+//! with the shift/mask scheme of the `unpack` kernels, and packs the values into the
+//! target registers as the `pack` kernels do, without decoding the blocks in a `u32`
+//! buffer. A row of `lanes` target words is stored as soon as it is complete.
+//!
+//! In place, a target block starts where its first source block did, and both are
+//! walked at the same number of bits per value: the rows of the target are only
+//! stored once the source rows they overwrite have been read.
+//!
+//! Sorted blocks store the deltas between consecutive values, which do not depend on
+//! the layout either. The deltas are transcoded as is, and the initial value of each
+//! target block is obtained by summing them as they go through.
+
+use std::marker::PhantomData;
+
+use crate::private::InOrderRows;
+use crate::{BitPacker, DeltaMode, MAX_BLOCK_LEN};
+
+/// Transcodes streams of blocks compressed by `TSource` into the layout of `TTarget`,
+/// among `BitPacker1x`, `BitPacker4x` and `BitPacker8x`.
+///
+/// Blocks are transcoded by groups of `max(TSource::BLOCK_LEN, TTarget::BLOCK_LEN)`
+/// values. When a target block spans several source blocks, its number of bits is the
+/// largest of theirs. Otherwise, the target blocks keep the number of bits of their
+/// source block.
+///
+/// ```
+/// # #[cfg(feature = "bitpacker8x")]
+/// # fn main() {
+/// use bitpacking::{BitPacker, BitPacker4x, BitPacker8x, DeltaMode, Transcoder};
+///
+/// let values: Vec<u32> = (0..256).map(|i| (i * 7) % 100).collect();
+/// let bitpacker = BitPacker4x::new();
+/// let mut stream = Vec::new();
+/// let mut num_bits = Vec::new();
+/// for block in values.chunks(BitPacker4x::BLOCK_LEN) {
+///     let block_num_bits = bitpacker.num_bits(block);
+///     let mut compressed = vec![0u8; BitPacker4x::compressed_block_size(block_num_bits)];
+///     bitpacker.compress(block, &mut compressed, block_num_bits);
+///     stream.extend_from_slice(&compressed);
+///     num_bits.push(block_num_bits);
+/// }
+///
+/// // Both blocks have the same number of bits: the stream can be transcoded in place.
+/// let transcoder = Transcoder::<BitPacker4x, BitPacker8x>::new();
+/// let mut target_num_bits = [0u8; 1];
+/// let len = transcoder.transcode_in_place(
+///     DeltaMode::NoDelta,
+///     &mut stream,
+///     &num_bits,
+///     &mut target_num_bits,
+///     &mut [],
+/// );
+/// assert_eq!(len, stream.len());
+///
+/// let mut decompressed = vec![0u32; BitPacker8x::BLOCK_LEN];
+/// BitPacker8x::new().decompress(&stream, &mut decompressed, target_num_bits[0]);
+/// assert_eq!(values, decompressed);
+/// # }
+/// # #[cfg(not(feature = "bitpacker8x"))]
+/// # fn main() {}
+/// ```
+pub struct Transcoder<TSource: BitPacker + InOrderRows, TTarget: BitPacker + InOrderRows> {
+    _marker: PhantomData<(TSource, TTarget)>,
+}
+
+impl<TSource: BitPacker + InOrderRows, TTarget: BitPacker + InOrderRows> Default
+    for Transcoder<TSource, TTarget>
+{
+    fn default() -> Self {
+        Transcoder::new()
+    }
+}
+
+/// Maximum number of lanes of a block.
+const MAX_LANES: usize = MAX_BLOCK_LEN / 32;
+
+/// Reads the registers of a compressed block, as the `unpack` kernels do.
+///
+/// The rows of words are only read when the register being decoded reaches them.
+struct RegisterReader {
+    block: *const u8,
+    num_lanes: usize,
+    num_bits: usize,
+    register_id: usize,
+    row: [u32; MAX_LANES],
+    row_id: usize,
+}
+
+impl RegisterReader {
+    /// # Safety
+    ///
+    /// `block` must be valid for reads of a block of `num_lanes * 32` values over
+    /// `num_bits` bits.
+    unsafe fn new(block: *const u8, num_lanes: usize, num_bits: u8) -> RegisterReader {
+        let mut reader = RegisterReader {
+            block,
+            num_lanes,
+            num_bits: num_bits as usize,
+            register_id: 0,
+            row: [0u32; MAX_LANES],
+            row_id: 0,
+        };
+        if num_bits > 0 {
+            reader.load_row();
+        }
+        reader
+    }
+
+    unsafe fn load_row(&mut self) {
+        let words = self.block.cast::<u32>().add(self.row_id * self.num_lanes);
+        for (lane, word) in self.row[..self.num_lanes].iter_mut().enumerate() {
+            *word = u32::from_le(words.add(lane).read_unaligned());
+        }
+        self.row_id += 1;
+    }
+
+    /// Reads the next register into `register`.
+    unsafe fn read(&mut self, register: &mut [u32]) {
+        let num_bits = self.num_bits;
+        let register_id = self.register_id;
+        self.register_id += 1;
+        if num_bits == 0 {
+            register.fill(0);
+            return;
+        }
+        let inner_cursor = (register_id * num_bits) % 32;
+        let inner_capacity = 32 - inner_cursor;
+        for (val, &word) in register.iter_mut().zip(&self.row) {
+            *val = word >> inner_cursor;
+        }
+        // We consumed the current row entirely: we read the next one.
+        if inner_capacity <= num_bits && register_id != 31 {
+            self.load_row();
+            // The register is cut between the two rows.
+            if inner_capacity < num_bits {
+                for (val, &word) in register.iter_mut().zip(&self.row) {
+                    *val |= word << inner_capacity;
+                }
+            }
+        }
+        let mask = ((1u64 << num_bits) - 1) as u32;
+        for val in register.iter_mut() {
+            *val &= mask;
+        }
+    }
+}
+
+/// Packs registers into a compressed block, as the `pack` kernels do.
+///
+/// Each row of words is stored once, when it is complete.
+struct RegisterWriter {
+    block: *mut u8,
+    num_lanes: usize,
+    num_bits: usize,
+    register_id: usize,
+    row: [u32; MAX_LANES],
+    row_id: usize,
+}
+
+impl RegisterWriter {
+    /// # Safety
+    ///
+    /// `block` must be valid for writes of a block of `num_lanes * 32` values over
+    /// `num_bits` bits.
+    unsafe fn new(block: *mut u8, num_lanes: usize, num_bits: u8) -> RegisterWriter {
+        RegisterWriter {
+            block,
+            num_lanes,
+            num_bits: num_bits as usize,
+            register_id: 0,
+            row: [0u32; MAX_LANES],
+            row_id: 0,
+        }
+    }
+
+    unsafe fn store_row(&mut self) {
+        let words = self.block.cast::<u32>().add(self.row_id * self.num_lanes);
+        for (lane, &word) in self.row[..self.num_lanes].iter().enumerate() {
+            words.add(lane).write_unaligned(word.to_le());
+        }
+        self.row_id += 1;
+    }
+
+    /// Packs `register`, whose values fit over the block's number of bits.
+    unsafe fn write(&mut self, register: &[u32]) {
+        let num_bits = self.num_bits;
+        let register_id = self.register_id;
+        self.register_id += 1;
+        if num_bits == 0 {
+            return;
+        }
+        let inner_cursor = (register_id * num_bits) % 32;
+        let remaining = 32 - inner_cursor;
+        for (word, &val) in self.row.iter_mut().zip(register) {
+            *word = if inner_cursor > 0 {
+                *word | (val << inner_cursor)
+            } else {
+                val
+            };
+        }
+        if remaining <= num_bits {
+            self.store_row();
+            if remaining < num_bits {
+                for (word, &val) in self.row.iter_mut().zip(register) {
+                    *word = val >> remaining;
+                }
+            }
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.register_id == 32
+    }
+}
+
+impl<TSource: BitPacker + InOrderRows, TTarget: BitPacker + InOrderRows>
+    Transcoder<TSource, TTarget>
+{
+    const GROUP_LEN: usize = if TSource::BLOCK_LEN > TTarget::BLOCK_LEN {
+        TSource::BLOCK_LEN
+    } else {
+        TTarget::BLOCK_LEN
+    };
+    const NUM_SOURCE_BLOCKS: usize = Self::GROUP_LEN / TSource::BLOCK_LEN;
+    const NUM_TARGET_BLOCKS: usize = Self::GROUP_LEN / TTarget::BLOCK_LEN;
+
+    /// Creates a new transcoder.
+    pub fn new() -> Self {
+        Transcoder {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of target blocks obtained from `num_source_blocks` blocks.
+    #[must_use]
+    pub fn num_target_blocks(num_source_blocks: usize) -> usize {
+        num_source_blocks * TSource::BLOCK_LEN / TTarget::BLOCK_LEN
+    }
+
+    /// Transcodes the blocks of `source`, whose numbers of bits are `source_num_bits`,
+    /// into `target`.
+    ///
+    /// The numbers of bits of the target blocks are written in `target_num_bits`. For
+    /// sorted modes, the initial values to pass to the target bitpacker's
+    /// `decompress_sorted` or `decompress_strictly_sorted` are written in
+    /// `target_initials`, which is left untouched for `DeltaMode::NoDelta`. An initial
+    /// value of `None` is reported as `u32::MAX`, which is equivalent.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the number of values is not a multiple of the largest `BLOCK_LEN`.
+    /// - Panics if `source` is too short.
+    /// - Panics if `target`, `target_num_bits` or `target_initials` are too short.
+    pub fn transcode(
+        &self,
+        mode: DeltaMode,
+        source: &[u8],
+        source_num_bits: &[u8],
+        target: &mut [u8],
+        target_num_bits: &mut [u8],
+        target_initials: &mut [u32],
+    ) -> usize {
+        let mut state = TranscodeState::new(mode);
+        let mut source_offset = 0;
+        let mut target_offset = 0;
+        for (group_id, group_num_bits) in self.groups(source_num_bits).enumerate() {
+            let source_len: usize = group_num_bits
+                .iter()
+                .map(|&num_bits| TSource::compressed_block_size(num_bits))
+                .sum();
+            assert!(
+                source.len() >= source_offset + source_len,
+                "Compressed array seems too small. ({} < {}) ",
+                source.len(),
+                source_offset + source_len
+            );
+            target_offset += unsafe {
+                self.transcode_group(
+                    &mut state,
+                    group_id,
+                    source[source_offset..].as_ptr(),
+                    group_num_bits,
+                    target[target_offset..].as_mut_ptr(),
+                    target.len() - target_offset,
+                    target_num_bits,
+                    target_initials,
+                )
+            };
+            source_offset += source_len;
+        }
+        target_offset
+    }
+
+    /// Transcodes the blocks of `data` in place.
+    ///
+    /// This requires the source blocks spanned by a target block to share their number
+    /// of bits, so that the transcoded stream has the same size. This is always the case
+    /// when `TTarget::BLOCK_LEN <= TSource::BLOCK_LEN`.
+    ///
+    /// See `.transcode(..)` for the other arguments.
+    ///
+    /// Returns the amount of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if source blocks spanned by a target block have different numbers of bits.
+    /// - Same as `.transcode(..)`.
+    pub fn transcode_in_place(
+        &self,
+        mode: DeltaMode,
+        data: &mut [u8],
+        source_num_bits: &[u8],
+        target_num_bits: &mut [u8],
+        target_initials: &mut [u32],
+    ) -> usize {
+        let mut state = TranscodeState::new(mode);
+        let mut offset = 0;
+        for (group_id, group_num_bits) in self.groups(source_num_bits).enumerate() {
+            let blocks_per_target = Self::NUM_SOURCE_BLOCKS / Self::NUM_TARGET_BLOCKS;
+            if blocks_per_target > 1 {
+                for chunk in group_num_bits.chunks(blocks_per_target) {
+                    assert!(
+                        chunk.iter().all(|&num_bits| num_bits == chunk[0]),
+                        "Blocks merged in place must have the same number of bits. Got {:?}",
+                        chunk
+                    );
+                }
+            }
+            let source_len: usize = group_num_bits
+                .iter()
+                .map(|&num_bits| TSource::compressed_block_size(num_bits))
+                .sum();
+            assert!(
+                data.len() >= offset + source_len,
+                "Compressed array seems too small. ({} < {}) ",
+                data.len(),
+                offset + source_len
+            );
+            // The target blocks overwrite the source blocks as they are read.
+            let len = data.len() - offset;
+            let group = data[offset..].as_mut_ptr();
+            offset += unsafe {
+                self.transcode_group(
+                    &mut state,
+                    group_id,
+                    group,
+                    group_num_bits,
+                    group,
+                    len,
+                    target_num_bits,
+                    target_initials,
+                )
+            };
+        }
+        offset
+    }
+
+    fn groups<'a>(&self, source_num_bits: &'a [u8]) -> std::slice::ChunksExact<'a, u8> {
+        assert_eq!(
+            source_num_bits.len() % Self::NUM_SOURCE_BLOCKS,
+            0,
+            "The number of source blocks must be a multiple of {}",
+            Self::NUM_SOURCE_BLOCKS
+        );
+        source_num_bits.chunks_exact(Self::NUM_SOURCE_BLOCKS)
+    }
+
+    /// Transcodes a group of source blocks, and returns the amount of bytes written.
+    ///
+    /// # Safety
+    ///
+    /// `source` must be valid for reads of the source blocks, and `target` for writes
+    /// of `target_len` bytes. If they overlap, `target` must be `source`, and each
+    /// target block must have the number of bits of the source blocks it spans.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn transcode_group(
+        &self,
+        state: &mut TranscodeState,
+        group_id: usize,
+        source: *const u8,
+        source_num_bits: &[u8],
+        target: *mut u8,
+        target_len: usize,
+        target_num_bits: &mut [u8],
+        target_initials: &mut [u32],
+    ) -> usize {
+        let source_lanes = TSource::BLOCK_LEN / 32;
+        let target_lanes = TTarget::BLOCK_LEN / 32;
+
+        let first_target_block = group_id * Self::NUM_TARGET_BLOCKS;
+        let mut target_blocks = [(0usize, 0u8); MAX_LANES];
+        let mut target_offset = 0;
+        for (i, block) in target_blocks[..Self::NUM_TARGET_BLOCKS]
+            .iter_mut()
+            .enumerate()
+        {
+            let first_value = i * TTarget::BLOCK_LEN;
+            let spanned = (first_value / TSource::BLOCK_LEN)
+                ..(first_value + TTarget::BLOCK_LEN).div_ceil(TSource::BLOCK_LEN);
+            let num_bits = source_num_bits[spanned].iter().copied().max().unwrap_or(0);
+            target_num_bits[first_target_block + i] = num_bits;
+            *block = (target_offset, num_bits);
+            target_offset += TTarget::compressed_block_size(num_bits);
+        }
+        assert!(
+            target_len >= target_offset,
+            "Output array too small. {} < {}",
+            target_len,
+            target_offset
+        );
+
+        let mut writers = target_blocks[..Self::NUM_TARGET_BLOCKS]
+            .iter()
+            .enumerate()
+            .map(|(i, &(offset, num_bits))| {
+                (
+                    first_target_block + i,
+                    RegisterWriter::new(target.add(offset), target_lanes, num_bits),
+                )
+            });
+        let (mut target_block_id, mut writer) = writers.next().unwrap();
+        let mut source_register = [0u32; MAX_LANES];
+        let mut target_register = [0u32; MAX_LANES];
+        let mut target_register_len = 0;
+        let mut source_offset = 0;
+        for &num_bits in source_num_bits {
+            let mut reader = RegisterReader::new(source.add(source_offset), source_lanes, num_bits);
+            source_offset += TSource::compressed_block_size(num_bits);
+            for _ in 0..32 {
+                reader.read(&mut source_register[..source_lanes]);
+                // A source register fills one or several target registers, or a part of
+                // one.
+                for values in source_register[..source_lanes].chunks(target_lanes) {
+                    target_register[target_register_len..target_register_len + values.len()]
+                        .copy_from_slice(values);
+                    target_register_len += values.len();
+                    if target_register_len < target_lanes {
+                        continue;
+                    }
+                    target_register_len = 0;
+                    if writer.is_complete() {
+                        (target_block_id, writer) = writers.next().unwrap();
+                    }
+                    if writer.register_id == 0 {
+                        if let Some(initial) = state.current() {
+                            target_initials[target_block_id] = initial;
+                        }
+                    }
+                    state.advance(&target_register[..target_lanes]);
+                    writer.write(&target_register[..target_lanes]);
+                }
+            }
+        }
+        target_offset
+    }
+}
+
+/// Tracks the current value of sorted streams, to compute the initial values of the
+/// target blocks.
+enum TranscodeState {
+    NoDelta,
+    Sorted { current: u32, strict: bool },
+}
+
+impl TranscodeState {
+    fn new(mode: DeltaMode) -> TranscodeState {
+        match mode {
+            DeltaMode::NoDelta => TranscodeState::NoDelta,
+            DeltaMode::Sorted { initial } => TranscodeState::Sorted {
+                current: initial,
+                strict: false,
+            },
+            DeltaMode::StrictlySorted { initial } => TranscodeState::Sorted {
+                current: initial.unwrap_or(u32::MAX),
+                strict: true,
+            },
+        }
+    }
+
+    fn current(&self) -> Option<u32> {
+        match *self {
+            TranscodeState::NoDelta => None,
+            TranscodeState::Sorted { current, .. } => Some(current),
+        }
+    }
+
+    fn advance(&mut self, deltas: &[u32]) {
+        if let TranscodeState::Sorted { current, strict } = self {
+            for &delta in deltas {
+                *current = current.wrapping_add(delta).wrapping_add(*strict as u32);
+            }
+        }
+    }
+}
+
+// `BitPackerFastLanes` alone has nothing to transcode.
+#[cfg(all(
+    test,
+    any(
+        feature = "bitpacker1x",
+        feature = "bitpacker4x",
+        feature = "bitpacker8x"
+    )
+))]
+mod tests {
+    use super::{DeltaMode, Transcoder};
+    use crate::private::InOrderRows;
+    use crate::tests::generate_array;
+    use crate::BitPacker;
+    #[cfg(feature = "bitpacker1x")]
+    use crate::BitPacker1x;
+    #[cfg(feature = "bitpacker4x")]
+    use crate::BitPacker4x;
+    #[cfg(feature = "bitpacker8x")]
+    use crate::BitPacker8x;
+
+    const NUM_VALUES: usize = 2 * 1024;
+
+    /// Generates values whose number of bits varies from block to block.
+    fn generate_values(block_len: usize) -> Vec<u32> {
+        let mut values = Vec::new();
+        for i in 0..NUM_VALUES.div_ceil(block_len) {
+            let num_bits = [3u8, 3, 17, 0, 32, 9, 9, 1][i % 8];
+            values.extend(generate_array(block_len, num_bits));
+        }
+        values.truncate(NUM_VALUES);
+        values
+    }
+
+    /// Compresses `values`, returning the stream, the numbers of bits and the initial
+    /// values of the blocks. The numbers of bits are computed unless `forced_num_bits`
+    /// is given.
+    fn compress<T: BitPacker>(
+        mode: DeltaMode,
+        values: &[u32],
+        forced_num_bits: Option<&[u8]>,
+    ) -> (Vec<u8>, Vec<u8>, Vec<u32>) {
+        let bitpacker = T::new();
+        let mut stream = Vec::new();
+        let mut num_bits = Vec::new();
+        let mut initials = Vec::new();
+        let mut compressed = vec![0u8; 4 * T::BLOCK_LEN];
+        for (i, block) in values.chunks(T::BLOCK_LEN).enumerate() {
+            let previous = i.checked_sub(1).map(|i| values[(i + 1) * T::BLOCK_LEN - 1]);
+            let (block_num_bits, len) = match mode {
+                DeltaMode::NoDelta => {
+                    let block_num_bits = forced_num_bits
+                        .map_or_else(|| bitpacker.num_bits(block), |num_bits| num_bits[i]);
+                    (
+                        block_num_bits,
+                        bitpacker.compress(block, &mut compressed, block_num_bits),
+                    )
+                }
+                DeltaMode::Sorted { initial } => {
+                    let initial = previous.unwrap_or(initial);
+                    initials.push(initial);
+                    let block_num_bits = forced_num_bits.map_or_else(
+                        || bitpacker.num_bits_sorted(initial, block),
+                        |num_bits| num_bits[i],
+                    );
+                    let len =
+                        bitpacker.compress_sorted(initial, block, &mut compressed, block_num_bits);
+                    (block_num_bits, len)
+                }
+                DeltaMode::StrictlySorted { initial } => {
+                    let initial = previous.or(initial);
+                    initials.push(initial.unwrap_or(u32::MAX));
+                    let block_num_bits = forced_num_bits.map_or_else(
+                        || bitpacker.num_bits_strictly_sorted(initial, block),
+                        |num_bits| num_bits[i],
+                    );
+                    let len = bitpacker.compress_strictly_sorted(
+                        initial,
+                        block,
+                        &mut compressed,
+                        block_num_bits,
+                    );
+                    (block_num_bits, len)
+                }
+            };
+            stream.extend_from_slice(&compressed[..len]);
+            num_bits.push(block_num_bits);
+        }
+        (stream, num_bits, initials)
+    }
+
+    fn decompress<T: BitPacker>(
+        mode: DeltaMode,
+        stream: &[u8],
+        num_bits: &[u8],
+        initials: &[u32],
+    ) -> Vec<u32> {
+        let bitpacker = T::new();
+        let mut values = vec![0u32; num_bits.len() * T::BLOCK_LEN];
+        let mut offset = 0;
+        for (i, (block, &block_num_bits)) in
+            values.chunks_mut(T::BLOCK_LEN).zip(num_bits).enumerate()
+        {
+            offset += match mode {
+                DeltaMode::NoDelta => {
+                    bitpacker.decompress(&stream[offset..], block, block_num_bits)
+                }
+                DeltaMode::Sorted { .. } => bitpacker.decompress_sorted(
+                    initials[i],
+                    &stream[offset..],
+                    block,
+                    block_num_bits,
+                ),
+                DeltaMode::StrictlySorted { .. } => bitpacker.decompress_strictly_sorted(
+                    Some(initials[i]),
+                    &stream[offset..],
+                    block,
+                    block_num_bits,
+                ),
+            };
+        }
+        values
+    }
+
+    fn test_util_transcode<TSource: BitPacker + InOrderRows, TTarget: BitPacker + InOrderRows>(
+        mode: DeltaMode,
+    ) {
+        let mut values = generate_values(TSource::BLOCK_LEN.min(TTarget::BLOCK_LEN));
+        match mode {
+            DeltaMode::NoDelta => {}
+            DeltaMode::Sorted { initial } => {
+                let mut cumul = initial;
+                for val in values.iter_mut() {
+                    cumul = cumul.wrapping_add(*val >> 8);
+                    *val = cumul;
+                }
+            }
+            DeltaMode::StrictlySorted { initial } => {
+                let mut cumul = initial.unwrap_or(u32::MAX);
+                for val in values.iter_mut() {
+                    cumul = cumul.wrapping_add(*val >> 8).wrapping_add(1);
+                    *val = cumul;
+                }
+            }
+        }
+        let (source, source_num_bits, _) = compress::<TSource>(mode, &values, None);
+
+        let transcoder = Transcoder::<TSource, TTarget>::new();
+        let num_target_blocks =
+            Transcoder::<TSource, TTarget>::num_target_blocks(source_num_bits.len());
+        let mut target = vec![0xffu8; 4 * NUM_VALUES];
+        let mut target_num_bits = vec![0u8; num_target_blocks];
+        let mut target_initials = vec![0u32; num_target_blocks];
+        let target_len = transcoder.transcode(
+            mode,
+            &source,
+            &source_num_bits,
+            &mut target,
+            &mut target_num_bits,
+            &mut target_initials,
+        );
+        target.truncate(target_len);
+
+        let decompressed = decompress::<TTarget>(mode, &target, &target_num_bits, &target_initials);
+        assert_eq!(decompressed, values);
+
+        // The stream is identical to a direct compression with the same numbers of bits.
+        let (expected, _, expected_initials) =
+            compress::<TTarget>(mode, &values, Some(&target_num_bits));
+        assert_eq!(target, expected);
+        if mode != DeltaMode::NoDelta {
+            assert_eq!(target_initials, expected_initials);
+        }
+    }
+
+    fn test_util_transcode_in_place<
+        TSource: BitPacker + InOrderRows,
+        TTarget: BitPacker + InOrderRows,
+    >() {
+        // Merged blocks must share their number of bits.
+        let values = if TTarget::BLOCK_LEN > TSource::BLOCK_LEN {
+            generate_array(NUM_VALUES, 13)
+        } else {
+            generate_values(TSource::BLOCK_LEN)
+        };
+        let mode = DeltaMode::NoDelta;
+        let (mut data, source_num_bits, _) = compress::<TSource>(mode, &values, None);
+        let num_target_blocks =
+            Transcoder::<TSource, TTarget>::num_target_blocks(source_num_bits.len());
+        let mut target_num_bits = vec![0u8; num_target_blocks];
+        let len = Transcoder::<TSource, TTarget>::new().transcode_in_place(
+            mode,
+            &mut data,
+            &source_num_bits,
+            &mut target_num_bits,
+            &mut [],
+        );
+        assert_eq!(len, data.len());
+        let decompressed = decompress::<TTarget>(mode, &data, &target_num_bits, &[]);
+        assert_eq!(decompressed, values);
+    }
+
+    fn test_suite_transcode<TSource: BitPacker + InOrderRows, TTarget: BitPacker + InOrderRows>() {
+        test_util_transcode::<TSource, TTarget>(DeltaMode::NoDelta);
+        test_util_transcode::<TSource, TTarget>(DeltaMode::Sorted { initial: 0 });
+        test_util_transcode::<TSource, TTarget>(DeltaMode::Sorted { initial: 1_000 });
+        test_util_transcode::<TSource, TTarget>(DeltaMode::StrictlySorted { initial: None });
+        test_util_transcode::<TSource, TTarget>(DeltaMode::StrictlySorted { initial: Some(7) });
+        test_util_transcode_in_place::<TSource, TTarget>();
+    }
+
+    #[test]
+    fn test_transcode_from_1x() {
+        #[cfg(feature = "bitpacker1x")]
+        test_suite_transcode::<BitPacker1x, BitPacker1x>();
+        #[cfg(all(feature = "bitpacker1x", feature = "bitpacker4x"))]
+        test_suite_transcode::<BitPacker1x, BitPacker4x>();
+        #[cfg(all(feature = "bitpacker1x", feature = "bitpacker8x"))]
+        test_suite_transcode::<BitPacker1x, BitPacker8x>();
+    }
+
+    #[test]
+    fn test_transcode_from_4x() {
+        #[cfg(feature = "bitpacker4x")]
+        test_suite_transcode::<BitPacker4x, BitPacker4x>();
+        #[cfg(all(feature = "bitpacker1x", feature = "bitpacker4x"))]
+        test_suite_transcode::<BitPacker4x, BitPacker1x>();
+        #[cfg(all(feature = "bitpacker4x", feature = "bitpacker8x"))]
+        test_suite_transcode::<BitPacker4x, BitPacker8x>();
+    }
+
+    #[test]
+    fn test_transcode_from_8x() {
+        #[cfg(feature = "bitpacker8x")]
+        test_suite_transcode::<BitPacker8x, BitPacker8x>();
+        #[cfg(all(feature = "bitpacker1x", feature = "bitpacker8x"))]
+        test_suite_transcode::<BitPacker8x, BitPacker1x>();
+        #[cfg(all(feature = "bitpacker4x", feature = "bitpacker8x"))]
+        test_suite_transcode::<BitPacker8x, BitPacker4x>();
+    }
+
+    #[cfg(all(feature = "bitpacker4x", feature = "bitpacker8x"))]
+    #[test]
+    #[should_panic(expected = "Blocks merged in place must have the same number of bits")]
+    fn test_transcode_in_place_different_num_bits() {
+        let values = generate_values(BitPacker4x::BLOCK_LEN);
+        let (mut data, num_bits, _) = compress::<BitPacker4x>(DeltaMode::NoDelta, &values, None);
+        Transcoder::<BitPacker4x, BitPacker8x>::new().transcode_in_place(
+            DeltaMode::NoDelta,
+            &mut data,
+            &num_bits,
+            &mut [0u8; NUM_VALUES / 256],
+            &mut [],
+        );
+    }
+}